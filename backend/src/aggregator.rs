@@ -1,21 +1,630 @@
-use crate::db::Database;
+use crate::cache::{CacheMode, ResponseCache};
+use crate::config::IngestConfig;
+use crate::db::{
+    self, ClimateNormal, DailyObservation, Database, HourlyObservation, IngestRun,
+    SeasonCompleteness, Station, StationMetadata,
+};
+use crate::ghcn::{self, GhcnStation};
+use crate::limiter::{RequestLimiter, RequestLimits};
+use crate::source::{MonthRecords, SourceKind, SourceStation, StationPage, WeatherSource};
 use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, Utc};
+use futures::stream::{self, BoxStream, FuturesUnordered, StreamExt};
 use indicatif::{ProgressBar, ProgressStyle};
-use reqwest::Client;
+use rand::Rng;
+use reqwest::{Client, StatusCode};
+use rusqlite::Connection;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Base URL of the ECCC OGC API that serves the climate station catalogue
+pub const DEFAULT_STATIONS_BASE_URL: &str = "https://api.weather.gc.ca";
+
+/// Base URL of the ECCC climate site that serves bulk CSV downloads
+pub const DEFAULT_CLIMATE_BASE_URL: &str = "https://climate.weather.gc.ca";
+
+/// User-Agent sent with every ingest request, so ECCC can identify the client
+pub const DEFAULT_USER_AGENT: &str = concat!(
+    "TireSwap/",
+    env!("CARGO_PKG_VERSION"),
+    " (tireswap backend; climate data ingest)"
+);
+
+/// Where daily observations are downloaded from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DailySource {
+    /// One `bulk_data_e.html` CSV request per station-month
+    #[default]
+    BulkCsv,
+    /// The `climate-daily` OGC API collection, one paged JSON query per run of missing months
+    ClimateDaily,
+}
+
+impl std::str::FromStr for DailySource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" | "bulk-csv" => Ok(DailySource::BulkCsv),
+            "ogc" | "climate-daily" => Ok(DailySource::ClimateDaily),
+            other => Err(format!(
+                "unknown daily source '{}' (expected 'csv' or 'ogc')",
+                other
+            )),
+        }
+    }
+}
+
+impl DailySource {
+    /// Name accepted by `from_str` and recorded in `ingest_runs`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DailySource::BulkCsv => "csv",
+            DailySource::ClimateDaily => "ogc",
+        }
+    }
+}
+
+/// Geographic bounding box in degrees, written `minLon,minLat,maxLon,maxLat` as in
+/// the OGC API `bbox` parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+impl std::str::FromStr for BoundingBox {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| format!("invalid bounding box '{}'", s))?;
+        let [min_lon, min_lat, max_lon, max_lat] = values[..] else {
+            return Err(format!(
+                "bounding box '{}' must have four values: minLon,minLat,maxLon,maxLat",
+                s
+            ));
+        };
+        if min_lon > max_lon || min_lat > max_lat || min_lat < -90.0 || max_lat > 90.0 {
+            return Err(format!("bounding box '{}' is out of order or out of range", s));
+        }
+        Ok(BoundingBox {
+            min_lon,
+            min_lat,
+            max_lon,
+            max_lat,
+        })
+    }
+}
+
+impl BoundingBox {
+    /// Whether a point lies inside the box, edges included
+    pub fn contains(&self, lon: f64, lat: f64) -> bool {
+        (self.min_lon..=self.max_lon).contains(&lon) && (self.min_lat..=self.max_lat).contains(&lat)
+    }
+}
+
+/// Part of the station catalogue an update is limited to
+///
+/// Each non-empty criterion must match; the default filter keeps every station.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StationFilter {
+    /// Station IDs (`STN_ID`)
+    pub station_ids: Vec<i64>,
+    /// Province or territory codes, e.g. "ON"
+    pub provinces: Vec<String>,
+    pub bbox: Option<BoundingBox>,
+}
+
+impl StationFilter {
+    /// Whether the filter keeps every station
+    pub fn is_empty(&self) -> bool {
+        self.station_ids.is_empty() && self.provinces.is_empty() && self.bbox.is_none()
+    }
+
+    /// Whether a station passes the filter
+    ///
+    /// # Arguments
+    /// * `id` - Station ID
+    /// * `province` - Province or territory code; a station without one fails a province filter
+    /// * `lon` - Longitude in degrees
+    /// * `lat` - Latitude in degrees
+    pub fn matches(&self, id: i64, province: Option<&str>, lon: f64, lat: f64) -> bool {
+        let id_ok = self.station_ids.is_empty() || self.station_ids.contains(&id);
+        let province_ok = self.provinces.is_empty()
+            || province.is_some_and(|p| self.provinces.iter().any(|f| f.eq_ignore_ascii_case(p)));
+        let bbox_ok = self.bbox.is_none_or(|bbox| bbox.contains(lon, lat));
+        id_ok && province_ok && bbox_ok
+    }
+
+    /// Catalogue query parameters that let the server narrow its answer
+    ///
+    /// Lists of several IDs or provinces cannot be expressed as one OGC API
+    /// property filter, so those are only applied by `matches`.
+    fn catalogue_query(&self) -> Vec<(&'static str, String)> {
+        let mut query = Vec::new();
+        if let [id] = self.station_ids[..] {
+            query.push(("STN_ID", id.to_string()));
+        }
+        if let [province] = &self.provinces[..] {
+            query.push(("PROV_STATE_TERR_CODE", province.to_ascii_uppercase()));
+        }
+        if let Some(bbox) = self.bbox {
+            query.push((
+                "bbox",
+                format!("{},{},{},{}", bbox.min_lon, bbox.min_lat, bbox.max_lon, bbox.max_lat),
+            ));
+        }
+        query
+    }
+}
+
+/// What started an ingest run, recorded in `ingest_runs`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunTrigger {
+    /// `--update-db`
+    Cli,
+    /// The `--serve` refresh schedule
+    Schedule,
+}
+
+impl RunTrigger {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunTrigger::Cli => "cli",
+            RunTrigger::Schedule => "schedule",
+        }
+    }
+}
+
+/// Error messages kept per ingest run; the rest are only counted
+const MAX_RECORDED_ERRORS: usize = 50;
+
+/// `timeframe` value of a bulk CSV download with hourly data
+const BULK_TIMEFRAME_HOURLY: &str = "1";
+
+/// `timeframe` value of a bulk CSV download with daily data
+const BULK_TIMEFRAME_DAILY: &str = "2";
+
+/// Checkpoint phase of a station's daily data
+const PHASE_DAILY: &str = "daily";
+
+/// Checkpoint phase of a station's hourly data
+const PHASE_HOURLY: &str = "hourly";
+
+/// Checkpoint phase of a station's climate normals
+const PHASE_NORMALS: &str = "normals";
+
+/// `NORMAL_ID` of the mean daily temperature element in the `climate-normals` collection
+const NORMAL_ID_MEAN_TEMPERATURE: &str = "1";
+
+/// Bounded exponential backoff for upstream requests
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts per request, including the first one
+    pub max_attempts: u32,
+    /// Delay before the first retry; doubled on every further attempt
+    pub base_delay: std::time::Duration,
+    /// Upper bound for a single delay, including any `Retry-After` hint
+    pub max_delay: std::time::Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: std::time::Duration::from_millis(500),
+            max_delay: std::time::Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Backoff before retry number `attempt` (1-based), with jitter in `[delay / 2, delay]`
+    fn backoff(&self, attempt: u32) -> std::time::Duration {
+        let exponent = attempt.saturating_sub(1).min(16);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << exponent)
+            .min(self.max_delay);
+        let millis = delay.as_millis() as u64;
+        let jittered = rand::rng().random_range(millis / 2..=millis);
+        std::time::Duration::from_millis(jittered)
+    }
+}
+
+/// Upstream endpoints and fetch behaviour used by the aggregator
+#[derive(Debug, Clone)]
+pub struct AggregatorOptions {
+    /// Base URL of the OGC API collections (`climate-stations`, `climate-daily` and
+    /// `climate-normals`)
+    pub stations_base_url: String,
+    /// Base URL for `/climate_data/bulk_data_e.html`
+    pub climate_base_url: String,
+    /// Retry behaviour for individual downloads
+    pub retry: RetryPolicy,
+    /// Concurrency and rate limits shared by every request of the aggregator
+    pub limits: RequestLimits,
+    /// User-Agent header of upstream requests
+    pub user_agent: String,
+    /// Stations with more failed months than this are marked incomplete
+    pub max_failed_months: usize,
+    /// Number of catalogue features requested per page
+    pub stations_page_size: usize,
+    /// Provider of stations and daily observations
+    pub source: SourceKind,
+    /// Where ECCC daily observations are downloaded from
+    pub daily_source: DailySource,
+    /// Number of `climate-daily` features requested per page
+    pub daily_page_size: usize,
+    /// Also download the climate normals of every station in `update_all`
+    pub normals: bool,
+    /// History window and station eligibility rules
+    pub ingest: IngestConfig,
+    /// Stations to fetch and update; the rest of the catalogue is left alone
+    pub filter: StationFilter,
+    /// Record upstream responses to, or replay them from, a cache directory
+    pub http_cache: Option<CacheMode>,
+    /// Time (UTC) the run treats as now, e.g. the time a replayed cache was recorded;
+    /// `None` for the time the `Aggregator` is created
+    pub as_of: Option<NaiveDateTime>,
+}
+
+impl Default for AggregatorOptions {
+    fn default() -> Self {
+        AggregatorOptions {
+            stations_base_url: DEFAULT_STATIONS_BASE_URL.to_string(),
+            climate_base_url: DEFAULT_CLIMATE_BASE_URL.to_string(),
+            retry: RetryPolicy::default(),
+            limits: RequestLimits::default(),
+            user_agent: DEFAULT_USER_AGENT.to_string(),
+            max_failed_months: 3,
+            stations_page_size: 500,
+            source: SourceKind::default(),
+            daily_source: DailySource::default(),
+            daily_page_size: 1000,
+            normals: false,
+            ingest: IngestConfig::default(),
+            filter: StationFilter::default(),
+            http_cache: None,
+            as_of: None,
+        }
+    }
+}
+
+/// Outcome of paging through the station catalogue
+#[derive(Debug, Clone, Default)]
+pub struct StationFetchReport {
+    /// `numberMatched` reported by the API, if any
+    pub number_matched: Option<u64>,
+    /// Pages requested
+    pub pages: usize,
+    /// Stations received with an ID, name and coordinates
+    pub total: usize,
+    pub inserted: usize,
+    pub inactive: usize,
+    pub insufficient_data: usize,
+    /// Features missing an ID, name or coordinates
+    pub skipped: usize,
+    /// Stations outside the `StationFilter`, not counted in `total`
+    pub excluded: usize,
+    /// IDs of the stations that passed the filter and eligibility rules and were stored
+    pub stored_ids: std::collections::BTreeSet<i64>,
+}
+
+impl StationFetchReport {
+    /// Features received from the catalogue, including skipped and excluded ones;
+    /// comparable to `number_matched`
+    pub fn received(&self) -> usize {
+        self.total + self.excluded + self.skipped
+    }
+}
+
+/// A month whose download failed after all retries
+#[derive(Debug, Clone)]
+pub struct FailedMonth {
+    pub year: i32,
+    pub month: u32,
+    pub error: String,
+}
+
+/// Outcome of fetching climate data for one station
+#[derive(Debug, Clone)]
+pub struct ClimateFetchReport {
+    pub station_id: i64,
+    /// Months that were missing or still open and had to be downloaded
+    pub months_requested: usize,
+    /// Months downloaded and stored successfully
+    pub months_fetched: usize,
+    pub failed_months: Vec<FailedMonth>,
+    /// False when too many months failed for the switch dates to be trusted
+    pub complete: bool,
+}
+
+/// Progress bar used for the per-station loops of `update_all`
+fn update_progress_bar(len: usize) -> ProgressBar {
+    let pb = ProgressBar::new(len as u64);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
+            .unwrap()
+            .progress_chars("##-"),
+    );
+    pb
+}
+
+/// Outcome of a full update run (`update_all`)
+#[derive(Debug, Clone, Default)]
+pub struct UpdateReport {
+    /// Row of this run in `ingest_runs`; `None` if it could not be recorded
+    pub run_id: Option<i64>,
+    /// Catalogue counts; `None` if the catalogue fetch failed
+    pub stations: Option<StationFetchReport>,
+    /// Stations whose daily data was fetched without an error
+    pub stations_updated: usize,
+    /// Stations whose daily fetch returned an error
+    pub stations_failed: usize,
+    /// Stations marked incomplete because too many months failed
+    pub incomplete_stations: usize,
+    /// Whether this run continued an interrupted one (`run_id` is the interrupted run)
+    pub resumed: bool,
+    /// Stations skipped because the interrupted run had already finished their daily data
+    pub stations_resumed: usize,
+    /// Daily months downloaded and stored
+    pub months_fetched: usize,
+    /// Daily months that failed after all retries
+    pub months_failed: usize,
+    /// Hourly months that failed after all retries (zero when hourly data was not requested)
+    pub hourly_months_failed: usize,
+    /// Stations whose hourly fetch returned an error
+    pub hourly_stations_failed: usize,
+    /// Stations whose climate normals were stored (zero when normals were not requested)
+    pub normals_stored: usize,
+    /// Number of errors, including failed months
+    pub error_count: usize,
+    /// The first `MAX_RECORDED_ERRORS` error messages
+    pub errors: Vec<String>,
+}
+
+impl UpdateReport {
+    fn push_error(&mut self, error: String) {
+        self.error_count += 1;
+        if self.errors.len() < MAX_RECORDED_ERRORS {
+            self.errors.push(error);
+        }
+    }
+
+    /// "failed" without a station catalogue, "partial" if any station, month or other
+    /// step failed in any phase, else "ok"
+    pub fn status(&self) -> &'static str {
+        if self.stations.is_none() {
+            "failed"
+        } else if self.error_count > 0
+            || self.incomplete_stations > 0
+            || self.stations_failed > 0
+            || self.hourly_stations_failed > 0
+        {
+            "partial"
+        } else {
+            "ok"
+        }
+    }
+}
+
+/// HTTP client shared by every upstream request of an aggregator and its source:
+/// one connection pool, retries, limits and response cache
+#[derive(Debug)]
+pub struct UpstreamClient {
+    client: Client,
+    retry: RetryPolicy,
+    /// Gate every upstream request passes through, built from `AggregatorOptions::limits`
+    pub limiter: Arc<RequestLimiter>,
+    http_cache: Option<CacheMode>,
+}
+
+impl UpstreamClient {
+    /// Create a client with the user agent, retry policy, limits and cache of `options`
+    pub fn new(options: &AggregatorOptions) -> Self {
+        let client = reqwest::Client::builder()
+            .user_agent(options.user_agent.clone())
+            .build()
+            .expect("Failed to build HTTP client");
+        UpstreamClient {
+            client,
+            retry: options.retry.clone(),
+            limiter: Arc::new(RequestLimiter::new(&options.limits)),
+            http_cache: options.http_cache.clone(),
+        }
+    }
+
+    /// GET a text body, retrying network errors, HTTP 429 and 5xx responses
+    /// with exponential backoff and jitter
+    ///
+    /// Each attempt waits for the shared `limiter`; the permit is released before
+    /// backing off, so a retry does not hold up other requests. With `http_cache`,
+    /// successful bodies are recorded, or all bodies are replayed without any request.
+    ///
+    /// # Arguments
+    /// * `url` - Request URL
+    /// * `query` - Query parameters appended to `url`
+    ///
+    /// # Returns
+    /// * `Result<String, String>` - Response body, or the last error
+    pub async fn get_text_with_retry(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+    ) -> Result<String, String> {
+        self.get_checked_text_with_retry(url, query, |_| Ok(())).await
+    }
+
+    /// Like `get_text_with_retry`, but a successful response whose body fails `check`
+    /// (e.g. an HTML error page served with status 200) is retried like a 5xx
+    /// response and never recorded in the cache
+    ///
+    /// # Arguments
+    /// * `url` - Request URL
+    /// * `query` - Query parameters appended to `url`
+    /// * `check` - Returns why a body is unusable, if it is
+    ///
+    /// # Returns
+    /// * `Result<String, String>` - Response body, or the last error
+    pub async fn get_checked_text_with_retry(
+        &self,
+        url: &str,
+        query: &[(&str, &str)],
+        check: impl Fn(&str) -> Result<(), String>,
+    ) -> Result<String, String> {
+        let cache_key = match &self.http_cache {
+            Some(_) => Some(ResponseCache::request_key(url, query)?),
+            None => None,
+        };
+        if let (Some(CacheMode::Replay(dir)), Some(key)) = (&self.http_cache, &cache_key) {
+            let body = ResponseCache::new(dir)
+                .load(key)?
+                .ok_or_else(|| format!("not in the replay cache: {}", key))?;
+            check(&body)?;
+            return Ok(body);
+        }
+
+        let policy = &self.retry;
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let mut retry_after = None;
+
+            let permit = self.limiter.acquire().await;
+            let error = match self.client.get(url).query(query).send().await {
+                Ok(response) if response.status().is_success() => match response.text().await {
+                    Ok(text) => match check(&text) {
+                        Ok(()) => {
+                            if let (Some(CacheMode::Record(dir)), Some(key)) =
+                                (&self.http_cache, &cache_key)
+                            {
+                                ResponseCache::new(dir).store(key, &text).map_err(|e| {
+                                    format!("failed to write response cache: {}", e)
+                                })?;
+                            }
+                            return Ok(text);
+                        }
+                        Err(e) => e,
+                    },
+                    Err(e) => format!("failed to read response body: {}", e),
+                },
+                Ok(response) => {
+                    let status = response.status();
+                    if !is_retryable_status(status) {
+                        return Err(format!("request failed with status {}", status));
+                    }
+                    retry_after = response
+                        .headers()
+                        .get(reqwest::header::RETRY_AFTER)
+                        .and_then(|v| v.to_str().ok())
+                        .and_then(|v| v.trim().parse::<u64>().ok())
+                        .map(std::time::Duration::from_secs);
+                    format!("request failed with status {}", status)
+                }
+                Err(e) => format!("request failed: {}", e),
+            };
+            drop(permit);
+
+            if attempt >= policy.max_attempts {
+                return Err(format!("{} (after {} attempts)", error, attempt));
+            }
+
+            let delay = retry_after
+                .map(|d| d.min(policy.max_delay))
+                .unwrap_or_else(|| policy.backoff(attempt));
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
 
 pub struct Aggregator<'a> {
-    pub client: Client,
     pub db: &'a Database,
+    pub options: AggregatorOptions,
+    /// Client for ECCC requests outside the source (hourly data and climate normals)
+    pub upstream: Arc<UpstreamClient>,
+    /// Provider of stations and daily observations
+    pub source: Arc<dyn WeatherSource>,
+    /// "Now" for the whole run, fixed when the aggregator is created so every station
+    /// sees the same history window and activity cut-off; also the time stamped on
+    /// the rows the run writes
+    now: NaiveDateTime,
 }
 
 impl<'a> Aggregator<'a> {
     pub fn new(db: &'a Database) -> Self {
-        let client = reqwest::Client::new();
-        Aggregator { client, db }
+        Self::with_options(db, AggregatorOptions::default())
+    }
+
+    /// Create an aggregator that talks to the given upstream endpoints, with the
+    /// weather source named by `options.source`
+    pub fn with_options(db: &'a Database, options: AggregatorOptions) -> Self {
+        let upstream = Arc::new(UpstreamClient::new(&options));
+        let source: Arc<dyn WeatherSource> = match options.source {
+            SourceKind::Eccc => Arc::new(EcccSource::new(Arc::clone(&upstream), &options)),
+        };
+        Self::with_upstream(db, options, upstream, source)
+    }
+
+    /// Create an aggregator that ingests from a custom weather source, e.g. another
+    /// provider or a test double; `options.source` is ignored
+    pub fn with_source(
+        db: &'a Database,
+        options: AggregatorOptions,
+        source: Arc<dyn WeatherSource>,
+    ) -> Self {
+        let upstream = Arc::new(UpstreamClient::new(&options));
+        Self::with_upstream(db, options, upstream, source)
+    }
+
+    fn with_upstream(
+        db: &'a Database,
+        options: AggregatorOptions,
+        upstream: Arc<UpstreamClient>,
+        source: Arc<dyn WeatherSource>,
+    ) -> Self {
+        let now = options.as_of.unwrap_or_else(|| Utc::now().naive_utc());
+        Aggregator {
+            db,
+            options,
+            upstream,
+            source,
+            now,
+        }
+    }
+
+    /// Time to record as the end of a run: the wall clock, except when recording or
+    /// replaying a response cache, where it is `now` so the replay writes the same rows
+    fn finished_at(&self) -> NaiveDateTime {
+        match self.options.http_cache {
+            Some(_) => self.now,
+            None => Utc::now().naive_utc(),
+        }
+    }
+
+    fn climate_normals_url(&self) -> String {
+        format!(
+            "{}/collections/climate-normals/items",
+            self.options.stations_base_url.trim_end_matches('/')
+        )
     }
 
-    /// Check if a station is still active (reported data within the last week)
-    fn is_station_active(dly_last_date: Option<&str>) -> bool {
+    /// Check if a station is still active (reported data within `active_within_days`).
+    /// Every station counts as active when no activity window is configured.
+    fn is_station_active(
+        dly_last_date: Option<&str>,
+        active_within_days: Option<i64>,
+        now: NaiveDateTime,
+    ) -> bool {
+        let Some(active_within_days) = active_within_days else {
+            return true;
+        };
         let Some(date_str) = dly_last_date else {
             return false; // No date means not active
         };
@@ -34,14 +643,17 @@ impl<'a> Aggregator<'a> {
             return false; // Couldn't parse date
         };
 
-        let now = Utc::now().naive_utc();
-        let one_week_ago = now - Duration::days(7);
+        let cutoff = now - Duration::days(active_within_days);
 
-        last_date >= one_week_ago
+        last_date >= cutoff
     }
 
-    /// Check if a station has at least 5 years of data
-    fn has_sufficient_data(dly_first_date: Option<&str>, dly_last_date: Option<&str>) -> bool {
+    /// Check if a station's daily record spans at least `min_record_days`
+    fn has_sufficient_data(
+        dly_first_date: Option<&str>,
+        dly_last_date: Option<&str>,
+        min_record_days: i64,
+    ) -> bool {
         let Some(first_str) = dly_first_date else {
             return false;
         };
@@ -67,32 +679,22 @@ impl<'a> Aggregator<'a> {
             return false;
         };
 
-        // Check if the difference is at least 5 years (1825 days to account for leap years)
-        let five_years = Duration::days(1825);
-        (last_date - first_date) >= five_years
+        (last_date - first_date) >= Duration::days(min_record_days)
     }
 
-    /// Fetch stations from the API and insert them directly into the database
-    /// Only includes stations that have reported data within the last week
+    /// Fetch stations from the weather source and insert them directly into the database
+    /// Only includes stations that pass the station filter and the `IngestConfig`
+    /// eligibility rules
+    ///
+    /// Each page of the catalogue is stored as soon as it arrives, so the stations of
+    /// the pages read before a failed one stay in the database.
     ///
     /// # Returns
-    /// * `Result<usize, Box<dyn std::error::Error>>` - Number of stations inserted or error
-    pub async fn fetch_and_store_stations(&self) -> Result<usize, Box<dyn std::error::Error>> {
-        let response = self
-            .client
-            .get("https://api.weather.gc.ca/collections/climate-stations/items?limit=99999")
-            .send()
-            .await?
-            .text()
-            .await?;
-
-        let json: serde_json::Value = serde_json::from_str(&response)?;
-
-        let features = json["features"]
-            .as_array()
-            .ok_or("No features array in response")?;
-
-        let pb = ProgressBar::new(features.len() as u64);
+    /// * `Result<StationFetchReport, Box<dyn std::error::Error>>` - Station counts or error
+    pub async fn fetch_and_store_stations(
+        &self,
+    ) -> Result<StationFetchReport, Box<dyn std::error::Error>> {
+        let pb = ProgressBar::new(0);
         pb.set_style(
             ProgressStyle::default_bar()
                 .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}")
@@ -101,262 +703,2577 @@ impl<'a> Aggregator<'a> {
         );
         pb.set_message("Processing stations...");
 
-        let mut inserted_count = 0;
-        let mut filtered_count = 0;
-        let mut insufficient_data_count = 0;
-        let mut total_count = 0;
-
-        for feature in features {
-            let properties = &feature["properties"];
-
-            if let (Some(id), Some(name), Some(lon), Some(lat)) = (
-                properties["STN_ID"].as_i64(),
-                properties["STATION_NAME"].as_str(),
-                properties["LONGITUDE"].as_i64(),
-                properties["LATITUDE"].as_i64(),
-            ) {
-                total_count += 1;
-                let lon_x = lon as f64 / 10000000.0;
-                let lat_y = lat as f64 / 10000000.0;
-                let dly_first_date = properties["DLY_FIRST_DATE"].as_str();
-                let dly_last_date = properties["DLY_LAST_DATE"].as_str();
-
-                // Filter out inactive stations
-                if !Self::is_station_active(dly_last_date) {
-                    filtered_count += 1;
-                    pb.inc(1);
-                    continue;
+        let mut report = StationFetchReport::default();
+        let mut pages = self.source.list_stations(&self.options.filter);
+        while let Some(page) = pages.next().await {
+            let page = match page {
+                Ok(page) => page,
+                Err(e) => {
+                    pb.abandon_with_message("Station catalogue failed");
+                    return Err(format!(
+                        "{} (after storing {} stations from {} pages)",
+                        e, report.inserted, report.pages
+                    )
+                    .into());
                 }
+            };
 
-                // Filter out stations with less than 5 years of data
-                if !Self::has_sufficient_data(dly_first_date, dly_last_date) {
-                    insufficient_data_count += 1;
-                    pb.inc(1);
-                    continue;
-                }
+            report.pages += 1;
+            if report.pages == 1 {
+                report.number_matched = page.number_matched;
+            }
+            match report.number_matched {
+                Some(matched) => pb.set_length(matched),
+                None => pb.inc_length(page.stations.len() as u64),
+            }
+            report.skipped += page.skipped;
 
-                match self.db.insert_station(
-                    id,
-                    &name.to_string(),
-                    lon_x,
-                    lat_y,
-                    dly_first_date,
-                    dly_last_date,
-                ) {
-                    Ok(_) => inserted_count += 1,
-                    Err(e) => eprintln!("Error inserting station ID {}: {}", id, e),
-                }
+            for station in &page.stations {
+                self.store_station(station, &mut report);
+                pb.inc(1);
             }
-            pb.inc(1);
         }
 
         pb.finish_with_message("Station processing complete");
-        println!("\nTotal stations processed: {}", total_count);
-        println!("Active stations (inserted): {}", inserted_count);
-        println!("Inactive stations (filtered out): {}", filtered_count);
-        println!("Insufficient data (<5 years): {}", insufficient_data_count);
+        println!("\nTotal stations processed: {}", report.total);
+        if let Some(matched) = report.number_matched {
+            println!("Stations matched by the API: {}", matched);
+            if matched != report.received() as u64 {
+                eprintln!(
+                    "Warning: the API matched {} stations but {} were received",
+                    matched,
+                    report.received()
+                );
+            }
+        }
+        if !self.options.filter.is_empty() {
+            println!("Outside the station filter: {}", report.excluded);
+        }
+        println!("Active stations (inserted): {}", report.inserted);
+        println!("Inactive stations (filtered out): {}", report.inactive);
+        println!(
+            "Insufficient data (<{} days): {}",
+            self.options.ingest.min_record_days, report.insufficient_data
+        );
+
+        Ok(report)
+    }
+
+    /// Apply the station filter and eligibility rules to one listed station and store
+    /// it if it passes
+    fn store_station(&self, station: &SourceStation, report: &mut StationFetchReport) {
+        let province = station.metadata.province.as_deref();
+        if !self
+            .options
+            .filter
+            .matches(station.id, province, station.lon_x, station.lat_y)
+        {
+            report.excluded += 1;
+            return;
+        }
+
+        report.total += 1;
+        let dly_first_date = station.dly_first_date.as_deref();
+        let dly_last_date = station.dly_last_date.as_deref();
+
+        // Filter out inactive stations
+        let ingest = &self.options.ingest;
+        if !Self::is_station_active(dly_last_date, ingest.active_within_days, self.now) {
+            report.inactive += 1;
+            return;
+        }
 
-        Ok(inserted_count)
+        // Filter out stations with too short a record
+        if !Self::has_sufficient_data(dly_first_date, dly_last_date, ingest.min_record_days) {
+            report.insufficient_data += 1;
+            return;
+        }
+
+        let metadata = StationMetadata {
+            source: Some(self.source.name().to_string()),
+            ..station.metadata.clone()
+        };
+        // A station created by `import_csv_dir` for the same Climate ID hands its
+        // data over in the same transaction
+        let stored = self.db.with_transaction(|tx| {
+            db::insert_station(
+                tx,
+                station.id,
+                &station.name,
+                station.lon_x,
+                station.lat_y,
+                dly_first_date,
+                dly_last_date,
+            )?;
+            db::set_station_metadata(tx, station.id, &metadata)?;
+            match &metadata.climate_id {
+                Some(climate_id) => db::merge_csv_stations(tx, climate_id, station.id),
+                None => Ok(0),
+            }
+        });
+        match stored
+        {
+            Ok(_) => {
+                report.inserted += 1;
+                report.stored_ids.insert(station.id);
+            }
+            Err(e) => eprintln!("Error inserting station ID {}: {}", station.id, e),
+        }
     }
 
     /// Fetch daily weather data for a station and calculate climate metrics
     ///
+    /// Only months that are missing from the database, or were still open when
+    /// they were last fetched, are downloaded. Each fetched month is recorded in
+    /// `ingested_months`, so a later update skips it. Switch dates are then
+    /// computed from the stored observations, unless more than `max_failed_months`
+    /// months failed to download, in which case the station is marked incomplete
+    /// and its previous dates are kept. All of the station's writes happen in
+    /// one transaction, so a crash never leaves it half stored.
+    ///
     /// # Arguments
     /// * `station_id` - The station ID
     /// * `station_name` - The station name
     ///
     /// # Returns
-    /// * `Result<(), Box<dyn std::error::Error>>` - Ok if successful
+    /// * `Result<ClimateFetchReport, Box<dyn std::error::Error>>` - Per-station fetch report,
+    ///   including months that failed after all retries
     pub async fn fetch_and_store_climate_data(
         &self,
         station_id: i64,
         _station_name: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        // Get last 5 years of data
-        let end_date = Utc::now().naive_utc().date();
-        let start_year = end_date.year() - 5;
-        let end_year = end_date.year();
+    ) -> Result<ClimateFetchReport, Box<dyn std::error::Error>> {
+        self.fetch_and_store_daily(station_id, None).await
+    }
 
-        // Build list of all (year, month) pairs to fetch
-        let mut months_to_fetch = Vec::new();
-        for year in start_year..=end_year {
-            for month in 1..=12 {
-                // Skip future months
-                if year == end_year && month > end_date.month() as i32 {
-                    break;
-                }
-                months_to_fetch.push((year, month));
-            }
-        }
-
-        // Fetch all months concurrently
-        let mut tasks = Vec::new();
-        for (year, month) in months_to_fetch {
-            let client = self.client.clone();
-            let station_id_str = station_id.to_string();
-            let year_str = year.to_string();
-            let month_str = month.to_string();
-
-            let task = tokio::spawn(async move {
-                let query = [
-                    ("format", "csv"),
-                    ("stationID", station_id_str.as_str()),
-                    ("Year", year_str.as_str()),
-                    ("Month", month_str.as_str()),
-                    ("Day", "1"),
-                    ("timeframe", "2"), // Daily data
-                    ("submit", "Download Data"),
-                ];
-
-                let response = match client
-                    .get("https://climate.weather.gc.ca/climate_data/bulk_data_e.html")
-                    .query(&query)
-                    .send()
-                    .await
-                {
-                    Ok(r) => match r.text().await {
-                        Ok(text) => text,
-                        Err(_) => return Vec::new(),
-                    },
-                    Err(_) => return Vec::new(),
-                };
+    /// `fetch_and_store_climate_data`, recording a checkpoint for `run_id` in the same
+    /// transaction as the station's data
+    async fn fetch_and_store_daily(
+        &self,
+        station_id: i64,
+        run_id: Option<i64>,
+    ) -> Result<ClimateFetchReport, Box<dyn std::error::Error>> {
+        // Skip months that were already ingested after they closed
+        let end_date = self.now.date();
+        let complete_months = self.db.get_complete_months(station_id)?;
+        let months_to_fetch = self.months_to_fetch(&complete_months, end_date);
 
-                let mut records = Vec::new();
-                let mut rdr = csv::Reader::from_reader(response.as_bytes());
-                for result in rdr.records() {
-                    if let Ok(record) = result {
-                        // Extract fields: Date is field 4, Mean Temp is field 13
-                        if let Some(date_str) = record.get(4) {
-                            if let Ok(date) = NaiveDate::parse_from_str(date_str, "%Y-%m-%d") {
-                                let mean_temp = record.get(13).and_then(|s| {
-                                    if s.is_empty() || s == "M" {
-                                        None
-                                    } else {
-                                        s.parse::<f64>().ok()
-                                    }
-                                });
-
-                                records.push(DailyRecord {
-                                    date,
-                                    mean_temp,
-                                });
-                            }
-                        }
+        // Fetch all missing months from the weather source
+        let results = self
+            .fetch_daily_months(station_id, &months_to_fetch, end_date)
+            .await;
+
+        // Store each month that arrived; failed months stay missing and are retried next run
+        let mut report = ClimateFetchReport {
+            station_id,
+            months_requested: months_to_fetch.len(),
+            months_fetched: 0,
+            failed_months: Vec::new(),
+            complete: true,
+        };
+        // The station's months, status, switch dates and checkpoint are stored together
+        self.db.with_transaction(|tx| {
+            for (year, month, result) in results {
+                let records = match result {
+                    Ok(records) => records,
+                    Err(error) => {
+                        report.failed_months.push(FailedMonth { year, month, error });
+                        continue;
                     }
-                }
-                records
-            });
-            tasks.push(task);
-        }
+                };
 
-        // Wait for all tasks to complete and collect results
-        let mut all_records: Vec<DailyRecord> = Vec::new();
-        for task in tasks {
-            if let Ok(records) = task.await {
-                all_records.extend(records);
+                self.store_month(tx, station_id, year, month, &records, end_date)?;
+                report.months_fetched += 1;
             }
-        }
-
-        if all_records.is_empty() {
-            return Ok(());
-        }
 
-        // Group data by year
-        let mut yearly_data: std::collections::HashMap<i32, Vec<&DailyRecord>> =
-            std::collections::HashMap::new();
-        for record in &all_records {
-            yearly_data
-                .entry(record.date.year())
-                .or_insert_with(Vec::new)
-                .push(record);
-        }
-
-        // Calculate metrics for each year
-        let mut switch_to_summer_days = Vec::new();
-        let mut switch_to_winter_days = Vec::new();
-
-        for (_year, records) in yearly_data.iter_mut() {
-            records.sort_by_key(|r| r.date);
-
-            // Find the day to switch from winter to summer tires:
-            // The day after the last time the mean daily temperature was below 7°C (in spring)
-            // We look for the last occurrence of temp < 7 before we get sustained warmth
-            let mut last_below_7_in_spring = None;
-            for (i, record) in records.iter().enumerate() {
-                if let Some(temp) = record.mean_temp {
-                    if temp < 7.0 {
-                        // Check if this is in the first half of the year (spring transition)
-                        if record.date.ordinal() <= 180 {
-                            last_below_7_in_spring = Some(i);
-                        }
-                    }
-                }
+            // Too large a gap would skew the switch dates, so keep the previous ones instead
+            report.complete = report.failed_months.len() <= self.options.max_failed_months;
+            db::set_station_ingest_status(
+                tx,
+                station_id,
+                report.complete,
+                report.failed_months.len(),
+                self.now,
+            )?;
+            if report.complete {
+                self.update_switch_dates(tx, station_id, end_date)?;
             }
-            if let Some(idx) = last_below_7_in_spring {
-                // The switch day is the day after the last below-7 day
-                if idx + 1 < records.len() {
-                    switch_to_summer_days.push(records[idx + 1].date.ordinal() as i32);
-                }
+            if let Some(run_id) = run_id {
+                db::mark_station_checkpoint(tx, run_id, station_id, PHASE_DAILY, self.now)?;
             }
+            Ok::<_, Box<dyn std::error::Error>>(())
+        })?;
 
-            // Find the day to switch from summer to winter tires:
-            // The FIRST day in fall where temp > 7°C and the following day was < 7°C
-            // Start looking from July onwards (day 182) to avoid catching spring transitions
-            for i in 0..records.len().saturating_sub(1) {
-                if let Some(day_of_year) = records.get(i).map(|r| r.date.ordinal()) {
-                    // Only look at dates from July onwards (after day 182)
-                    if day_of_year >= 182 {
-                        if let (Some(temp_today), Some(temp_tomorrow)) =
-                            (records[i].mean_temp, records[i + 1].mean_temp)
-                        {
-                            if temp_today > 7.0 && temp_tomorrow < 7.0 {
-                                // This is the first fall transition from above to below 7°C
-                                // The switch day is this day (the last day above 7°C before cold)
-                                switch_to_winter_days.push(records[i].date.ordinal() as i32);
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
-        }
+        Ok(report)
+    }
+
+    /// Fetch hourly temperature and dew point for a station from the bulk CSV endpoint
+    ///
+    /// Works like `fetch_and_store_climate_data` with `timeframe=1`: only
+    /// missing or still-open months of the history window are downloaded, and
+    /// each stored month is recorded in `ingested_hourly_months`. Switch dates
+    /// are not touched; the hourly criteria are evaluated by the `Analyzer`.
+    ///
+    /// # Arguments
+    /// * `station_id` - The station ID
+    ///
+    /// # Returns
+    /// * `Result<ClimateFetchReport, Box<dyn std::error::Error>>` - Per-station fetch report;
+    ///   `complete` is false when more than `max_failed_months` months failed
+    pub async fn fetch_and_store_hourly_data(
+        &self,
+        station_id: i64,
+    ) -> Result<ClimateFetchReport, Box<dyn std::error::Error>> {
+        self.fetch_and_store_hourly(station_id, None).await
+    }
 
-        // Calculate averages
-        let avg_switch_to_summer = average_day_of_year(&switch_to_summer_days);
-        let avg_switch_to_winter = average_day_of_year(&switch_to_winter_days);
+    /// `fetch_and_store_hourly_data`, recording a checkpoint for `run_id` in the same
+    /// transaction as the station's data
+    async fn fetch_and_store_hourly(
+        &self,
+        station_id: i64,
+        run_id: Option<i64>,
+    ) -> Result<ClimateFetchReport, Box<dyn std::error::Error>> {
+        let end_date = self.now.date();
+        let complete_months = self.db.get_complete_hourly_months(station_id)?;
+        let months_to_fetch = self.months_to_fetch(&complete_months, end_date);
 
-        // Store in database (using current year as reference)
-        let current_year = Utc::now().year() as i64;
-        self.db.insert_data(
+        let results = fetch_bulk_csv_months(
+            &self.upstream,
+            &self.options.climate_base_url,
             station_id,
-            current_year,
-            avg_switch_to_summer.as_deref(),
-            avg_switch_to_winter.as_deref(),
-        )?;
+            &months_to_fetch,
+            BULK_TIMEFRAME_HOURLY,
+        )
+        .await;
 
-        Ok(())
+        let mut report = ClimateFetchReport {
+            station_id,
+            months_requested: months_to_fetch.len(),
+            months_fetched: 0,
+            failed_months: Vec::new(),
+            complete: true,
+        };
+        self.db.with_transaction(|tx| {
+            for (year, month, result) in results {
+                let body = match result {
+                    Ok(body) => body,
+                    Err(error) => {
+                        report.failed_months.push(FailedMonth { year, month, error });
+                        continue;
+                    }
+                };
+
+                db::insert_hourly_observations(tx, &parse_hourly_csv(&body, station_id))?;
+                let complete = is_month_closed(year, month, end_date);
+                db::mark_hourly_month_ingested(tx, station_id, year, month, complete, self.now)?;
+                report.months_fetched += 1;
+            }
+            if let Some(run_id) = run_id {
+                db::mark_station_checkpoint(tx, run_id, station_id, PHASE_HOURLY, self.now)?;
+            }
+            Ok::<_, rusqlite::Error>(())
+        })?;
+        report.complete = report.failed_months.len() <= self.options.max_failed_months;
+
+        Ok(report)
     }
-}
 
-/// Helper struct for daily weather records
-struct DailyRecord {
-    date: NaiveDate,
-    mean_temp: Option<f64>,
-}
+    /// Fetch the monthly mean temperature normals of a station from the OGC API
+    /// `climate-normals` collection and replace the stored ones
+    ///
+    /// A station ECCC publishes no normals for keeps whatever was stored before.
+    /// If the collection holds more than one normals period, the latest one is kept.
+    ///
+    /// # Arguments
+    /// * `station_id` - The station ID
+    ///
+    /// # Returns
+    /// * `Result<usize, Box<dyn std::error::Error>>` - Number of months stored
+    pub async fn fetch_and_store_climate_normals(
+        &self,
+        station_id: i64,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        self.fetch_and_store_normals(station_id, None).await
+    }
 
-/// Calculate average day of year and convert to date string
-fn average_day_of_year(days: &[i32]) -> Option<String> {
-    if days.is_empty() {
-        return None;
+    /// `fetch_and_store_climate_normals`, recording a checkpoint for `run_id` in the same
+    /// transaction as the station's normals
+    async fn fetch_and_store_normals(
+        &self,
+        station_id: i64,
+        run_id: Option<i64>,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        let normals = self.fetch_climate_normals(station_id).await?;
+        self.db.with_transaction(|tx| {
+            if !normals.is_empty() {
+                db::replace_climate_normals(tx, station_id, &normals, self.now)?;
+            }
+            if let Some(run_id) = run_id {
+                db::mark_station_checkpoint(tx, run_id, station_id, PHASE_NORMALS, self.now)?;
+            }
+            Ok::<_, rusqlite::Error>(())
+        })?;
+        Ok(normals.len())
     }
 
-    let sum: i32 = days.iter().sum();
-    let avg_day = sum / days.len() as i32;
+    /// Page through the mean temperature normals of one station, one per month
+    async fn fetch_climate_normals(&self, station_id: i64) -> Result<Vec<ClimateNormal>, String> {
+        let query = vec![
+            ("f", "json".to_string()),
+            ("STN_ID", station_id.to_string()),
+            ("NORMAL_ID", NORMAL_ID_MEAN_TEMPERATURE.to_string()),
+        ];
+        let mut pages = ogc_pages(&self.upstream, self.climate_normals_url(), query, 100);
 
-    // Convert day of year to date (using a non-leap year for simplicity)
-    if let Some(date) = NaiveDate::from_yo_opt(2023, avg_day as u32) {
-        Some(date.format("%Y-%m-%d").to_string())
-    } else {
-        None
+        let mut by_month: std::collections::BTreeMap<u32, ClimateNormal> =
+            std::collections::BTreeMap::new();
+        while let Some(json) = pages.next().await {
+            let json = json?;
+            for feature in json["features"].as_array().into_iter().flatten() {
+                let Some(normal) = parse_climate_normal_feature(&feature["properties"]) else {
+                    continue;
+                };
+                let newer = by_month
+                    .get(&normal.month)
+                    .is_none_or(|kept| normal.last_year >= kept.last_year);
+                if newer {
+                    by_month.insert(normal.month, normal);
+                }
+            }
+        }
+        Ok(by_month
+            .into_values()
+            .map(|normal| ClimateNormal {
+                station_id,
+                ..normal
+            })
+            .collect())
+    }
+
+    /// Run the whole ingest pipeline: the station catalogue, then daily data for every
+    /// stored station and, optionally, hourly data and climate normals
+    ///
+    /// This is what `--update-db` does; the `--serve` refresh schedule calls it as well.
+    /// Per-station errors are reported on the progress bar and counted, never returned.
+    /// The run and its statistics are recorded in `ingest_runs`, and every station it
+    /// finishes gets a checkpoint in `ingest_checkpoints`.
+    ///
+    /// # Arguments
+    /// * `hourly` - Also download hourly observations (timeframe=1)
+    /// * `trigger` - What started the run
+    /// * `resume` - Continue the most recent interrupted run, skipping the stations it
+    ///   finished; starts a new run if there is none
+    ///
+    /// # Returns
+    /// * `UpdateReport` - Counts of stations and months processed and failed
+    pub async fn update_all(
+        &self,
+        hourly: bool,
+        trigger: RunTrigger,
+        resume: bool,
+    ) -> UpdateReport {
+        let mut update = UpdateReport::default();
+        if resume {
+            match self.db.get_interrupted_ingest_run() {
+                Ok(Some(id)) => {
+                    println!("Resuming interrupted ingest run #{}", id);
+                    update.run_id = Some(id);
+                    update.resumed = true;
+                }
+                Ok(None) => println!("No interrupted ingest run to resume; starting a new one"),
+                Err(e) => eprintln!("Warning: could not look up interrupted runs: {}", e),
+            }
+        }
+        if update.run_id.is_none() {
+            match self.db.start_ingest_run(
+                trigger.as_str(),
+                &self.options.stations_base_url,
+                &self.options.climate_base_url,
+                self.options.daily_source.as_str(),
+                self.now,
+            ) {
+                Ok(id) => update.run_id = Some(id),
+                Err(e) => eprintln!("Warning: could not record ingest run: {}", e),
+            }
+        }
+        if let Some(CacheMode::Record(dir)) = &self.options.http_cache
+            && let Err(e) = ResponseCache::new(dir).write_manifest(self.now)
+        {
+            eprintln!("Warning: could not write response cache manifest: {}", e);
+        }
+
+        self.run_update(hourly, &mut update).await;
+
+        if let Some(id) = update.run_id {
+            let stations = update.stations.clone().unwrap_or_default();
+            let run = IngestRun {
+                id,
+                status: update.status().to_string(),
+                stations_seen: stations.total as i64,
+                stations_inserted: stations.inserted as i64,
+                stations_inactive: stations.inactive as i64,
+                stations_insufficient_data: stations.insufficient_data as i64,
+                stations_skipped: stations.skipped as i64,
+                stations_failed: update.stations_failed as i64,
+                stations_incomplete: update.incomplete_stations as i64,
+                stations_resumed: update.stations_resumed as i64,
+                months_fetched: update.months_fetched as i64,
+                months_failed: update.months_failed as i64,
+                hourly_months_failed: update.hourly_months_failed as i64,
+                error_count: update.error_count as i64,
+                errors: update.errors.clone(),
+                ..IngestRun::default()
+            };
+            if let Err(e) = self.db.finish_ingest_run(&run, self.finished_at()) {
+                eprintln!("Warning: could not record ingest run: {}", e);
+            }
+        }
+        update
+    }
+
+    /// The steps of `update_all`, filling in `update` as they go
+    async fn run_update(&self, hourly: bool, update: &mut UpdateReport) {
+        println!("\nFetching stations from API...");
+        match self.fetch_and_store_stations().await {
+            Ok(report) => {
+                println!(
+                    "Successfully inserted {} stations into database",
+                    report.inserted
+                );
+                update.stations = Some(report);
+            }
+            Err(e) => {
+                eprintln!("Error fetching/storing stations: {}", e);
+                update.push_error(format!("station catalogue: {}", e));
+                return;
+            }
+        }
+
+        // Fetch climate data for all of this source's stations, or those selected by
+        // the filter; stations imported from other providers are left alone
+        println!("\nFetching climate data for all stations...");
+        let filter = &self.options.filter;
+        let stations = match self.db.get_all_stations() {
+            Ok(stations) => stations
+                .into_iter()
+                .filter(|s| {
+                    s.metadata.source_name() == self.source.name()
+                        && filter.matches(s.id, s.metadata.province.as_deref(), s.lon_x, s.lat_y)
+                })
+                .collect::<Vec<_>>(),
+            Err(e) => {
+                eprintln!("Error retrieving stations: {}", e);
+                update.push_error(format!("retrieving stations: {}", e));
+                return;
+            }
+        };
+        let stations: Vec<(i64, String)> = stations.into_iter().map(|s| (s.id, s.name)).collect();
+        let run_id = update.run_id;
+
+        // A resumed run skips the stations it finished before it was interrupted
+        let daily = |station_id| self.fetch_and_store_daily(station_id, run_id);
+        let resumed = self
+            .for_each_station(
+                update,
+                &stations,
+                PHASE_DAILY,
+                daily,
+                |update, pb, name, result| match result {
+                    Ok(report) => {
+                        update.stations_updated += 1;
+                        update.months_fetched += report.months_fetched;
+                        update.months_failed += report.failed_months.len();
+                        for failed in &report.failed_months {
+                            update.push_error(format!(
+                                "{} {}-{:02}: {}",
+                                name, failed.year, failed.month, failed.error
+                            ));
+                        }
+                        if !report.complete {
+                            update.incomplete_stations += 1;
+                            pb.println(format!(
+                                "  ✗ Incomplete data for {}: {} of {} months failed",
+                                name,
+                                report.failed_months.len(),
+                                report.months_requested
+                            ));
+                        } else {
+                            for failed in &report.failed_months {
+                                pb.println(format!(
+                                    "  ⚠ {} {}-{:02} failed: {}",
+                                    name, failed.year, failed.month, failed.error
+                                ));
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        update.stations_failed += 1;
+                        pb.println(format!("  ✗ Error for {}: {}", name, e));
+                        update.push_error(format!("{}: {}", name, e));
+                    }
+                },
+                "Climate data collection complete!",
+            )
+            .await;
+        update.stations_resumed = resumed;
+        println!();
+        println!("Months failed after retries: {}", update.months_failed);
+        println!("Stations marked incomplete: {}", update.incomplete_stations);
+
+        if hourly {
+            println!("\nFetching hourly data for all stations...");
+            let hourly = |station_id| self.fetch_and_store_hourly(station_id, run_id);
+            self.for_each_station(
+                update,
+                &stations,
+                PHASE_HOURLY,
+                hourly,
+                |update, pb, name, result| match result {
+                    Ok(report) => {
+                        update.hourly_months_failed += report.failed_months.len();
+                        for failed in &report.failed_months {
+                            update.push_error(format!(
+                                "{} {}-{:02} (hourly): {}",
+                                name, failed.year, failed.month, failed.error
+                            ));
+                        }
+                        if !report.complete {
+                            pb.println(format!(
+                                "  ✗ Incomplete hourly data for {}: {}/{} months failed",
+                                name,
+                                report.failed_months.len(),
+                                report.months_requested
+                            ));
+                        }
+                    }
+                    Err(e) => {
+                        update.hourly_stations_failed += 1;
+                        pb.println(format!("  ✗ Error for {}: {}", name, e));
+                        update.push_error(format!("{} (hourly): {}", name, e));
+                    }
+                },
+                "Hourly data collection complete!",
+            )
+            .await;
+            println!();
+            println!(
+                "Hourly months failed after retries: {}",
+                update.hourly_months_failed
+            );
+            println!(
+                "Stations whose hourly fetch failed: {}",
+                update.hourly_stations_failed
+            );
+        }
+
+        if self.options.normals {
+            println!("\nFetching climate normals for all stations...");
+            let normals = |station_id| self.fetch_and_store_normals(station_id, run_id);
+            self.for_each_station(
+                update,
+                &stations,
+                PHASE_NORMALS,
+                normals,
+                |update, pb, name, result| match result {
+                    Ok(0) => {}
+                    Ok(_) => update.normals_stored += 1,
+                    Err(e) => {
+                        pb.println(format!("  ✗ Error for {}: {}", name, e));
+                        update.push_error(format!("{} (normals): {}", name, e));
+                    }
+                },
+                "Climate normals collection complete!",
+            )
+            .await;
+            println!();
+            println!("Stations with climate normals: {}", update.normals_stored);
+        }
+    }
+
+    /// Run one phase of `update_all` for every station, a few stations at a time
+    ///
+    /// Stations a resumed run already finished for `phase` are skipped. Each result
+    /// is passed to `handle`, with the station's name and the phase's progress bar,
+    /// as soon as the station is done.
+    ///
+    /// # Arguments
+    /// * `update` - Report of the run, passed on to `handle`
+    /// * `stations` - (ID, name) of every station of the run
+    /// * `phase` - Checkpoint phase of the stations skipped on resume
+    /// * `fetch` - Fetches and stores one station's data
+    /// * `handle` - Records one station's result in `update`
+    /// * `finished` - Message left on the progress bar once every station is done
+    ///
+    /// # Returns
+    /// * `usize` - Number of stations skipped because the interrupted run finished them
+    async fn for_each_station<T, Fut>(
+        &self,
+        update: &mut UpdateReport,
+        stations: &[(i64, String)],
+        phase: &str,
+        fetch: impl Fn(i64) -> Fut,
+        mut handle: impl FnMut(&mut UpdateReport, &ProgressBar, &str, Result<T, String>),
+        finished: &'static str,
+    ) -> usize
+    where
+        Fut: std::future::Future<Output = Result<T, Box<dyn std::error::Error>>>,
+    {
+        let done = self.checkpointed_stations(update, phase);
+        let pending: Vec<(i64, String)> = stations
+            .iter()
+            .filter(|(id, _)| !done.contains(id))
+            .cloned()
+            .collect();
+        let resumed = stations.len() - pending.len();
+        if resumed > 0 {
+            println!(
+                "Skipping {} stations finished before the interruption",
+                resumed
+            );
+        }
+        let pb = update_progress_bar(pending.len());
+
+        // Work on a few stations at a time; the requests themselves are bounded by `limiter`
+        let concurrent_limit = 10;
+        let mut results = stream::iter(pending)
+            .map(|(station_id, name)| {
+                let result = fetch(station_id);
+                async move { (result.await.map_err(|e| e.to_string()), name) }
+            })
+            .buffer_unordered(concurrent_limit);
+
+        // Process results as they complete
+        while let Some((result, name)) = results.next().await {
+            handle(update, &pb, &name, result);
+            pb.inc(1);
+        }
+        pb.finish_with_message(finished);
+        resumed
+    }
+
+    /// Stations a resumed run finished before it was interrupted, for one phase;
+    /// empty for a new run
+    fn checkpointed_stations(
+        &self,
+        update: &UpdateReport,
+        phase: &str,
+    ) -> std::collections::HashSet<i64> {
+        let Some(run_id) = update.run_id.filter(|_| update.resumed) else {
+            return Default::default();
+        };
+        self.db
+            .get_checkpointed_stations(run_id, phase)
+            .unwrap_or_else(|e| {
+                eprintln!("Warning: could not read checkpoints, fetching every station: {}", e);
+                Default::default()
+            })
+    }
+
+    /// Months of the history window ending `today` that are not in `complete_months`
+    fn months_to_fetch(
+        &self,
+        complete_months: &std::collections::HashSet<(i32, u32)>,
+        today: NaiveDate,
+    ) -> Vec<(i32, u32)> {
+        let start_year = today.year() - self.options.ingest.history_years;
+        let end_year = today.year();
+
+        let mut months = Vec::new();
+        for year in start_year..=end_year {
+            for month in 1..=12 {
+                // Skip future months
+                if year == end_year && month > today.month() {
+                    break;
+                }
+                if !complete_months.contains(&(year, month)) {
+                    months.push((year, month));
+                }
+            }
+        }
+        months
+    }
+
+    /// Download the given months from the weather source
+    ///
+    /// Consecutive months are requested together as one date range, and the
+    /// ranges are fetched concurrently. A month the source does not deliver is
+    /// reported failed.
+    async fn fetch_daily_months(
+        &self,
+        station_id: i64,
+        months: &[(i32, u32)],
+        today: NaiveDate,
+    ) -> Vec<(i32, u32, Result<Vec<DailyRecord>, String>)> {
+        let tasks = consecutive_month_runs(months).into_iter().map(|run| async move {
+            let (first_year, first_month) = run[0];
+            let (last_year, last_month) = run[run.len() - 1];
+            let mut by_month: std::collections::BTreeMap<_, Result<Vec<DailyRecord>, String>> =
+                run.iter()
+                    .map(|&month| (month, Err("not delivered by the weather source".to_string())))
+                    .collect();
+
+            let start = NaiveDate::from_ymd_opt(first_year, first_month, 1);
+            let end = last_day_of_month(last_year, last_month).map(|end| end.min(today));
+            let (Some(start), Some(end)) = (start, end) else {
+                let error =
+                    format!("invalid month range starting {}-{:02}", first_year, first_month);
+                for result in by_month.values_mut() {
+                    *result = Err(error.clone());
+                }
+                return by_month;
+            };
+
+            let mut batches = self.source.daily_records(station_id, start, end);
+            while let Some(batch) = batches.next().await {
+                // Months outside the requested range are not stored
+                if let Some(slot) = by_month.get_mut(&(batch.year, batch.month)) {
+                    *slot = batch.records.map(|observations| {
+                        observations
+                            .iter()
+                            .filter_map(DailyRecord::from_observation)
+                            .collect()
+                    });
+                }
+            }
+            by_month
+        });
+        futures::future::join_all(tasks)
+            .await
+            .into_iter()
+            .flatten()
+            .map(|((year, month), result)| (year, month, result))
+            .collect()
+    }
+
+    /// Store the daily records of one (station, year, month) slice and record it as ingested,
+    /// on the transaction `conn` of the caller
+    fn store_month(
+        &self,
+        conn: &Connection,
+        station_id: i64,
+        year: i32,
+        month: u32,
+        records: &[DailyRecord],
+        today: NaiveDate,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Keep the raw observations so recommendations can be recomputed later
+        let observations: Vec<DailyObservation> = records
+            .iter()
+            .map(|record| record.to_observation(station_id))
+            .collect();
+        db::insert_daily_observations(conn, &observations)?;
+
+        let complete = is_month_closed(year, month, today);
+        db::mark_month_ingested(conn, station_id, year, month, complete, self.now)?;
+        Ok(())
+    }
+
+    /// Recompute a station's per-year switch dates from its stored observations,
+    /// e.g. after gap filling added estimated days
+    ///
+    /// # Arguments
+    /// * `station_id` - The station ID
+    pub fn refresh_switch_dates(&self, station_id: i64) -> Result<(), Box<dyn std::error::Error>> {
+        let today = self.now.date();
+        self.db.with_transaction(|tx| self.update_switch_dates(tx, station_id, today))
+    }
+
+    /// Recompute a station's per-year switch dates from everything stored for
+    /// the history window ending `today`, not just what was fetched in this run
+    ///
+    /// Years before the window are deleted, so shrinking `history_years` also
+    /// takes them out of the recommendations.
+    fn update_switch_dates(
+        &self,
+        conn: &Connection,
+        station_id: i64,
+        today: NaiveDate,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let start_year = today.year() - self.options.ingest.history_years;
+        db::delete_data_before(conn, station_id, start_year as i64)?;
+
+        let start_date = format!("{}-01-01", start_year);
+        let end_date = today.format("%Y-%m-%d").to_string();
+        let all_records: Vec<DailyRecord> =
+            db::get_daily_observations(conn, station_id, &start_date, &end_date)?
+            .iter()
+            .filter_map(DailyRecord::from_observation)
+            .collect();
+
+        if all_records.is_empty() {
+            return Ok(());
+        }
+
+        // One row per season year; averaging across years happens in the Analyzer
+        for season in compute_yearly_switch_dates(&all_records) {
+            let switch_to_summer = season
+                .switch_to_summer
+                .map(|date| date.format("%Y-%m-%d").to_string());
+            let switch_to_winter = season
+                .switch_to_winter
+                .map(|date| date.format("%Y-%m-%d").to_string());
+            db::insert_data(
+                conn,
+                station_id,
+                season.year as i64,
+                switch_to_summer.as_deref(),
+                switch_to_winter.as_deref(),
+            )?;
+
+            // Stored next to the dates so the Analyzer can leave out patchy years
+            let (spring, fall) = season_completeness(&all_records, season.year, today);
+            db::set_season_completeness(conn, station_id, season.year as i64, &spring, &fall)?;
+        }
+
+        Ok(())
+    }
+
+    /// Import a directory of ECCC daily bulk CSV files without touching the network
+    ///
+    /// Files are found recursively and matched to an ECCC station already in the
+    /// database by the Climate ID column of their first row, or by its Station
+    /// Name and coordinates for stations stored without a Climate ID. A file that
+    /// matches no station creates one from its Climate ID, Station Name and
+    /// coordinate columns, so an empty database can be built without the
+    /// catalogue. Bulk CSV files carry no ECCC station ID, so such a station gets
+    /// an ID derived from its Climate ID (see `csv_station_id`); once
+    /// `fetch_and_store_stations` lists the Climate ID, it is merged into the
+    /// catalogue's station, data included. Their rows go through the same
+    /// parsing, storage and switch-date logic as `fetch_and_store_climate_data`,
+    /// and every imported month is recorded so a later online update does not
+    /// download it again. Each file is stored in one transaction, together with
+    /// its station's recomputed switch dates; a file that cannot be read as text
+    /// is listed in the report and skipped.
+    ///
+    /// # Arguments
+    /// * `dir` - Directory containing `*.csv` bulk data files
+    ///
+    /// # Returns
+    /// * `Result<CsvImportReport, Box<dyn std::error::Error>>` - Import summary
+    pub fn import_csv_dir(
+        &self,
+        dir: &Path,
+    ) -> Result<CsvImportReport, Box<dyn std::error::Error>> {
+        let mut files = Vec::new();
+        collect_files(dir, "csv", &mut files)?;
+        files.sort();
+
+        let mut stations: Vec<Station> = self
+            .db
+            .get_all_stations()?
+            .into_iter()
+            .filter(|s| s.metadata.source_name() == SourceKind::Eccc.as_str())
+            .collect();
+        let today = self.now.date();
+
+        let mut report = CsvImportReport {
+            files_read: 0,
+            unmatched_files: Vec::new(),
+            stations_created: 0,
+            failed_files: Vec::new(),
+            stations_updated: 0,
+            observations_stored: 0,
+        };
+        let mut touched_stations = std::collections::BTreeSet::new();
+
+        for path in files {
+            let body = match std::fs::read_to_string(&path) {
+                Ok(body) => body,
+                Err(e) => {
+                    report.failed_files.push((path, e.to_string()));
+                    continue;
+                }
+            };
+            report.files_read += 1;
+
+            let Some(info) = read_csv_station(&body) else {
+                report.unmatched_files.push(path);
+                continue;
+            };
+            let matched = match_station(&stations, &info)
+                .and_then(|id| stations.iter().position(|station| station.id == id));
+            let index = match matched {
+                Some(index) => index,
+                None => match csv_station(&info) {
+                    Some(station) => {
+                        report.stations_created += 1;
+                        stations.push(station);
+                        stations.len() - 1
+                    }
+                    None => {
+                        report.unmatched_files.push(path);
+                        continue;
+                    }
+                },
+            };
+
+            // Group rows by month so each slice is recorded like a downloaded month
+            let mut by_month: std::collections::BTreeMap<(i32, u32), Vec<DailyRecord>> =
+                std::collections::BTreeMap::new();
+            for record in parse_bulk_csv(&body) {
+                by_month
+                    .entry((record.date.year(), record.date.month()))
+                    .or_default()
+                    .push(record);
+            }
+
+            // Stations created from CSV files span the days of all their files
+            let station = &mut stations[index];
+            let station_id = station.id;
+            let created_from_csv = station.metadata.source_id.is_some();
+            if created_from_csv {
+                let first = by_month.values().flatten().map(|r| r.date).min();
+                let last = by_month.values().flatten().map(|r| r.date).max();
+                widen_record_date(&mut station.dly_first_date, first, std::cmp::Ordering::Less);
+                widen_record_date(
+                    &mut station.dly_last_date,
+                    last,
+                    std::cmp::Ordering::Greater,
+                );
+            }
+            let station = &stations[index];
+
+            // A file's station, months and the station's recomputed dates are stored together
+            let observations: usize = by_month.values().map(Vec::len).sum();
+            self.db.with_transaction(|tx| -> Result<(), Box<dyn std::error::Error>> {
+                if created_from_csv {
+                    db::insert_station(
+                        tx,
+                        station_id,
+                        &station.name,
+                        station.lon_x,
+                        station.lat_y,
+                        station.dly_first_date.as_deref(),
+                        station.dly_last_date.as_deref(),
+                    )?;
+                    db::set_station_metadata(tx, station_id, &station.metadata)?;
+                }
+                for ((year, month), records) in &by_month {
+                    self.store_month(tx, station_id, *year, *month, records, today)?;
+                }
+                self.update_switch_dates(tx, station_id, today)
+            })?;
+            report.observations_stored += observations;
+            touched_stations.insert(station_id);
+        }
+
+        report.stations_updated = touched_stations.len();
+        Ok(report)
+    }
+
+    /// Import GHCN-Daily stations and `.dly` files from a directory without touching the network
+    ///
+    /// `dir` must hold the `ghcnd-stations.txt` catalogue; `.dly` files are found
+    /// recursively. The station of each file is registered with source "ghcn" and
+    /// a negative ID derived from its GHCN-Daily ID (see `ghcn::station_id`), so
+    /// `NearestStationFinder` uses it like any other station; its daily record
+    /// dates are the first and last day with a temperature. Only days in the
+    /// history window are stored, through the same storage and switch-date logic
+    /// as the ECCC data. Files without a temperature in the window are left out,
+    /// so long-closed stations do not crowd out active ones. A file that cannot be
+    /// read or parsed is listed in the report and skipped. Each station is stored in
+    /// one transaction.
+    ///
+    /// # Arguments
+    /// * `dir` - Directory containing `ghcnd-stations.txt` and `*.dly` files
+    ///
+    /// # Returns
+    /// * `Result<GhcnImportReport, Box<dyn std::error::Error>>` - Import summary
+    pub fn import_ghcn_dir(
+        &self,
+        dir: &Path,
+    ) -> Result<GhcnImportReport, Box<dyn std::error::Error>> {
+        let catalogue_path = dir.join(ghcn::STATIONS_FILE);
+        let catalogue = std::fs::read_to_string(&catalogue_path)
+            .map_err(|e| format!("failed to read {}: {}", catalogue_path.display(), e))?;
+        let catalogue: std::collections::HashMap<String, GhcnStation> =
+            ghcn::parse_stations(&catalogue)
+                .into_iter()
+                .map(|station| (station.ghcn_id.clone(), station))
+                .collect();
+
+        let mut files = Vec::new();
+        collect_files(dir, "dly", &mut files)?;
+        files.sort();
+
+        let today = self.now.date();
+        let window_start = format!("{}-01-01", today.year() - self.options.ingest.history_years);
+        let mut report = GhcnImportReport::default();
+
+        for path in files {
+            let body = match std::fs::read_to_string(&path) {
+                Ok(body) => body,
+                Err(e) => {
+                    report.failed_files.push((path, e.to_string()));
+                    continue;
+                }
+            };
+            report.files_read += 1;
+
+            let file = match ghcn::parse_dly(&body) {
+                Ok(file) => file,
+                Err(e) => {
+                    report.failed_files.push((path, e));
+                    continue;
+                }
+            };
+            let Some(station) = catalogue.get(&file.ghcn_id) else {
+                report.unmatched_files.push(path);
+                continue;
+            };
+            let (Some(first), Some(last)) = (file.observations.first(), file.observations.last())
+            else {
+                report.files_without_recent_data += 1;
+                continue;
+            };
+            let dly_first_date = format!("{} 00:00:00", first.date);
+            let dly_last_date = format!("{} 00:00:00", last.date);
+
+            // Group the window's days by month so each is recorded like a downloaded month
+            let mut by_month: std::collections::BTreeMap<(i32, u32), Vec<DailyRecord>> =
+                std::collections::BTreeMap::new();
+            for record in file
+                .observations
+                .iter()
+                .filter(|obs| obs.date >= window_start)
+                .filter_map(DailyRecord::from_observation)
+            {
+                by_month
+                    .entry((record.date.year(), record.date.month()))
+                    .or_default()
+                    .push(record);
+            }
+            if by_month.is_empty() {
+                report.files_without_recent_data += 1;
+                continue;
+            }
+
+            let station_id = station.id();
+            self.db.with_transaction(|tx| -> Result<(), Box<dyn std::error::Error>> {
+                db::insert_station(
+                    tx,
+                    station_id,
+                    &station.name,
+                    station.longitude,
+                    station.latitude,
+                    Some(&dly_first_date),
+                    Some(&dly_last_date),
+                )?;
+                db::set_station_metadata(tx, station_id, &station.metadata())?;
+                for ((year, month), records) in &by_month {
+                    report.observations_stored += records.len();
+                    self.store_month(tx, station_id, *year, *month, records, today)?;
+                }
+                self.update_switch_dates(tx, station_id, today)
+            })?;
+            report.stations_updated += 1;
+        }
+
+        Ok(report)
+    }
+}
+
+/// ECCC as a `WeatherSource`: stations from the `climate-stations` catalogue, and daily
+/// observations from the bulk CSV site or the `climate-daily` collection
+pub struct EcccSource {
+    upstream: Arc<UpstreamClient>,
+    stations_base_url: String,
+    climate_base_url: String,
+    daily_source: DailySource,
+    stations_page_size: usize,
+    daily_page_size: usize,
+}
+
+impl EcccSource {
+    /// Create a source for the endpoints, daily source and page sizes of `options`
+    pub fn new(upstream: Arc<UpstreamClient>, options: &AggregatorOptions) -> Self {
+        EcccSource {
+            upstream,
+            stations_base_url: options.stations_base_url.clone(),
+            climate_base_url: options.climate_base_url.clone(),
+            daily_source: options.daily_source,
+            stations_page_size: options.stations_page_size,
+            daily_page_size: options.daily_page_size,
+        }
+    }
+
+    fn stations_url(&self) -> String {
+        format!(
+            "{}/collections/climate-stations/items",
+            self.stations_base_url.trim_end_matches('/')
+        )
+    }
+
+    fn climate_daily_url(&self) -> String {
+        format!(
+            "{}/collections/climate-daily/items",
+            self.stations_base_url.trim_end_matches('/')
+        )
+    }
+
+    /// Read the station catalogue one page at a time
+    ///
+    /// Each page is requested only once the previous one has been consumed, and a
+    /// page that fails ends the stream after its error.
+    fn station_pages<'a>(
+        &'a self,
+        filter: &'a StationFilter,
+    ) -> BoxStream<'a, Result<StationPage, String>> {
+        let pages = ogc_pages(
+            &self.upstream,
+            self.stations_url(),
+            filter.catalogue_query(),
+            self.stations_page_size,
+        );
+        pages
+            .map(|json| {
+                let json = json?;
+                let mut page = StationPage {
+                    number_matched: json["numberMatched"].as_u64(),
+                    ..StationPage::default()
+                };
+                for feature in json["features"].as_array().into_iter().flatten() {
+                    match parse_catalogue_feature(&feature["properties"]) {
+                        Some(station) => page.stations.push(station),
+                        None => page.skipped += 1,
+                    }
+                }
+                Ok(page)
+            })
+            .boxed()
+    }
+
+    /// Page through the `climate-daily` items of one station between two dates (inclusive)
+    async fn fetch_climate_daily_range(
+        &self,
+        station_id: i64,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<Vec<DailyRecord>, String> {
+        let query = vec![
+            ("f", "json".to_string()),
+            ("STN_ID", station_id.to_string()),
+            ("datetime", format!("{}/{}", start.format("%Y-%m-%d"), end.format("%Y-%m-%d"))),
+            ("sortby", "LOCAL_DATE".to_string()),
+        ];
+        let mut pages =
+            ogc_pages(&self.upstream, self.climate_daily_url(), query, self.daily_page_size);
+
+        let mut records = Vec::new();
+        while let Some(json) = pages.next().await {
+            let json = json?;
+            records.extend(
+                json["features"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|feature| parse_climate_daily_feature(&feature["properties"])),
+            );
+        }
+        Ok(records)
+    }
+}
+
+impl WeatherSource for EcccSource {
+    fn name(&self) -> &'static str {
+        SourceKind::Eccc.as_str()
+    }
+
+    fn list_stations<'a>(
+        &'a self,
+        filter: &'a StationFilter,
+    ) -> BoxStream<'a, Result<StationPage, String>> {
+        self.station_pages(filter)
+    }
+
+    /// With `DailySource::BulkCsv`, every month is one request and is delivered as
+    /// soon as it arrives. With `DailySource::ClimateDaily`, the range is one paged
+    /// query whose records are split into months; if it fails, every month fails.
+    fn daily_records<'a>(
+        &'a self,
+        station_id: i64,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> BoxStream<'a, MonthRecords> {
+        let months = months_between(start, end);
+        match self.daily_source {
+            DailySource::BulkCsv => months
+                .into_iter()
+                .map(|(year, month)| async move {
+                    let records = fetch_bulk_csv_month(
+                        &self.upstream,
+                        &self.climate_base_url,
+                        station_id,
+                        year,
+                        month,
+                        BULK_TIMEFRAME_DAILY,
+                    )
+                    .await
+                    .map(|body| {
+                        parse_bulk_csv(&body)
+                            .iter()
+                            .map(|record| record.to_observation(station_id))
+                            .collect()
+                    });
+                    MonthRecords {
+                        year,
+                        month,
+                        records,
+                    }
+                })
+                .collect::<FuturesUnordered<_>>()
+                .boxed(),
+            DailySource::ClimateDaily => stream::once(async move {
+                let result = self.fetch_climate_daily_range(station_id, start, end).await;
+                let mut by_month: std::collections::BTreeMap<(i32, u32), Vec<DailyObservation>> =
+                    months.iter().map(|&month| (month, Vec::new())).collect();
+                if let Ok(records) = &result {
+                    for record in records {
+                        if let Some(slot) =
+                            by_month.get_mut(&(record.date.year(), record.date.month()))
+                        {
+                            slot.push(record.to_observation(station_id));
+                        }
+                    }
+                }
+                let batches: Vec<MonthRecords> = by_month
+                    .into_iter()
+                    .map(|((year, month), observations)| MonthRecords {
+                        year,
+                        month,
+                        records: match &result {
+                            Ok(_) => Ok(observations),
+                            Err(error) => Err(error.clone()),
+                        },
+                    })
+                    .collect();
+                stream::iter(batches)
+            })
+            .flatten()
+            .boxed(),
+        }
+    }
+}
+
+/// Download one month of a station from the bulk CSV endpoint
+///
+/// # Arguments
+/// * `upstream` - Client to send the request with
+/// * `climate_base_url` - Base URL of the bulk climate data site
+/// * `station_id` - The station ID
+/// * `year` - Year of the month
+/// * `month` - Month (1-12)
+/// * `timeframe` - `BULK_TIMEFRAME_DAILY` or `BULK_TIMEFRAME_HOURLY`
+///
+/// # Returns
+/// * `Result<String, String>` - CSV body, or the error after all retries
+async fn fetch_bulk_csv_month(
+    upstream: &UpstreamClient,
+    climate_base_url: &str,
+    station_id: i64,
+    year: i32,
+    month: u32,
+    timeframe: &str,
+) -> Result<String, String> {
+    let url = format!(
+        "{}/climate_data/bulk_data_e.html",
+        climate_base_url.trim_end_matches('/')
+    );
+    let station_id_str = station_id.to_string();
+    let year_str = year.to_string();
+    let month_str = month.to_string();
+    let query = [
+        ("format", "csv"),
+        ("stationID", station_id_str.as_str()),
+        ("Year", year_str.as_str()),
+        ("Month", month_str.as_str()),
+        ("Day", "1"),
+        ("timeframe", timeframe),
+        ("submit", "Download Data"),
+    ];
+    upstream
+        .get_checked_text_with_retry(&url, &query, check_bulk_csv)
+        .await
+}
+
+/// Reject a bulk CSV response without a "Date/Time" (daily) or "Date/Time (LST)"
+/// (hourly) column, so an error page is not stored as a month without data
+fn check_bulk_csv(body: &str) -> Result<(), String> {
+    let mut rdr = csv::Reader::from_reader(body.as_bytes());
+    let has_date_column = rdr.headers().is_ok_and(|headers| {
+        headers.iter().any(|header| {
+            matches!(
+                header.trim_start_matches('\u{feff}').trim(),
+                "Date/Time" | "Date/Time (LST)"
+            )
+        })
+    });
+    if has_date_column {
+        Ok(())
+    } else {
+        Err("response is not a bulk CSV (no Date/Time column)".to_string())
+    }
+}
+
+/// Download the given months from the bulk CSV endpoint, one request per month, concurrently
+async fn fetch_bulk_csv_months(
+    upstream: &UpstreamClient,
+    climate_base_url: &str,
+    station_id: i64,
+    months: &[(i32, u32)],
+    timeframe: &str,
+) -> Vec<(i32, u32, Result<String, String>)> {
+    let tasks = months.iter().map(|&(year, month)| async move {
+        let result =
+            fetch_bulk_csv_month(upstream, climate_base_url, station_id, year, month, timeframe)
+                .await;
+        (year, month, result)
+    });
+    futures::future::join_all(tasks).await
+}
+
+/// Where `ogc_pages` is in a collection between pages
+struct OgcPaging {
+    url: String,
+    query: Vec<(&'static str, String)>,
+    page_size: usize,
+    /// `next` link of the last page; `None` to request the next page by offset
+    next_link: Option<String>,
+    /// Features received so far
+    offset: usize,
+    /// `numberMatched` of the first page
+    number_matched: Option<u64>,
+    /// Set once the collection is exhausted or a page failed
+    done: bool,
+    visited: std::collections::HashSet<String>,
+}
+
+/// Read the items of an OGC API collection one page at a time
+///
+/// Pages follow the `next` links, or keep paging by `offset` while pages without a
+/// `next` link come back full and `numberMatched` has not been reached. A link that
+/// was already followed ends the stream, as does a page that fails, after its error.
+///
+/// # Arguments
+/// * `upstream` - Client the pages are requested through
+/// * `url` - Items URL of the collection
+/// * `query` - Query parameters of the first page, without `limit` and `offset`
+/// * `page_size` - Requested number of features per page
+///
+/// # Returns
+/// * `BoxStream<Result<serde_json::Value, String>>` - Each page, which has a features array
+fn ogc_pages<'a>(
+    upstream: &'a UpstreamClient,
+    url: String,
+    query: Vec<(&'static str, String)>,
+    page_size: usize,
+) -> BoxStream<'a, Result<serde_json::Value, String>> {
+    let paging = OgcPaging {
+        url,
+        query,
+        page_size: page_size.max(1),
+        next_link: None,
+        offset: 0,
+        number_matched: None,
+        done: false,
+        visited: std::collections::HashSet::new(),
+    };
+    stream::unfold(paging, move |mut paging| async move {
+        if paging.done {
+            return None;
+        }
+        let body = match paging.next_link.take() {
+            Some(link) => {
+                // Guard against a server handing back a link we already followed
+                if !paging.visited.insert(link.clone()) {
+                    return None;
+                }
+                upstream.get_text_with_retry(&link, &[]).await
+            }
+            None => {
+                let limit = paging.page_size.to_string();
+                let offset = paging.offset.to_string();
+                let mut query: Vec<(&str, &str)> =
+                    paging.query.iter().map(|(key, value)| (*key, value.as_str())).collect();
+                query.push(("limit", &limit));
+                query.push(("offset", &offset));
+                upstream.get_text_with_retry(&paging.url, &query).await
+            }
+        };
+        let json = body.and_then(|body| {
+            let json: serde_json::Value = serde_json::from_str(&body)
+                .map_err(|e| format!("invalid response from {}: {}", paging.url, e))?;
+            if !json["features"].is_array() {
+                return Err(format!("No features array in response from {}", paging.url));
+            }
+            Ok(json)
+        });
+        let json = match json {
+            Ok(json) => json,
+            Err(e) => {
+                paging.done = true;
+                return Some((Err(e), paging));
+            }
+        };
+
+        let features = json["features"].as_array().map_or(0, Vec::len);
+        if paging.offset == 0 {
+            paging.number_matched = json["numberMatched"].as_u64();
+        }
+        paging.offset += features;
+        paging.next_link = next_link(&json);
+        if paging.next_link.is_none() {
+            let more_matched = paging
+                .number_matched
+                .is_none_or(|matched| (paging.offset as u64) < matched);
+            paging.done = features < paging.page_size || !more_matched;
+        }
+        if features == 0 {
+            paging.done = true;
+        }
+        Some((Ok(json), paging))
+    })
+    .boxed()
+}
+
+/// Station of one `climate-stations` feature; `None` without an ID, name or coordinates
+fn parse_catalogue_feature(properties: &serde_json::Value) -> Option<SourceStation> {
+    Some(SourceStation {
+        id: properties["STN_ID"].as_i64()?,
+        name: properties["STATION_NAME"].as_str()?.to_string(),
+        // Coordinates come in units of 1e-7 degrees
+        lon_x: properties["LONGITUDE"].as_i64()? as f64 / 10000000.0,
+        lat_y: properties["LATITUDE"].as_i64()? as f64 / 10000000.0,
+        dly_first_date: properties["DLY_FIRST_DATE"].as_str().map(str::to_string),
+        dly_last_date: properties["DLY_LAST_DATE"].as_str().map(str::to_string),
+        metadata: parse_station_metadata(properties),
+    })
+}
+
+/// Calendar months from the month of `start` to the month of `end`, inclusive
+fn months_between(start: NaiveDate, end: NaiveDate) -> Vec<(i32, u32)> {
+    let mut months = Vec::new();
+    let (mut year, mut month) = (start.year(), start.month());
+    while (year, month) <= (end.year(), end.month()) {
+        months.push((year, month));
+        (year, month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+    }
+    months
+}
+
+/// Outcome of importing a directory of bulk CSV files
+#[derive(Debug, Clone)]
+pub struct CsvImportReport {
+    pub files_read: usize,
+    /// Files without the columns to find or create their station
+    pub unmatched_files: Vec<PathBuf>,
+    /// Stations created from the columns of files whose station was not in the database
+    pub stations_created: usize,
+    /// Files that could not be read as text, with the error
+    pub failed_files: Vec<(PathBuf, String)>,
+    pub stations_updated: usize,
+    pub observations_stored: usize,
+}
+
+/// Outcome of importing a directory of GHCN-Daily files
+#[derive(Debug, Clone, Default)]
+pub struct GhcnImportReport {
+    pub files_read: usize,
+    /// `.dly` files whose station is not in `ghcnd-stations.txt`
+    pub unmatched_files: Vec<PathBuf>,
+    /// `.dly` files left out for having no temperature in the history window
+    pub files_without_recent_data: usize,
+    /// `.dly` files that could not be read or parsed, with the error
+    pub failed_files: Vec<(PathBuf, String)>,
+    pub stations_updated: usize,
+    pub observations_stored: usize,
+}
+
+/// Station identification columns from a bulk CSV file
+struct CsvStationInfo {
+    climate_id: Option<String>,
+    name: String,
+    lon: f64,
+    lat: f64,
+}
+
+/// Bulk CSV coordinates are rounded to two decimals, so a station counts as being at
+/// a file's coordinates when both differ by no more than this
+const CSV_COORDINATE_TOLERANCE_DEG: f64 = 0.005;
+
+/// Recursively collect the files with an extension (any case) below a directory
+fn collect_files(dir: &Path, extension: &str, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, extension, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(extension))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Read the Longitude, Latitude, Station Name and Climate ID columns of the first data row
+fn read_csv_station(body: &str) -> Option<CsvStationInfo> {
+    let mut rdr = csv::Reader::from_reader(body.as_bytes());
+    let columns = BulkCsvColumns::from_headers(rdr.headers().ok()?);
+    let record = rdr.records().flatten().next()?;
+    Some(CsvStationInfo {
+        lon: cell(&record, columns.longitude)?.parse().ok()?,
+        lat: cell(&record, columns.latitude)?.parse().ok()?,
+        name: cell(&record, columns.station_name)?.trim().to_string(),
+        climate_id: parse_flag(cell(&record, columns.climate_id)),
+    })
+}
+
+/// Find the station a CSV file belongs to: the one with the same Climate ID, else
+/// one stored without a Climate ID that has the same name and coordinates
+///
+/// Anything less certain is no match, so a file is never stored under a
+/// neighbouring station's ID.
+fn match_station(stations: &[Station], info: &CsvStationInfo) -> Option<i64> {
+    if let Some(climate_id) = &info.climate_id
+        && let Some(station) = stations
+            .iter()
+            .find(|station| station.metadata.climate_id.as_ref() == Some(climate_id))
+    {
+        return Some(station.id);
+    }
+
+    stations
+        .iter()
+        .find(|station| {
+            station.metadata.climate_id.is_none()
+                && station.name.trim().eq_ignore_ascii_case(&info.name)
+                && (station.lon_x - info.lon).abs() <= CSV_COORDINATE_TOLERANCE_DEG
+                && (station.lat_y - info.lat).abs() <= CSV_COORDINATE_TOLERANCE_DEG
+        })
+        .map(|station| station.id)
+}
+
+/// Database station ID for a station created from bulk CSV files
+///
+/// Bulk CSV files carry the Climate ID but not ECCC's numeric station ID, so the
+/// ID is hashed from the Climate ID like a GHCN-Daily one (see `ghcn::station_id`);
+/// the prefix keeps the two kinds apart.
+///
+/// # Arguments
+/// * `climate_id` - ECCC Climate ID, e.g. "6158355"
+///
+/// # Returns
+/// * `i64` - Negative station ID, the same for every import
+pub fn csv_station_id(climate_id: &str) -> i64 {
+    ghcn::station_id(&format!("eccc:{}", climate_id))
+}
+
+/// A station for a CSV file that matches no stored station, or `None` without a
+/// Climate ID to derive its ID from
+///
+/// The Climate ID doubles as `source_id`, which marks the station as created from
+/// CSV files for `Database::delete_csv_stations`.
+fn csv_station(info: &CsvStationInfo) -> Option<Station> {
+    let climate_id = info.climate_id.clone()?;
+    Some(Station {
+        id: csv_station_id(&climate_id),
+        name: info.name.clone(),
+        lon_x: info.lon,
+        lat_y: info.lat,
+        dly_first_date: None,
+        dly_last_date: None,
+        metadata: StationMetadata {
+            climate_id: Some(climate_id.clone()),
+            source: Some(SourceKind::Eccc.as_str().to_string()),
+            source_id: Some(climate_id),
+            ..StationMetadata::default()
+        },
+    })
+}
+
+/// Move a stored record date ("YYYY-MM-DD 00:00:00") to `date` when `date` lies
+/// further out in the direction of `outward`
+fn widen_record_date(
+    stored: &mut Option<String>,
+    date: Option<NaiveDate>,
+    outward: std::cmp::Ordering,
+) {
+    let Some(date) = date else {
+        return;
+    };
+    let current = stored
+        .as_deref()
+        .and_then(|s| NaiveDate::parse_from_str(s.get(..10)?, "%Y-%m-%d").ok());
+    if current.is_none_or(|current| date.cmp(&current) == outward) {
+        *stored = Some(format!("{} 00:00:00", date));
+    }
+}
+
+/// The `href` of the `rel="next"` link of an OGC API Features response
+fn next_link(json: &serde_json::Value) -> Option<String> {
+    json["links"]
+        .as_array()?
+        .iter()
+        .find(|link| link["rel"].as_str() == Some("next"))
+        .and_then(|link| link["href"].as_str())
+        .map(str::to_string)
+}
+
+/// Split an ordered list of months into runs of consecutive calendar months
+fn consecutive_month_runs(months: &[(i32, u32)]) -> Vec<Vec<(i32, u32)>> {
+    let mut runs: Vec<Vec<(i32, u32)>> = Vec::new();
+    for &(year, month) in months {
+        let follows = runs.last().and_then(|run| run.last()).is_some_and(|&(y, m)| {
+            if m == 12 {
+                (year, month) == (y + 1, 1)
+            } else {
+                (year, month) == (y, m + 1)
+            }
+        });
+        match runs.last_mut() {
+            Some(run) if follows => run.push((year, month)),
+            _ => runs.push(vec![(year, month)]),
+        }
+    }
+    runs
+}
+
+/// Last calendar day of a month
+fn last_day_of_month(year: i32, month: u32) -> Option<NaiveDate> {
+    let next_month_start = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    };
+    next_month_start.and_then(|date| date.pred_opt())
+}
+
+/// Whether a response status is worth retrying (rate limiting or a server-side error)
+fn is_retryable_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// Number of days after a month ends before its data is treated as final.
+/// ECCC keeps back-filling the most recent days for a short while.
+const MONTH_SETTLE_DAYS: i64 = 7;
+
+/// Whether a month is old enough that its data will no longer change
+fn is_month_closed(year: i32, month: u32, today: NaiveDate) -> bool {
+    let next_month_start = if month == 12 {
+        NaiveDate::from_ymd_opt(year + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(year, month + 1, 1)
+    };
+    match next_month_start {
+        Some(start) => today >= start + Duration::days(MONTH_SETTLE_DAYS),
+        None => false,
+    }
+}
+
+/// Positions of the bulk CSV columns we read, located by header name so a
+/// reordering of the ECCC layout does not shift values into the wrong field
+#[derive(Debug, Default)]
+struct BulkCsvColumns {
+    date: Option<usize>,
+    max_temp: Option<usize>,
+    max_temp_flag: Option<usize>,
+    min_temp: Option<usize>,
+    min_temp_flag: Option<usize>,
+    mean_temp: Option<usize>,
+    mean_temp_flag: Option<usize>,
+    total_rain: Option<usize>,
+    total_rain_flag: Option<usize>,
+    total_snow: Option<usize>,
+    total_snow_flag: Option<usize>,
+    total_precip: Option<usize>,
+    total_precip_flag: Option<usize>,
+    snow_on_ground: Option<usize>,
+    snow_on_ground_flag: Option<usize>,
+    station_name: Option<usize>,
+    climate_id: Option<usize>,
+    longitude: Option<usize>,
+    latitude: Option<usize>,
+}
+
+impl BulkCsvColumns {
+    fn from_headers(headers: &csv::StringRecord) -> Self {
+        let mut columns = BulkCsvColumns::default();
+        for (i, header) in headers.iter().enumerate() {
+            // The first header carries a UTF-8 byte order mark in ECCC downloads
+            let name = header.trim_start_matches('\u{feff}').trim();
+            // Value headers carry their unit, e.g. "Max Temp (°C)"; flag headers do not
+            let unit = |prefix: &str| {
+                name.strip_prefix(prefix)
+                    .is_some_and(|rest| rest.starts_with(" ("))
+            };
+            let slot = match name {
+                "Date/Time" => &mut columns.date,
+                "Max Temp Flag" => &mut columns.max_temp_flag,
+                "Min Temp Flag" => &mut columns.min_temp_flag,
+                "Mean Temp Flag" => &mut columns.mean_temp_flag,
+                "Total Rain Flag" => &mut columns.total_rain_flag,
+                "Total Snow Flag" => &mut columns.total_snow_flag,
+                "Total Precip Flag" => &mut columns.total_precip_flag,
+                "Snow on Grnd Flag" => &mut columns.snow_on_ground_flag,
+                "Station Name" => &mut columns.station_name,
+                "Climate ID" => &mut columns.climate_id,
+                _ if unit("Max Temp") => &mut columns.max_temp,
+                _ if unit("Min Temp") => &mut columns.min_temp,
+                _ if unit("Mean Temp") => &mut columns.mean_temp,
+                _ if unit("Total Rain") => &mut columns.total_rain,
+                _ if unit("Total Snow") => &mut columns.total_snow,
+                _ if unit("Total Precip") => &mut columns.total_precip,
+                _ if unit("Snow on Grnd") => &mut columns.snow_on_ground,
+                _ if unit("Longitude") => &mut columns.longitude,
+                _ if unit("Latitude") => &mut columns.latitude,
+                _ => continue,
+            };
+            slot.get_or_insert(i);
+        }
+        columns
+    }
+}
+
+/// Get the cell at an optional column position
+fn cell(record: &csv::StringRecord, column: Option<usize>) -> Option<&str> {
+    column.and_then(|i| record.get(i))
+}
+
+/// Parse a flag cell, treating an empty cell as no flag
+fn parse_flag(cell: Option<&str>) -> Option<String> {
+    cell.map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+}
+
+/// Parse an ECCC daily bulk CSV body into daily records
+fn parse_bulk_csv(body: &str) -> Vec<DailyRecord> {
+    let mut rdr = csv::Reader::from_reader(body.as_bytes());
+    let columns = match rdr.headers() {
+        Ok(headers) => BulkCsvColumns::from_headers(headers),
+        Err(_) => return Vec::new(),
+    };
+    if columns.date.is_none() {
+        return Vec::new();
+    }
+
+    let mut records = Vec::new();
+    for record in rdr.records().flatten() {
+        if let Some(date_str) = cell(&record, columns.date)
+            && let Ok(date) = NaiveDate::parse_from_str(date_str, "%Y-%m-%d")
+        {
+            records.push(DailyRecord {
+                date,
+                mean_temp: parse_value(cell(&record, columns.mean_temp)),
+                mean_temp_flag: parse_flag(cell(&record, columns.mean_temp_flag)),
+                min_temp: parse_value(cell(&record, columns.min_temp)),
+                min_temp_flag: parse_flag(cell(&record, columns.min_temp_flag)),
+                max_temp: parse_value(cell(&record, columns.max_temp)),
+                max_temp_flag: parse_flag(cell(&record, columns.max_temp_flag)),
+                total_rain: parse_value(cell(&record, columns.total_rain)),
+                total_rain_flag: parse_flag(cell(&record, columns.total_rain_flag)),
+                total_snow: parse_value(cell(&record, columns.total_snow)),
+                total_snow_flag: parse_flag(cell(&record, columns.total_snow_flag)),
+                total_precip: parse_value(cell(&record, columns.total_precip)),
+                total_precip_flag: parse_flag(cell(&record, columns.total_precip_flag)),
+                snow_on_ground: parse_value(cell(&record, columns.snow_on_ground)),
+                snow_on_ground_flag: parse_flag(cell(&record, columns.snow_on_ground_flag)),
+                mean_temp_imputed: false,
+                min_temp_imputed: false,
+                max_temp_imputed: false,
+            });
+        }
+    }
+    records
+}
+
+/// Parse an ECCC hourly bulk CSV body into hourly observations
+///
+/// Columns are located by header name, like the daily parser. Rows without a
+/// parseable "Date/Time (LST)" value are skipped.
+fn parse_hourly_csv(body: &str, station_id: i64) -> Vec<HourlyObservation> {
+    let mut rdr = csv::Reader::from_reader(body.as_bytes());
+    let Ok(headers) = rdr.headers() else {
+        return Vec::new();
+    };
+
+    let (mut date_time, mut temp, mut temp_flag, mut dew_point, mut dew_point_flag) =
+        (None, None, None, None, None);
+    for (i, header) in headers.iter().enumerate() {
+        let name = header.trim_start_matches('\u{feff}').trim();
+        let unit = |prefix: &str| {
+            name.strip_prefix(prefix)
+                .is_some_and(|rest| rest.starts_with(" ("))
+        };
+        let slot = match name {
+            "Date/Time (LST)" | "Date/Time" => &mut date_time,
+            "Temp Flag" => &mut temp_flag,
+            "Dew Point Temp Flag" => &mut dew_point_flag,
+            _ if unit("Temp") => &mut temp,
+            _ if unit("Dew Point Temp") => &mut dew_point,
+            _ => continue,
+        };
+        slot.get_or_insert(i);
+    }
+    if date_time.is_none() {
+        return Vec::new();
+    }
+
+    let mut observations = Vec::new();
+    for record in rdr.records().flatten() {
+        if let Some(cell_value) = cell(&record, date_time)
+            && let Ok(parsed) = NaiveDateTime::parse_from_str(cell_value, "%Y-%m-%d %H:%M")
+        {
+            observations.push(HourlyObservation {
+                station_id,
+                date_time: parsed.format("%Y-%m-%d %H:%M").to_string(),
+                temp: parse_value(cell(&record, temp)),
+                temp_flag: parse_flag(cell(&record, temp_flag)),
+                dew_point: parse_value(cell(&record, dew_point)),
+                dew_point_flag: parse_flag(cell(&record, dew_point_flag)),
+            });
+        }
+    }
+    observations
+}
+
+/// Read a numeric `climate-daily` property, treating null as missing
+fn json_value(value: &serde_json::Value) -> Option<f64> {
+    value.as_f64()
+}
+
+/// Read a catalogue property that may be a string or a number as trimmed text
+fn json_text(value: &serde_json::Value) -> Option<String> {
+    match value {
+        serde_json::Value::String(s) => parse_flag(Some(s)),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+/// Read a catalogue property that may be a number or a numeric string
+fn json_number(value: &serde_json::Value) -> Option<f64> {
+    json_value(value).or_else(|| value.as_str()?.trim().parse().ok())
+}
+
+/// Province, identifiers, elevation and time zone of a `climate-stations` feature
+fn parse_station_metadata(properties: &serde_json::Value) -> StationMetadata {
+    StationMetadata {
+        province: json_text(&properties["PROV_STATE_TERR_CODE"]),
+        climate_id: json_text(&properties["CLIMATE_IDENTIFIER"]),
+        wmo_id: json_number(&properties["WMO_IDENTIFIER"]).map(|id| id as i64),
+        tc_id: json_text(&properties["TC_IDENTIFIER"]),
+        elevation_m: json_number(&properties["ELEVATION"]),
+        timezone: json_text(&properties["TIMEZONE"]),
+        ..StationMetadata::default()
+    }
+}
+
+/// Read a `climate-daily` flag property, treating null and empty strings as no flag
+fn json_flag(value: &serde_json::Value) -> Option<String> {
+    parse_flag(value.as_str())
+}
+
+/// Convert the properties of one `climate-normals` feature into a monthly mean
+/// temperature normal; `None` for other elements, the annual value (`MONTH` 13)
+/// and months without a value
+fn parse_climate_normal_feature(properties: &serde_json::Value) -> Option<ClimateNormal> {
+    let normal_id = json_text(&properties["NORMAL_ID"])?;
+    if normal_id != NORMAL_ID_MEAN_TEMPERATURE {
+        return None;
+    }
+    let month = json_number(&properties["MONTH"])? as u32;
+    if !(1..=12).contains(&month) {
+        return None;
+    }
+
+    Some(ClimateNormal {
+        station_id: json_number(&properties["STN_ID"])? as i64,
+        month,
+        mean_temp: json_number(&properties["VALUE"])?,
+        normal_code: json_text(&properties["NORMAL_CODE"]),
+        first_year: json_number(&properties["FIRST_YEAR"]).map(|year| year as i32),
+        last_year: json_number(&properties["LAST_YEAR"]).map(|year| year as i32),
+    })
+}
+
+/// Convert the properties of one `climate-daily` feature into a daily record
+fn parse_climate_daily_feature(properties: &serde_json::Value) -> Option<DailyRecord> {
+    // LOCAL_DATE is "YYYY-MM-DD 00:00:00"; only the date part matters
+    let local_date = properties["LOCAL_DATE"].as_str()?;
+    let date = NaiveDate::parse_from_str(local_date.get(..10)?, "%Y-%m-%d").ok()?;
+
+    Some(DailyRecord {
+        date,
+        mean_temp: json_value(&properties["MEAN_TEMPERATURE"]),
+        mean_temp_flag: json_flag(&properties["MEAN_TEMPERATURE_FLAG"]),
+        min_temp: json_value(&properties["MIN_TEMPERATURE"]),
+        min_temp_flag: json_flag(&properties["MIN_TEMPERATURE_FLAG"]),
+        max_temp: json_value(&properties["MAX_TEMPERATURE"]),
+        max_temp_flag: json_flag(&properties["MAX_TEMPERATURE_FLAG"]),
+        total_rain: json_value(&properties["TOTAL_RAIN"]),
+        total_rain_flag: json_flag(&properties["TOTAL_RAIN_FLAG"]),
+        total_snow: json_value(&properties["TOTAL_SNOW"]),
+        total_snow_flag: json_flag(&properties["TOTAL_SNOW_FLAG"]),
+        total_precip: json_value(&properties["TOTAL_PRECIPITATION"]),
+        total_precip_flag: json_flag(&properties["TOTAL_PRECIPITATION_FLAG"]),
+        snow_on_ground: json_value(&properties["SNOW_ON_GROUND"]),
+        snow_on_ground_flag: json_flag(&properties["SNOW_ON_GROUND_FLAG"]),
+        mean_temp_imputed: false,
+        min_temp_imputed: false,
+        max_temp_imputed: false,
+    })
+}
+
+/// Switch dates detected in one season year of a station's record
+#[derive(Debug, Clone, PartialEq)]
+struct SeasonSwitchDates {
+    year: i32,
+    switch_to_summer: Option<NaiveDate>,
+    switch_to_winter: Option<NaiveDate>,
+}
+
+/// Last day of the spring window; spring switch dates are searched up to here
+pub(crate) const SPRING_LAST_ORDINAL: u32 = 180;
+
+/// First day of the fall window; fall switch dates are searched from here on
+pub(crate) const FALL_FIRST_ORDINAL: u32 = 182;
+
+/// Compute the switch-to-summer and switch-to-winter dates of each year in the records
+fn compute_yearly_switch_dates(all_records: &[DailyRecord]) -> Vec<SeasonSwitchDates> {
+    // Group data by year
+    let mut yearly_data: std::collections::BTreeMap<i32, Vec<&DailyRecord>> =
+        std::collections::BTreeMap::new();
+    for record in all_records {
+        yearly_data
+            .entry(record.date.year())
+            .or_default()
+            .push(record);
+    }
+
+    // Calculate metrics for each year
+    let mut seasons = Vec::new();
+
+    for (year, records) in yearly_data.iter_mut() {
+        records.sort_by_key(|r| r.date);
+
+        // Find the day to switch from winter to summer tires:
+        // The day after the last time the mean daily temperature was below 7°C (in spring)
+        // We look for the last occurrence of temp < 7 before we get sustained warmth.
+        // A spring still in progress could see another cold day, so it needs data past the window.
+        let spring_over = records
+            .last()
+            .is_some_and(|r| r.date.ordinal() > SPRING_LAST_ORDINAL);
+        let mut last_below_7_in_spring = None;
+        for (i, record) in records.iter().enumerate() {
+            // Only count the first half of the year (spring transition)
+            if let Some(temp) = record.mean_temp
+                && temp < 7.0
+                && record.date.ordinal() <= SPRING_LAST_ORDINAL
+            {
+                last_below_7_in_spring = Some(i);
+            }
+        }
+        // The switch day is the day after the last below-7 day
+        let switch_to_summer = last_below_7_in_spring
+            .filter(|&idx| spring_over && idx + 1 < records.len())
+            .map(|idx| records[idx + 1].date);
+
+        // Find the day to switch from summer to winter tires:
+        // The FIRST day in fall where temp > 7°C and the following day was < 7°C
+        // Start looking from July onwards (day 182) to avoid catching spring transitions
+        let mut switch_to_winter = None;
+        for i in 0..records.len().saturating_sub(1) {
+            if let Some(day_of_year) = records.get(i).map(|r| r.date.ordinal()) {
+                // Only look at dates from July onwards (after day 182)
+                if day_of_year >= FALL_FIRST_ORDINAL
+                    && let (Some(temp_today), Some(temp_tomorrow)) =
+                        (records[i].mean_temp, records[i + 1].mean_temp)
+                    && temp_today > 7.0
+                    && temp_tomorrow < 7.0
+                {
+                    // This is the first fall transition from above to below 7°C
+                    // The switch day is this day (the last day above 7°C before cold)
+                    switch_to_winter = Some(records[i].date);
+                    break;
+                }
+            }
+        }
+
+        seasons.push(SeasonSwitchDates {
+            year: *year,
+            switch_to_summer,
+            switch_to_winter,
+        });
+    }
+
+    seasons
+}
+
+/// Completeness of the spring and fall windows of one year of a station's records
+///
+/// The spring window is days 1..=`SPRING_LAST_ORDINAL` and the fall window is
+/// `FALL_FIRST_ORDINAL` to the end of the year, both cut off at `today`. A day
+/// is valid when it has a mean temperature; a day is flagged when its mean
+/// temperature carries an "M" (missing) or "E" (estimated) flag. Values filled
+/// in by gap filling do not count as valid; the figures describe what the
+/// station itself reported.
+fn season_completeness(
+    all_records: &[DailyRecord],
+    year: i32,
+    today: NaiveDate,
+) -> (SeasonCompleteness, SeasonCompleteness) {
+    let days_in_year = NaiveDate::from_ymd_opt(year, 12, 31).map_or(365, |d| d.ordinal());
+    // Days of the year that have fully passed; today's values are not in yet
+    let elapsed = match year.cmp(&today.year()) {
+        std::cmp::Ordering::Less => days_in_year,
+        std::cmp::Ordering::Equal => today.ordinal() - 1,
+        std::cmp::Ordering::Greater => 0,
+    };
+
+    let window = |first: u32, last: u32| {
+        let last = last.min(elapsed);
+        let expected = (last + 1).saturating_sub(first);
+        let mut valid = 0;
+        let mut flagged = 0;
+        for record in all_records.iter().filter(|r| {
+            r.date.year() == year && (first..=last).contains(&r.date.ordinal())
+        }) {
+            if record.mean_temp.is_some() && !record.mean_temp_imputed {
+                valid += 1;
+            }
+            if matches!(record.mean_temp_flag.as_deref(), Some("M") | Some("E")) {
+                flagged += 1;
+            }
+        }
+        SeasonCompleteness {
+            valid_pct: if expected == 0 {
+                0.0
+            } else {
+                valid as f64 * 100.0 / expected as f64
+            },
+            flagged_days: flagged,
+        }
+    };
+
+    (
+        window(1, SPRING_LAST_ORDINAL),
+        window(FALL_FIRST_ORDINAL, days_in_year),
+    )
+}
+
+/// Helper struct for daily weather records
+struct DailyRecord {
+    date: NaiveDate,
+    mean_temp: Option<f64>,
+    mean_temp_flag: Option<String>,
+    min_temp: Option<f64>,
+    min_temp_flag: Option<String>,
+    max_temp: Option<f64>,
+    max_temp_flag: Option<String>,
+    total_rain: Option<f64>,
+    total_rain_flag: Option<String>,
+    total_snow: Option<f64>,
+    total_snow_flag: Option<String>,
+    total_precip: Option<f64>,
+    total_precip_flag: Option<String>,
+    snow_on_ground: Option<f64>,
+    snow_on_ground_flag: Option<String>,
+    mean_temp_imputed: bool,
+    min_temp_imputed: bool,
+    max_temp_imputed: bool,
+}
+
+impl DailyRecord {
+    fn from_observation(obs: &DailyObservation) -> Option<Self> {
+        Some(DailyRecord {
+            date: NaiveDate::parse_from_str(&obs.date, "%Y-%m-%d").ok()?,
+            mean_temp: obs.mean_temp,
+            mean_temp_flag: obs.mean_temp_flag.clone(),
+            min_temp: obs.min_temp,
+            min_temp_flag: obs.min_temp_flag.clone(),
+            max_temp: obs.max_temp,
+            max_temp_flag: obs.max_temp_flag.clone(),
+            total_rain: obs.total_rain,
+            total_rain_flag: obs.total_rain_flag.clone(),
+            total_snow: obs.total_snow,
+            total_snow_flag: obs.total_snow_flag.clone(),
+            total_precip: obs.total_precip,
+            total_precip_flag: obs.total_precip_flag.clone(),
+            snow_on_ground: obs.snow_on_ground,
+            snow_on_ground_flag: obs.snow_on_ground_flag.clone(),
+            mean_temp_imputed: obs.mean_temp_imputed,
+            min_temp_imputed: obs.min_temp_imputed,
+            max_temp_imputed: obs.max_temp_imputed,
+        })
+    }
+
+    fn to_observation(&self, station_id: i64) -> DailyObservation {
+        DailyObservation {
+            station_id,
+            date: self.date.format("%Y-%m-%d").to_string(),
+            mean_temp: self.mean_temp,
+            mean_temp_flag: self.mean_temp_flag.clone(),
+            min_temp: self.min_temp,
+            min_temp_flag: self.min_temp_flag.clone(),
+            max_temp: self.max_temp,
+            max_temp_flag: self.max_temp_flag.clone(),
+            total_rain: self.total_rain,
+            total_rain_flag: self.total_rain_flag.clone(),
+            total_snow: self.total_snow,
+            total_snow_flag: self.total_snow_flag.clone(),
+            total_precip: self.total_precip,
+            total_precip_flag: self.total_precip_flag.clone(),
+            snow_on_ground: self.snow_on_ground,
+            snow_on_ground_flag: self.snow_on_ground_flag.clone(),
+            mean_temp_imputed: self.mean_temp_imputed,
+            min_temp_imputed: self.min_temp_imputed,
+            max_temp_imputed: self.max_temp_imputed,
+        }
+    }
+}
+
+/// Parse a numeric CSV cell, treating empty and missing ("M") values as None
+fn parse_value(cell: Option<&str>) -> Option<f64> {
+    cell.and_then(|s| {
+        if s.is_empty() || s == "M" {
+            None
+        } else {
+            s.parse::<f64>().ok()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_month_closed_after_settle_period() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 5).unwrap();
+        assert!(is_month_closed(2024, 1, today));
+        assert!(is_month_closed(2023, 12, today));
+        // February ended less than a week ago and may still be back-filled
+        assert!(!is_month_closed(2024, 2, today));
+        assert!(!is_month_closed(2024, 3, today));
+        assert!(is_month_closed(2024, 2, NaiveDate::from_ymd_opt(2024, 3, 8).unwrap()));
+    }
+
+    #[test]
+    fn test_consecutive_month_runs_cross_year_boundaries() {
+        let months = [(2022, 11), (2022, 12), (2023, 1), (2023, 3), (2023, 4), (2024, 4)];
+        assert_eq!(
+            consecutive_month_runs(&months),
+            vec![
+                vec![(2022, 11), (2022, 12), (2023, 1)],
+                vec![(2023, 3), (2023, 4)],
+                vec![(2024, 4)],
+            ]
+        );
+        assert!(consecutive_month_runs(&[]).is_empty());
+        assert_eq!(last_day_of_month(2024, 2), NaiveDate::from_ymd_opt(2024, 2, 29));
+        assert_eq!(last_day_of_month(2023, 12), NaiveDate::from_ymd_opt(2023, 12, 31));
+    }
+
+    #[test]
+    fn test_parse_hourly_csv_by_header_name() {
+        let body = "\u{feff}\"Longitude (x)\",\"Latitude (y)\",\"Station Name\",\"Climate ID\",\"Date/Time (LST)\",\"Year\",\"Month\",\"Day\",\"Time (LST)\",\"Temp (°C)\",\"Temp Flag\",\"Dew Point Temp (°C)\",\"Dew Point Temp Flag\",\"Rel Hum (%)\"\n\
+            \"-79.40\",\"43.67\",\"TORONTO CITY\",\"6158355\",\"2023-01-01 05:00\",\"2023\",\"01\",\"01\",\"05:00\",\"-2.1\",\"\",\"-4.0\",\"\",\"86\"\n\
+            \"-79.40\",\"43.67\",\"TORONTO CITY\",\"6158355\",\"2023-01-01 06:00\",\"2023\",\"01\",\"01\",\"06:00\",\"\",\"M\",\"\",\"M\",\"\"\n\
+            \"-79.40\",\"43.67\",\"TORONTO CITY\",\"6158355\",\"\",\"\",\"\",\"\",\"\",\"1.0\",\"\",\"\",\"\",\"\"\n";
+
+        let hours = parse_hourly_csv(body, 4607);
+        assert_eq!(hours.len(), 2);
+        assert_eq!(hours[0].station_id, 4607);
+        assert_eq!(hours[0].date_time, "2023-01-01 05:00");
+        assert_eq!(hours[0].temp, Some(-2.1));
+        assert_eq!(hours[0].dew_point, Some(-4.0));
+        assert_eq!(hours[1].temp, None);
+        assert_eq!(hours[1].temp_flag.as_deref(), Some("M"));
+        assert_eq!(hours[1].dew_point_flag.as_deref(), Some("M"));
+    }
+
+    #[test]
+    fn test_match_station_prefers_climate_id() {
+        let station = |id: i64, name: &str, lon_x: f64, climate_id: Option<&str>| Station {
+            id,
+            name: name.to_string(),
+            lon_x,
+            lat_y: 43.67,
+            dly_first_date: None,
+            dly_last_date: None,
+            metadata: StationMetadata {
+                climate_id: climate_id.map(str::to_string),
+                ..Default::default()
+            },
+        };
+        let stations = vec![
+            station(1, "TORONTO CITY", -79.40, None),
+            station(2, "TORONTO CITY CENTRE", -79.90, Some("6158355")),
+        ];
+        let info = |climate_id: Option<&str>| CsvStationInfo {
+            climate_id: climate_id.map(str::to_string),
+            name: "TORONTO CITY".to_string(),
+            lon: -79.40,
+            lat: 43.67,
+        };
+
+        // The Climate ID wins over a same-name station at the exact coordinates
+        assert_eq!(match_station(&stations, &info(Some("6158355"))), Some(2));
+        // Unknown or missing Climate IDs fall back to name and coordinates
+        assert_eq!(match_station(&stations, &info(Some("6150000"))), Some(1));
+        assert_eq!(match_station(&stations, &info(None)), Some(1));
+
+        // A nearby station under another name, or the same name elsewhere, is no match
+        let elsewhere = |name: &str, lon: f64| CsvStationInfo {
+            name: name.to_string(),
+            lon,
+            ..info(None)
+        };
+        assert_eq!(match_station(&stations, &elsewhere("TORONTO ISLAND", -79.40)), None);
+        assert_eq!(match_station(&stations, &elsewhere("TORONTO CITY", -79.42)), None);
+        assert_eq!(match_station(&stations, &elsewhere("toronto city", -79.404)), Some(1));
+    }
+
+    #[test]
+    fn test_parse_station_metadata() {
+        let properties = serde_json::json!({
+            "PROV_STATE_TERR_CODE": "BC",
+            "CLIMATE_IDENTIFIER": "1108447",
+            "WMO_IDENTIFIER": null,
+            "TC_IDENTIFIER": "",
+            "ELEVATION": 4.3,
+            "TIMEZONE": "PST"
+        });
+        let metadata = parse_station_metadata(&properties);
+        assert_eq!(metadata.province.as_deref(), Some("BC"));
+        assert_eq!(metadata.climate_id.as_deref(), Some("1108447"));
+        assert_eq!(metadata.wmo_id, None);
+        assert_eq!(metadata.tc_id, None);
+        assert_eq!(metadata.elevation_m, Some(4.3));
+        assert_eq!(metadata.timezone.as_deref(), Some("PST"));
+        assert_eq!(parse_station_metadata(&serde_json::json!({})), StationMetadata::default());
+    }
+
+    #[test]
+    fn test_parse_climate_daily_feature() {
+        let properties = serde_json::json!({
+            "STN_ID": 4607,
+            "LOCAL_DATE": "2023-01-02 00:00:00",
+            "MEAN_TEMPERATURE": -3.5,
+            "MEAN_TEMPERATURE_FLAG": null,
+            "MIN_TEMPERATURE": null,
+            "MIN_TEMPERATURE_FLAG": "M",
+            "MAX_TEMPERATURE": 1,
+            "TOTAL_SNOW": 4.2,
+            "TOTAL_SNOW_FLAG": "",
+            "SNOW_ON_GROUND": 12,
+            "SNOW_ON_GROUND_FLAG": "E"
+        });
+        let record = parse_climate_daily_feature(&properties).unwrap();
+        assert_eq!(record.date, NaiveDate::from_ymd_opt(2023, 1, 2).unwrap());
+        assert_eq!(record.mean_temp, Some(-3.5));
+        assert_eq!(record.mean_temp_flag, None);
+        assert_eq!(record.min_temp, None);
+        assert_eq!(record.min_temp_flag.as_deref(), Some("M"));
+        assert_eq!(record.max_temp, Some(1.0));
+        assert_eq!(record.total_snow_flag, None);
+        assert_eq!(record.snow_on_ground_flag.as_deref(), Some("E"));
+        assert_eq!(record.total_rain, None);
+
+        let undated = serde_json::json!({ "MEAN_TEMPERATURE": 1.0 });
+        assert!(parse_climate_daily_feature(&undated).is_none());
+    }
+
+    #[test]
+    fn test_parse_climate_normal_feature() {
+        let feature = |normal_id: &str, month: u32, value: &str| {
+            serde_json::json!({
+                "STN_ID": 5051,
+                "NORMAL_ID": normal_id.parse::<i64>().unwrap(),
+                "MONTH": month,
+                "VALUE": serde_json::from_str::<serde_json::Value>(value).unwrap(),
+                "NORMAL_CODE": "A",
+                "FIRST_YEAR": 1991,
+                "LAST_YEAR": 2020
+            })
+        };
+
+        let normal = parse_climate_normal_feature(&feature("1", 4, "7.5")).unwrap();
+        assert_eq!(normal.station_id, 5051);
+        assert_eq!(normal.month, 4);
+        assert_eq!(normal.mean_temp, 7.5);
+        assert_eq!(normal.normal_code.as_deref(), Some("A"));
+        assert_eq!((normal.first_year, normal.last_year), (Some(1991), Some(2020)));
+
+        // Other elements, the annual value and months without a value are skipped
+        assert_eq!(parse_climate_normal_feature(&feature("5", 4, "12.0")), None);
+        assert_eq!(parse_climate_normal_feature(&feature("1", 13, "7.5")), None);
+        assert_eq!(parse_climate_normal_feature(&feature("1", 4, "null")), None);
+    }
+
+    #[test]
+    fn test_daily_source_from_str() {
+        assert_eq!("csv".parse::<DailySource>(), Ok(DailySource::BulkCsv));
+        assert_eq!("OGC".parse::<DailySource>(), Ok(DailySource::ClimateDaily));
+        assert_eq!("climate-daily".parse::<DailySource>(), Ok(DailySource::ClimateDaily));
+        assert!("hourly".parse::<DailySource>().is_err());
+    }
+
+    #[test]
+    fn test_bounding_box_from_str() {
+        let bbox: BoundingBox = "-80.5, 43, -78.9,44.2".parse().unwrap();
+        assert_eq!(bbox.min_lon, -80.5);
+        assert_eq!(bbox.max_lat, 44.2);
+        assert!(bbox.contains(-79.4, 43.67));
+        assert!(!bbox.contains(-73.6, 45.5));
+
+        assert!("-80,43,-79".parse::<BoundingBox>().is_err());
+        assert!("-79,43,-80,44".parse::<BoundingBox>().is_err());
+        assert!("west,43,-79,44".parse::<BoundingBox>().is_err());
+    }
+
+    #[test]
+    fn test_station_filter_matches_every_criterion() {
+        assert!(StationFilter::default().matches(1, None, 0.0, 0.0));
+
+        let filter = StationFilter {
+            station_ids: vec![4607, 5051],
+            provinces: vec!["on".to_string()],
+            bbox: Some("-80,43,-79,44".parse().unwrap()),
+        };
+        assert!(filter.matches(4607, Some("ON"), -79.4, 43.67));
+        assert!(!filter.matches(9999, Some("ON"), -79.4, 43.67));
+        assert!(!filter.matches(5051, Some("QC"), -79.4, 43.67));
+        assert!(!filter.matches(5051, None, -79.4, 43.67));
+        assert!(!filter.matches(4607, Some("ON"), -81.0, 43.67));
+
+        // Only single IDs and provinces can be narrowed down by the server
+        let query = filter.catalogue_query();
+        assert_eq!(
+            query,
+            vec![
+                ("PROV_STATE_TERR_CODE", "ON".to_string()),
+                ("bbox", "-80,43,-79,44".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_station_activity_window() {
+        let now = Utc::now().naive_utc();
+        let recent = (Utc::now() - Duration::days(3))
+            .format("%Y-%m-%d %H:%M:%S")
+            .to_string();
+        let last_year = (Utc::now() - Duration::days(300)).format("%Y-%m-%d").to_string();
+
+        assert!(Aggregator::is_station_active(Some(&recent), Some(7), now));
+        assert!(!Aggregator::is_station_active(Some(&last_year), Some(7), now));
+        assert!(Aggregator::is_station_active(Some(&last_year), Some(365), now));
+        assert!(!Aggregator::is_station_active(None, Some(7), now));
+        // No activity window: decommissioned stations are kept
+        assert!(Aggregator::is_station_active(Some("1990-01-01"), None, now));
+        assert!(Aggregator::is_station_active(None, None, now));
+    }
+
+    #[test]
+    fn test_sufficient_data_threshold() {
+        let first = Some("2015-01-01 00:00:00");
+        let last = Some("2020-01-01");
+
+        assert!(Aggregator::has_sufficient_data(first, last, 1825));
+        assert!(!Aggregator::has_sufficient_data(first, last, 3650));
+        assert!(!Aggregator::has_sufficient_data(None, last, 0));
+    }
+
+    #[test]
+    fn test_backoff_is_exponential_jittered_and_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 10,
+            base_delay: std::time::Duration::from_millis(100),
+            max_delay: std::time::Duration::from_millis(1000),
+        };
+        for _ in 0..20 {
+            let first = policy.backoff(1).as_millis();
+            assert!((50..=100).contains(&first), "first backoff was {}", first);
+            let third = policy.backoff(3).as_millis();
+            assert!((200..=400).contains(&third), "third backoff was {}", third);
+            let capped = policy.backoff(9).as_millis();
+            assert!((500..=1000).contains(&capped), "capped backoff was {}", capped);
+        }
+    }
+
+    #[test]
+    fn test_retryable_statuses() {
+        assert!(is_retryable_status(StatusCode::TOO_MANY_REQUESTS));
+        assert!(is_retryable_status(StatusCode::SERVICE_UNAVAILABLE));
+        assert!(is_retryable_status(StatusCode::INTERNAL_SERVER_ERROR));
+        assert!(!is_retryable_status(StatusCode::NOT_FOUND));
+        assert!(!is_retryable_status(StatusCode::BAD_REQUEST));
+    }
+
+    fn daily(date: NaiveDate, mean_temp: f64) -> DailyRecord {
+        DailyRecord::from_observation(&DailyObservation {
+            date: date.format("%Y-%m-%d").to_string(),
+            mean_temp: Some(mean_temp),
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    /// Daily records for a whole year: cold until `spring`, warm until `fall`, cold after
+    fn year_of_records(year: i32, spring: (u32, u32), fall: (u32, u32)) -> Vec<DailyRecord> {
+        let spring = NaiveDate::from_ymd_opt(year, spring.0, spring.1).unwrap();
+        let fall = NaiveDate::from_ymd_opt(year, fall.0, fall.1).unwrap();
+        NaiveDate::from_ymd_opt(year, 1, 1)
+            .unwrap()
+            .iter_days()
+            .take_while(|date| date.year() == year)
+            .map(|date| {
+                let warm = date >= spring && date <= fall;
+                daily(date, if warm { 12.0 } else { 2.0 })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_update_status_counts_failed_stations_of_every_phase() {
+        let ok = UpdateReport {
+            stations: Some(StationFetchReport::default()),
+            ..UpdateReport::default()
+        };
+        assert_eq!(ok.status(), "ok");
+        let hourly_failed = UpdateReport {
+            hourly_stations_failed: 1,
+            ..ok.clone()
+        };
+        assert_eq!(hourly_failed.status(), "partial");
+        let daily_failed = UpdateReport {
+            stations_failed: 1,
+            ..ok.clone()
+        };
+        assert_eq!(daily_failed.status(), "partial");
+        assert_eq!(UpdateReport::default().status(), "failed");
+    }
+
+    #[test]
+    fn test_yearly_switch_dates_are_kept_per_year() {
+        let mut records = year_of_records(2022, (4, 10), (10, 20));
+        records.extend(year_of_records(2023, (4, 20), (10, 30)));
+
+        let seasons = compute_yearly_switch_dates(&records);
+        assert_eq!(
+            seasons,
+            vec![
+                SeasonSwitchDates {
+                    year: 2022,
+                    switch_to_summer: NaiveDate::from_ymd_opt(2022, 4, 10),
+                    switch_to_winter: NaiveDate::from_ymd_opt(2022, 10, 20),
+                },
+                SeasonSwitchDates {
+                    year: 2023,
+                    switch_to_summer: NaiveDate::from_ymd_opt(2023, 4, 20),
+                    switch_to_winter: NaiveDate::from_ymd_opt(2023, 10, 30),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_season_completeness_counts_valid_and_flagged_days() {
+        let mut records = year_of_records(2023, (4, 10), (10, 17));
+        // Flag 45 spring days: 30 missing ("M") and 15 estimated ("E") but kept
+        for record in records.iter_mut().filter(|r| (11..=40).contains(&r.date.ordinal())) {
+            record.mean_temp = None;
+            record.mean_temp_flag = Some("M".to_string());
+        }
+        for record in records.iter_mut().filter(|r| (41..=55).contains(&r.date.ordinal())) {
+            record.mean_temp_flag = Some("E".to_string());
+        }
+        let today = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+
+        let (spring, fall) = season_completeness(&records, 2023, today);
+        assert!((spring.valid_pct - 150.0 * 100.0 / 180.0).abs() < 1e-9);
+        assert_eq!(spring.flagged_days, 45);
+        assert_eq!(fall.valid_pct, 100.0);
+        assert_eq!(fall.flagged_days, 0);
+
+        // Gap-filled days have a value but were not reported by the station
+        for record in records.iter_mut().filter(|r| r.date.ordinal() >= 319) {
+            record.mean_temp_imputed = true;
+        }
+        let (_, fall) = season_completeness(&records, 2023, today);
+        assert!((fall.valid_pct - 137.0 * 100.0 / 184.0).abs() < 1e-9);
+
+        // A year in progress is measured against the days elapsed so far
+        let partial: Vec<DailyRecord> = year_of_records(2024, (4, 10), (10, 17))
+            .into_iter()
+            .filter(|r| r.date < today)
+            .collect();
+        let (spring, fall) = season_completeness(&partial, 2024, today);
+        assert_eq!(spring.valid_pct, 100.0);
+        assert_eq!(fall.valid_pct, 0.0);
+    }
+
+    #[test]
+    fn test_spring_in_progress_has_no_switch_date() {
+        // Record ends in May: a later cold day could still move the spring date
+        let records: Vec<DailyRecord> = year_of_records(2024, (4, 10), (10, 20))
+            .into_iter()
+            .filter(|r| r.date.month() < 6)
+            .collect();
+
+        let seasons = compute_yearly_switch_dates(&records);
+        assert_eq!(seasons.len(), 1);
+        assert_eq!(seasons[0].switch_to_summer, None);
+        assert_eq!(seasons[0].switch_to_winter, None);
+    }
+
+    #[test]
+    fn test_parse_bulk_csv_by_header_name() {
+        // Columns deliberately out of the usual ECCC order, with a byte order mark
+        let body = "\u{feff}\"Date/Time\",\"Mean Temp Flag\",\"Mean Temp (°C)\",\"Min Temp (°C)\",\"Max Temp (°C)\",\"Snow on Grnd (cm)\",\"Snow on Grnd Flag\",\"Total Rain (mm)\",\"Total Snow (cm)\",\"Total Snow Flag\",\"Total Precip (mm)\"\n\
+            \"2023-01-01\",\"\",\"-3.5\",\"-8.0\",\"1.0\",\"12\",\"E\",\"0.0\",\"4.2\",\"\",\"4.2\"\n\
+            \"2023-01-02\",\"M\",\"\",\"-9.5\",\"\",\"\",\"\",\"\",\"\",\"T\",\"\"\n";
+
+        let records = parse_bulk_csv(body);
+        assert_eq!(records.len(), 2);
+
+        let first = &records[0];
+        assert_eq!(first.date, NaiveDate::from_ymd_opt(2023, 1, 1).unwrap());
+        assert_eq!(first.mean_temp, Some(-3.5));
+        assert_eq!(first.mean_temp_flag, None);
+        assert_eq!(first.min_temp, Some(-8.0));
+        assert_eq!(first.max_temp, Some(1.0));
+        assert_eq!(first.total_rain, Some(0.0));
+        assert_eq!(first.total_snow, Some(4.2));
+        assert_eq!(first.total_precip, Some(4.2));
+        assert_eq!(first.snow_on_ground, Some(12.0));
+        assert_eq!(first.snow_on_ground_flag.as_deref(), Some("E"));
+
+        let second = &records[1];
+        assert_eq!(second.mean_temp, None);
+        assert_eq!(second.mean_temp_flag.as_deref(), Some("M"));
+        assert_eq!(second.min_temp, Some(-9.5));
+        assert_eq!(second.total_snow_flag.as_deref(), Some("T"));
+        // Columns absent from the file are simply missing
+        assert_eq!(second.max_temp_flag, None);
+    }
+
+    #[test]
+    fn test_parse_bulk_csv_without_date_column() {
+        assert!(parse_bulk_csv("\"Mean Temp (°C)\"\n\"5.0\"\n").is_empty());
+    }
+
+    #[test]
+    fn test_check_bulk_csv() {
+        assert!(check_bulk_csv("\u{feff}\"Longitude (x)\",\"Date/Time\"\n").is_ok());
+        assert!(check_bulk_csv("\"Date/Time (LST)\",\"Temp (°C)\"\n").is_ok());
+        assert!(check_bulk_csv("<html><body>Service unavailable</body></html>").is_err());
+        assert!(check_bulk_csv("\"Mean Temp (°C)\"\n\"5.0\"\n").is_err());
+        assert!(check_bulk_csv("").is_err());
     }
 }
@@ -1,5 +1,7 @@
-use crate::db::Database;
+use crate::aggregator::{FALL_FIRST_ORDINAL, SPRING_LAST_ORDINAL};
+use crate::db::{ClimateNormal, Database, HourlyObservation, SeasonCompleteness};
 use crate::nearest::{NearestStationFinder, StationWithDistance};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, Timelike, Utc};
 
 #[derive(Debug, Clone)]
 pub struct Recommendation {
@@ -9,11 +11,15 @@ pub struct Recommendation {
     pub latitude: f64,
     #[allow(dead_code)]
     pub longitude: f64,
+    /// Elevation of the location in metres, if one was given or looked up
+    pub elevation_m: Option<f64>,
     pub stations_analyzed: usize,
     pub stations_requested: usize,
     pub stations: Vec<StationWithDistance>,
     pub summer_stations_with_data: usize,
     pub winter_stations_with_data: usize,
+    pub summer_completeness: CompletenessStats,
+    pub winter_completeness: CompletenessStats,
     pub data_years: DataYearsStats,
 }
 
@@ -24,15 +30,209 @@ pub struct DataYearsStats {
     pub max_span_years: Option<i64>,
 }
 
+/// Completeness of the station-years behind one season's recommendation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompletenessStats {
+    /// Station-years whose date was averaged in
+    pub years_used: usize,
+    /// Station-years left out for falling below `min_valid_pct`
+    pub years_excluded: usize,
+    /// Mean share (0-100) of valid days over the used station-years with known completeness
+    pub avg_valid_pct: Option<f64>,
+    /// Days flagged "M" or "E" across the used station-years
+    pub flagged_days: i64,
+}
+
+/// How a station's season transitions are defined
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum TransitionCriterion {
+    /// Daily mean temperature crossing 7°C, as computed per season year during ingest
+    #[default]
+    DailyMean,
+    /// Mornings with an hourly temperature below `threshold_c` between
+    /// `MORNING_FIRST_HOUR` and `MORNING_LAST_HOUR` (local standard time),
+    /// computed from stored hourly observations
+    MorningCold { threshold_c: f64 },
+    /// Daily mean temperature crossing 7°C on the curve interpolated through the
+    /// station's monthly climate normals, one date per station instead of per year
+    Normals,
+}
+
+/// How elevation differences between a location and its stations are handled
+///
+/// Only used when the location's elevation is known. A station higher than the
+/// location is colder by `lapse_rate_c_per_km` per km, so it warms past the
+/// spring threshold later and cools past the fall threshold earlier; its dates
+/// are shifted by that temperature difference divided by the seasonal rate.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ElevationModel {
+    /// Ranking distance (km) added per metre of elevation difference when picking stations
+    pub penalty_km_per_m: f64,
+    /// Drop in temperature per km of altitude (standard atmosphere: 6.5°C)
+    pub lapse_rate_c_per_km: f64,
+    /// Typical rise of daily mean temperature per day around the spring transition
+    pub spring_warming_c_per_day: f64,
+    /// Typical fall of daily mean temperature per day around the fall transition
+    pub fall_cooling_c_per_day: f64,
+}
+
+impl Default for ElevationModel {
+    fn default() -> Self {
+        ElevationModel {
+            // 100 m of elevation difference weighs like 10 km of distance
+            penalty_km_per_m: 0.1,
+            lapse_rate_c_per_km: 6.5,
+            spring_warming_c_per_day: 0.2,
+            fall_cooling_c_per_day: 0.2,
+        }
+    }
+}
+
+impl ElevationModel {
+    /// Days to add to a station's (summer, winter) switch dates to move them from
+    /// `station_m` to `location_m`
+    fn date_shift_days(&self, station_m: f64, location_m: f64) -> (f64, f64) {
+        // Positive when the station is colder than the location
+        let station_colder_c = (station_m - location_m) / 1000.0 * self.lapse_rate_c_per_km;
+        (
+            -station_colder_c / self.spring_warming_c_per_day,
+            station_colder_c / self.fall_cooling_c_per_day,
+        )
+    }
+}
+
+/// Settings that change how the analyzer derives dates
+#[derive(Debug, Clone)]
+pub struct AnalyzerOptions {
+    pub criterion: TransitionCriterion,
+    /// Station-years whose season window has a lower share (0-100) of valid days are left out
+    pub min_valid_pct: f64,
+    /// Number of past years of hourly observations used by `MorningCold`
+    pub history_years: i32,
+    /// Station ranking and date correction for locations with a known elevation
+    pub elevation: ElevationModel,
+}
+
+impl Default for AnalyzerOptions {
+    fn default() -> Self {
+        AnalyzerOptions {
+            criterion: TransitionCriterion::default(),
+            min_valid_pct: 80.0,
+            history_years: 5,
+            elevation: ElevationModel::default(),
+        }
+    }
+}
+
+/// First hour (LST) of the morning commute window, inclusive
+pub const MORNING_FIRST_HOUR: u32 = 5;
+
+/// Last hour (LST) of the morning commute window, inclusive
+pub const MORNING_LAST_HOUR: u32 = 9;
+
 pub struct Analyzer<'a> {
     db: &'a Database,
     finder: NearestStationFinder,
+    options: AnalyzerOptions,
 }
 
 impl<'a> Analyzer<'a> {
     pub fn new(db: &'a Database) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::with_options(db, AnalyzerOptions::default())
+    }
+
+    /// Create an analyzer with a non-default transition criterion
+    pub fn with_options(
+        db: &'a Database,
+        options: AnalyzerOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
         let finder = NearestStationFinder::new(db)?;
-        Ok(Self { db, finder })
+        Ok(Self {
+            db,
+            finder,
+            options,
+        })
+    }
+
+    /// Per-year (switch to summer, switch to winter) dates of one station under the
+    /// configured criterion, as "YYYY-MM-DD" strings with the completeness of their window
+    #[allow(clippy::type_complexity)]
+    fn station_switch_dates(
+        &self,
+        station_id: i64,
+    ) -> Result<
+        (Vec<(String, Option<SeasonCompleteness>)>, Vec<(String, Option<SeasonCompleteness>)>),
+        Box<dyn std::error::Error>,
+    > {
+        match self.options.criterion {
+            TransitionCriterion::DailyMean => {
+                let data_records = self.db.get_data_by_station(station_id)?;
+                let summer = data_records
+                    .iter()
+                    .filter_map(|data| {
+                        let date = data.switch_to_summer.clone()?;
+                        Some((date, data.spring_completeness))
+                    })
+                    .collect();
+                let winter = data_records
+                    .iter()
+                    .filter_map(|data| {
+                        let date = data.switch_to_winter.clone()?;
+                        Some((date, data.fall_completeness))
+                    })
+                    .collect();
+                Ok((summer, winter))
+            }
+            TransitionCriterion::MorningCold { threshold_c } => {
+                let today = Utc::now().naive_utc().date();
+                let start_date = format!("{}-01-01", today.year() - self.options.history_years);
+                let end_date = today.format("%Y-%m-%d").to_string();
+                let hours = self
+                    .db
+                    .get_hourly_observations(station_id, &start_date, &end_date)?;
+
+                let mut summer = Vec::new();
+                let mut winter = Vec::new();
+                // No completeness figures: hourly gaps are not scored
+                let unscored = |date: NaiveDate| (date.format("%Y-%m-%d").to_string(), None);
+                for (to_summer, to_winter) in morning_switch_dates(&hours, threshold_c) {
+                    summer.extend(to_summer.map(unscored));
+                    winter.extend(to_winter.map(unscored));
+                }
+                Ok((summer, winter))
+            }
+            TransitionCriterion::Normals => {
+                let normals = self.db.get_climate_normals(station_id)?;
+                // A single 30-year date; completeness is already reflected in the normals code
+                let unscored = |day: u32| (reference_date(day), None);
+                let (to_summer, to_winter) = normals_switch_days(&normals);
+                Ok((
+                    to_summer.map(unscored).into_iter().collect(),
+                    to_winter.map(unscored).into_iter().collect(),
+                ))
+            }
+        }
+    }
+
+    /// Days of year (switch to summer, switch to winter) one station recommends on its
+    /// own: its complete enough years averaged, with no elevation shift
+    ///
+    /// # Arguments
+    /// * `station_id` - The station ID
+    ///
+    /// # Returns
+    /// * `Result<(Option<u32>, Option<u32>), Box<dyn std::error::Error>>` - Days of year,
+    ///   `None` for a season without usable data
+    pub fn station_days(
+        &self,
+        station_id: i64,
+    ) -> Result<(Option<u32>, Option<u32>), Box<dyn std::error::Error>> {
+        let (summer, winter) = self.station_switch_dates(station_id)?;
+        let min_valid_pct = self.options.min_valid_pct;
+        let (mut stats, mut valid_pcts) = (CompletenessStats::default(), Vec::new());
+        let summer = filter_complete_years(summer, min_valid_pct, &mut stats, &mut valid_pcts);
+        let winter = filter_complete_years(winter, min_valid_pct, &mut stats, &mut valid_pcts);
+        Ok((average_day_of_year(&summer), average_day_of_year(&winter)))
     }
 
     /// Analyze tire swap dates for a given location
@@ -47,67 +247,252 @@ impl<'a> Analyzer<'a> {
         longitude: f64,
         num_stations: usize,
     ) -> Result<Recommendation, Box<dyn std::error::Error>> {
-        let nearest_stations = self
-            .finder
-            .find_k_nearest(latitude, longitude, num_stations);
+        self.analyze_with_elevation(latitude, longitude, None, num_stations)
+    }
 
-        let mut summer_dates = Vec::new();
-        let mut winter_dates = Vec::new();
+    /// Analyze tire swap dates for a location at a known elevation
+    ///
+    /// Stations are ranked with the `ElevationModel` penalty, and each station's
+    /// dates are shifted to the location's elevation before they are averaged.
+    /// Stations without a stored elevation are used as they are.
+    ///
+    /// # Arguments
+    /// * `latitude` - Latitude of the location
+    /// * `longitude` - Longitude of the location
+    /// * `elevation_m` - Elevation of the location in metres; `None` behaves like `analyze`
+    /// * `num_stations` - Number of nearest stations to consider
+    pub fn analyze_with_elevation(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        elevation_m: Option<f64>,
+        num_stations: usize,
+    ) -> Result<Recommendation, Box<dyn std::error::Error>> {
+        let model = self.options.elevation;
+        let nearest_stations = self.finder.find_k_nearest_with_elevation(
+            latitude,
+            longitude,
+            elevation_m,
+            num_stations,
+            model.penalty_km_per_m,
+        );
+
+        let mut summer_days = Vec::new();
+        let mut winter_days = Vec::new();
         let mut summer_stations_with_data = 0;
         let mut winter_stations_with_data = 0;
+        let mut summer_completeness = CompletenessStats::default();
+        let mut winter_completeness = CompletenessStats::default();
+        let mut summer_valid_pcts = Vec::new();
+        let mut winter_valid_pcts = Vec::new();
+        let min_valid_pct = self.options.min_valid_pct;
 
         for station in &nearest_stations {
-            match self.db.get_data_by_station(station.id) {
-                Ok(data_records) => {
-                    if let Some(data) = data_records.first() {
-                        if let Some(switch_summer) = &data.switch_to_summer {
-                            summer_dates.push(switch_summer.clone());
-                            summer_stations_with_data += 1;
-                        }
-                        if let Some(switch_winter) = &data.switch_to_winter {
-                            winter_dates.push(switch_winter.clone());
-                            winter_stations_with_data += 1;
-                        }
+            match self.station_switch_dates(station.id) {
+                Ok((station_summer, station_winter)) => {
+                    // Patchy years would pull the dates towards whatever days happened to report
+                    let station_summer = filter_complete_years(
+                        station_summer,
+                        min_valid_pct,
+                        &mut summer_completeness,
+                        &mut summer_valid_pcts,
+                    );
+                    let station_winter = filter_complete_years(
+                        station_winter,
+                        min_valid_pct,
+                        &mut winter_completeness,
+                        &mut winter_valid_pcts,
+                    );
+
+                    // Move the station's dates to the location's elevation
+                    let (summer_shift, winter_shift) =
+                        match (station.metadata.elevation_m, elevation_m) {
+                            (Some(station_m), Some(location_m)) => {
+                                model.date_shift_days(station_m, location_m)
+                            }
+                            _ => (0.0, 0.0),
+                        };
+
+                    // One date per season year; average the years into one date per station
+                    if let Some(day) = average_day_of_year(&station_summer) {
+                        summer_days.push(shift_day(day, summer_shift));
+                        summer_stations_with_data += 1;
+                    }
+                    if let Some(day) = average_day_of_year(&station_winter) {
+                        winter_days.push(shift_day(day, winter_shift));
+                        winter_stations_with_data += 1;
                     }
                 }
                 Err(_) => continue,
             }
         }
 
-        let switch_to_summer = if !summer_dates.is_empty() {
-            calculate_average_date(&summer_dates)
-        } else {
-            None
-        };
-
-        let switch_to_winter = if !winter_dates.is_empty() {
-            calculate_average_date(&winter_dates)
-        } else {
-            None
-        };
+        // Every station carries the same weight, however many years it has
+        let switch_to_summer = mean_day(&summer_days).map(day_of_year_to_date);
+        let switch_to_winter = mean_day(&winter_days).map(day_of_year_to_date);
 
         let data_years = calculate_data_years_stats(&nearest_stations);
+        summer_completeness.avg_valid_pct = mean(&summer_valid_pcts);
+        winter_completeness.avg_valid_pct = mean(&winter_valid_pcts);
 
         Ok(Recommendation {
             switch_to_summer,
             switch_to_winter,
             latitude,
             longitude,
+            elevation_m,
             stations_analyzed: nearest_stations.len(),
             stations_requested: num_stations,
             stations: nearest_stations,
             summer_stations_with_data,
             winter_stations_with_data,
+            summer_completeness,
+            winter_completeness,
             data_years,
         })
     }
 }
 
-fn parse_year_from_date(date_str: &str) -> Option<i64> {
-    if let Some(year_part) = date_str.split('-').next() {
-        if year_part.len() == 4 {
-            return year_part.parse().ok();
+/// Per-year switch dates from the morning-commute criterion
+///
+/// A day is a cold morning when any hourly temperature between
+/// `MORNING_FIRST_HOUR` and `MORNING_LAST_HOUR` is below `threshold_c`; days
+/// without a reading in that window are ignored. Mirroring the daily-mean rule,
+/// summer tires go on the day after the last cold morning of spring (only once
+/// the spring window is over), and winter tires go on the last mild morning
+/// before the first cold one from July onwards.
+///
+/// # Returns
+/// * `Vec<(Option<NaiveDate>, Option<NaiveDate>)>` - (switch to summer, switch to winter) per year
+fn morning_switch_dates(
+    hours: &[HourlyObservation],
+    threshold_c: f64,
+) -> Vec<(Option<NaiveDate>, Option<NaiveDate>)> {
+    // Classify each day by its coldest morning reading
+    let mut mornings: std::collections::BTreeMap<NaiveDate, bool> =
+        std::collections::BTreeMap::new();
+    for hour in hours {
+        let (Ok(date_time), Some(temp)) = (
+            NaiveDateTime::parse_from_str(&hour.date_time, "%Y-%m-%d %H:%M"),
+            hour.temp,
+        ) else {
+            continue;
+        };
+        if !(MORNING_FIRST_HOUR..=MORNING_LAST_HOUR).contains(&date_time.hour()) {
+            continue;
         }
+        let cold = mornings.entry(date_time.date()).or_insert(false);
+        *cold |= temp < threshold_c;
+    }
+
+    let mut years: std::collections::BTreeMap<i32, Vec<(NaiveDate, bool)>> =
+        std::collections::BTreeMap::new();
+    for (date, cold) in mornings {
+        years.entry(date.year()).or_default().push((date, cold));
+    }
+
+    years
+        .values()
+        .map(|days| {
+            let spring_over = days
+                .last()
+                .is_some_and(|(date, _)| date.ordinal() > SPRING_LAST_ORDINAL);
+            let last_cold_in_spring = days
+                .iter()
+                .rposition(|(date, cold)| *cold && date.ordinal() <= SPRING_LAST_ORDINAL);
+            let switch_to_summer = last_cold_in_spring
+                .filter(|&idx| spring_over && idx + 1 < days.len())
+                .map(|idx| days[idx + 1].0);
+
+            let switch_to_winter = days
+                .windows(2)
+                .find(|pair| {
+                    pair[0].0.ordinal() >= FALL_FIRST_ORDINAL && !pair[0].1 && pair[1].1
+                })
+                .map(|pair| pair[0].0);
+
+            (switch_to_summer, switch_to_winter)
+        })
+        .collect()
+}
+
+/// Mean temperature threshold of the switch dates (°C), as used during ingest
+const SWITCH_THRESHOLD_C: f64 = 7.0;
+
+/// Cumulative days before each month of a non-leap year
+const DAYS_BEFORE_MONTH: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+
+/// Switch days of year from a station's monthly climate normals
+///
+/// Each normal is placed at the middle of its month and the daily curve is
+/// interpolated linearly between them, wrapping from December to January.
+/// The daily-mean rule is then applied to the curve: summer tires go on the day
+/// after the last day of the spring window below 7°C, and winter tires go on
+/// the last day above 7°C before the first day below it from July onwards.
+///
+/// # Returns
+/// * `(Option<u32>, Option<u32>)` - (switch to summer, switch to winter) days of a
+///   non-leap year; both `None` unless all twelve months have a normal
+fn normals_switch_days(normals: &[ClimateNormal]) -> (Option<u32>, Option<u32>) {
+    let mut monthly = [None; 12];
+    for normal in normals {
+        if (1..=12).contains(&normal.month) {
+            monthly[normal.month as usize - 1] = Some(normal.mean_temp);
+        }
+    }
+    let Some(monthly) = monthly.into_iter().collect::<Option<Vec<f64>>>() else {
+        return (None, None);
+    };
+    let curve = normals_daily_curve(&monthly);
+    let temp = |day: u32| curve[day as usize - 1];
+
+    let switch_to_summer = (1..=SPRING_LAST_ORDINAL)
+        .rev()
+        .find(|&day| temp(day) < SWITCH_THRESHOLD_C)
+        .map(|day| day + 1);
+    let switch_to_winter = (FALL_FIRST_ORDINAL..365)
+        .find(|&day| temp(day) > SWITCH_THRESHOLD_C && temp(day + 1) < SWITCH_THRESHOLD_C);
+    (switch_to_summer, switch_to_winter)
+}
+
+/// Daily mean temperatures (days 1-365) interpolated through twelve monthly means
+fn normals_daily_curve(monthly: &[f64]) -> Vec<f64> {
+    let days_in_months = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
+    let mid_month =
+        |i: usize| DAYS_BEFORE_MONTH[i] as f64 + (days_in_months[i] as f64 + 1.0) / 2.0;
+
+    (1..=365)
+        .map(|day| {
+            let day = day as f64;
+            // The last month whose middle is not after `day`, wrapping before mid-January
+            let before = (0..12).rev().find(|&i| mid_month(i) <= day).unwrap_or(11);
+            let after = (before + 1) % 12;
+            let (start, mut end) = (mid_month(before), mid_month(after));
+            let mut offset = day - start;
+            if end <= start {
+                end += 365.0;
+            }
+            if offset < 0.0 {
+                offset += 365.0;
+            }
+            let fraction = offset / (end - start);
+            monthly[before] + (monthly[after] - monthly[before]) * fraction
+        })
+        .collect()
+}
+
+/// "YYYY-MM-DD" in a non-leap reference year for a day of year
+fn reference_date(day: u32) -> String {
+    NaiveDate::from_yo_opt(2001, day)
+        .map(|date| date.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+fn parse_year_from_date(date_str: &str) -> Option<i64> {
+    if let Some(year_part) = date_str.split('-').next()
+        && year_part.len() == 4
+    {
+        return year_part.parse().ok();
     }
     None
 }
@@ -125,10 +510,10 @@ fn calculate_data_years_stats(stations: &[StationWithDistance]) -> DataYearsStat
             .as_deref()
             .and_then(parse_year_from_date);
 
-        if let (Some(start), Some(end)) = (start_year, end_year) {
-            if end >= start {
-                spans.push(end - start + 1);
-            }
+        if let (Some(start), Some(end)) = (start_year, end_year)
+            && end >= start
+        {
+            spans.push(end - start + 1);
         }
     }
 
@@ -163,7 +548,7 @@ fn parse_date_to_day_of_year(date_str: &str) -> Option<u32> {
 
             // Calculate day of year (assuming non-leap year for simplicity)
             let days_before_month = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
-            if month >= 1 && month <= 12 {
+            if (1..=12).contains(&month) {
                 return Some(days_before_month[(month - 1) as usize] + day);
             }
         }
@@ -199,7 +584,7 @@ fn parse_date_to_day_of_year(date_str: &str) -> Option<u32> {
 }
 
 /// Convert day of year back to "Month Day" format
-fn day_of_year_to_date(day: u32) -> String {
+pub fn day_of_year_to_date(day: u32) -> String {
     let days_in_months = [31, 28, 31, 30, 31, 30, 31, 31, 30, 31, 30, 31];
     let month_names = [
         "January",
@@ -226,20 +611,64 @@ fn day_of_year_to_date(day: u32) -> String {
     "Invalid date".to_string()
 }
 
-/// Calculate the average date from a list of date strings
-fn calculate_average_date(dates: &[String]) -> Option<String> {
+/// Calculate the average day of year from a list of date strings
+fn average_day_of_year(dates: &[String]) -> Option<u32> {
     let days: Vec<u32> = dates
         .iter()
         .filter_map(|d| parse_date_to_day_of_year(d))
         .collect();
 
+    mean_day(&days)
+}
+
+/// Keep the dates whose season window is complete enough, tallying kept and
+/// excluded station-years into `stats` and the kept valid-day shares into `valid_pcts`
+///
+/// Dates without completeness figures (stored before it was tracked, or from
+/// the hourly criterion) are always kept.
+fn filter_complete_years(
+    dates: Vec<(String, Option<SeasonCompleteness>)>,
+    min_valid_pct: f64,
+    stats: &mut CompletenessStats,
+    valid_pcts: &mut Vec<f64>,
+) -> Vec<String> {
+    let mut kept = Vec::new();
+    for (date, completeness) in dates {
+        if let Some(c) = completeness {
+            if c.valid_pct < min_valid_pct {
+                stats.years_excluded += 1;
+                continue;
+            }
+            valid_pcts.push(c.valid_pct);
+            stats.flagged_days += c.flagged_days;
+        }
+        stats.years_used += 1;
+        kept.push(date);
+    }
+    kept
+}
+
+/// Mean of a list of values
+fn mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    Some(values.iter().sum::<f64>() / values.len() as f64)
+}
+
+/// Move a day of year by a (possibly fractional) number of days, staying within the year
+fn shift_day(day: u32, shift_days: f64) -> u32 {
+    (day as f64 + shift_days).round().clamp(1.0, 365.0) as u32
+}
+
+/// Integer mean of a list of days of year
+fn mean_day(days: &[u32]) -> Option<u32> {
     if days.is_empty() {
         return None;
     }
 
     let sum: u32 = days.iter().sum();
-    let avg = sum / days.len() as u32;
-    Some(day_of_year_to_date(avg))
+    Some(sum / days.len() as u32)
 }
 
 #[cfg(test)]
@@ -258,6 +687,7 @@ mod tests {
                 distance_km: 1.0,
                 dly_first_date: Some("2010-01-01".to_string()),
                 dly_last_date: Some("2019-12-31".to_string()),
+                metadata: Default::default(),
             },
             StationWithDistance {
                 id: 2,
@@ -267,6 +697,7 @@ mod tests {
                 distance_km: 2.0,
                 dly_first_date: Some("2015-01-01".to_string()),
                 dly_last_date: Some("2020-12-31".to_string()),
+                metadata: Default::default(),
             },
         ];
 
@@ -300,7 +731,7 @@ mod tests {
     }
 
     #[test]
-    fn test_analyze_uses_latest_year_data_per_station() {
+    fn test_analyze_averages_years_per_station() {
         let db = Database::new_in_memory().unwrap();
         db.initialize_schema().unwrap();
 
@@ -315,7 +746,267 @@ mod tests {
         let analyzer = Analyzer::new(&db).unwrap();
         let rec = analyzer.analyze(43.7, -79.4, 1).unwrap();
 
-        assert_eq!(rec.switch_to_summer.as_deref(), Some("May 15"));
-        assert_eq!(rec.switch_to_winter.as_deref(), Some("November 15"));
+        // April 1 (day 91) and May 15 (day 135) average to day 113
+        assert_eq!(rec.switch_to_summer.as_deref(), Some("April 23"));
+        // October 1 (day 274) and November 15 (day 319) average to day 296
+        assert_eq!(rec.switch_to_winter.as_deref(), Some("October 23"));
+    }
+
+    /// Hourly readings for 05:00-09:00 of every day of a year; mornings are cold
+    /// (-2°C at 06:00, mild otherwise) before `spring_day` and from `fall_day` on
+    fn morning_hours(
+        station_id: i64,
+        year: i32,
+        spring_day: u32,
+        fall_day: u32,
+    ) -> Vec<HourlyObservation> {
+        let mut hours = Vec::new();
+        let mut date = NaiveDate::from_ymd_opt(year, 1, 1).unwrap();
+        while date.year() == year {
+            let cold = date.ordinal() < spring_day || date.ordinal() >= fall_day;
+            for hour in MORNING_FIRST_HOUR..=MORNING_LAST_HOUR {
+                let temp = if cold && hour == 6 { -2.0 } else { 4.0 };
+                hours.push(HourlyObservation {
+                    station_id,
+                    date_time: format!("{} {:02}:00", date.format("%Y-%m-%d"), hour),
+                    temp: Some(temp),
+                    ..Default::default()
+                });
+            }
+            date = date.succ_opt().unwrap();
+        }
+        // Afternoon frost outside the window does not count
+        hours.push(HourlyObservation {
+            station_id,
+            date_time: format!("{}-07-15 15:00", year),
+            temp: Some(-10.0),
+            ..Default::default()
+        });
+        hours
+    }
+
+    #[test]
+    fn test_morning_switch_dates() {
+        // 2023 is not a leap year: day 100 is April 10, day 300 is October 27
+        let hours = morning_hours(1, 2023, 100, 300);
+        let seasons = morning_switch_dates(&hours, 0.0);
+        assert_eq!(
+            seasons,
+            vec![(NaiveDate::from_ymd_opt(2023, 4, 10), NaiveDate::from_ymd_opt(2023, 10, 26))]
+        );
+
+        // Nothing is below -5°C, so there are no transitions at that threshold
+        assert_eq!(morning_switch_dates(&hours, -5.0), vec![(None, None)]);
+    }
+
+    #[test]
+    fn test_analyze_with_morning_cold_criterion() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+
+        db.insert_station(1, &"Station 1".to_string(), -79.4, 43.7, None, None)
+            .unwrap();
+        // Daily-mean dates exist too, but the hourly criterion ignores them
+        db.insert_data(1, 2023, Some("2023-03-01"), Some("2023-12-01"))
+            .unwrap();
+        let year = Utc::now().year() - 1;
+        let spring_day = 100 + u32::from(NaiveDate::from_ymd_opt(year, 1, 1).unwrap().leap_year());
+        db.insert_hourly_observations(&morning_hours(1, year, spring_day, 300))
+            .unwrap();
+
+        let options = AnalyzerOptions {
+            criterion: TransitionCriterion::MorningCold { threshold_c: 0.0 },
+            ..AnalyzerOptions::default()
+        };
+        let analyzer = Analyzer::with_options(&db, options).unwrap();
+        let rec = analyzer.analyze(43.7, -79.4, 1).unwrap();
+
+        assert_eq!(rec.summer_stations_with_data, 1);
+        assert_eq!(rec.switch_to_summer.as_deref(), Some("April 10"));
+        assert!(rec.switch_to_winter.as_deref().unwrap().starts_with("October 2"));
+
+        let daily = Analyzer::new(&db).unwrap().analyze(43.7, -79.4, 1).unwrap();
+        assert_eq!(daily.switch_to_summer.as_deref(), Some("March 1"));
+    }
+
+    /// Monthly mean temperature normals shaped like Toronto's (1991-2020)
+    fn toronto_normals(station_id: i64) -> Vec<ClimateNormal> {
+        [-5.5, -4.5, 0.1, 6.7, 12.9, 18.4, 21.5, 20.6, 16.4, 9.9, 4.2, -1.7]
+            .into_iter()
+            .zip(1..)
+            .map(|(mean_temp, month)| ClimateNormal {
+                station_id,
+                month,
+                mean_temp,
+                ..ClimateNormal::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_normals_switch_days() {
+        let normals = toronto_normals(1);
+        // 7°C is crossed 1.5 days after mid-April (day 105.5) and 15.5 days after
+        // mid-October (day 289)
+        assert_eq!(normals_switch_days(&normals), (Some(107), Some(304)));
+
+        // The curve wraps around the new year: January 1 is 16 of the 31 days from
+        // mid-December (day 350) to mid-January (day 16)
+        let mut monthly = [0.0; 12];
+        monthly[11] = 31.0;
+        let curve = normals_daily_curve(&monthly);
+        assert_eq!(curve[349], 31.0);
+        assert_eq!(curve[0], 15.0);
+        assert_eq!(curve[15], 0.0);
+
+        // Every month is needed, and a station that never gets cold never switches
+        assert_eq!(normals_switch_days(&normals[..11]), (None, None));
+        let warm: Vec<_> = normals
+            .iter()
+            .map(|n| ClimateNormal {
+                mean_temp: n.mean_temp + 20.0,
+                ..n.clone()
+            })
+            .collect();
+        assert_eq!(normals_switch_days(&warm), (None, None));
+    }
+
+    #[test]
+    fn test_analyze_with_normals_criterion() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+        db.insert_station(1, &"Station 1".to_string(), -79.4, 43.7, None, None)
+            .unwrap();
+        db.insert_station(2, &"Station 2".to_string(), -79.5, 43.7, None, None)
+            .unwrap();
+        // Daily-mean dates exist too, but the normals criterion ignores them
+        db.insert_data(1, 2023, Some("2023-03-01"), Some("2023-12-01"))
+            .unwrap();
+        db.replace_climate_normals(1, &toronto_normals(1), Utc::now().naive_utc())
+            .unwrap();
+
+        let options = AnalyzerOptions {
+            criterion: TransitionCriterion::Normals,
+            ..AnalyzerOptions::default()
+        };
+        let analyzer = Analyzer::with_options(&db, options).unwrap();
+        let rec = analyzer.analyze(43.7, -79.4, 2).unwrap();
+
+        // Station 2 has no normals and is left out
+        assert_eq!(rec.summer_stations_with_data, 1);
+        assert_eq!(rec.switch_to_summer.as_deref(), Some("April 17"));
+        assert_eq!(rec.switch_to_winter.as_deref(), Some("October 31"));
+        assert_eq!(analyzer.station_days(1).unwrap(), (Some(107), Some(304)));
+    }
+
+    #[test]
+    fn test_analyze_excludes_incomplete_years() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+
+        db.insert_station(1, &"Station 1".to_string(), -79.4, 43.7, None, None)
+            .unwrap();
+        let complete = SeasonCompleteness { valid_pct: 98.0, flagged_days: 3 };
+        let patchy = SeasonCompleteness { valid_pct: 40.0, flagged_days: 108 };
+
+        // 2021 is complete, 2022 had a patchy spring, 2020 predates completeness tracking
+        db.insert_data(1, 2020, Some("2020-04-20"), None).unwrap();
+        db.insert_data(1, 2021, Some("2021-04-10"), Some("2021-10-20"))
+            .unwrap();
+        db.set_season_completeness(1, 2021, &complete, &complete).unwrap();
+        db.insert_data(1, 2022, Some("2022-06-01"), Some("2022-10-20"))
+            .unwrap();
+        db.set_season_completeness(1, 2022, &patchy, &complete).unwrap();
+
+        let analyzer = Analyzer::new(&db).unwrap();
+        let rec = analyzer.analyze(43.7, -79.4, 1).unwrap();
+
+        // April 20 (day 110) and April 10 (day 100); June 1 is left out
+        assert_eq!(rec.switch_to_summer.as_deref(), Some("April 15"));
+        assert_eq!(
+            rec.summer_completeness,
+            CompletenessStats {
+                years_used: 2,
+                years_excluded: 1,
+                avg_valid_pct: Some(98.0),
+                flagged_days: 3,
+            }
+        );
+        assert_eq!(rec.winter_completeness.years_used, 2);
+        assert_eq!(rec.winter_completeness.flagged_days, 6);
+
+        // A threshold of 0 keeps every year
+        let options = AnalyzerOptions {
+            min_valid_pct: 0.0,
+            ..AnalyzerOptions::default()
+        };
+        let rec = Analyzer::with_options(&db, options)
+            .unwrap()
+            .analyze(43.7, -79.4, 1)
+            .unwrap();
+        assert_eq!(rec.summer_completeness.years_excluded, 0);
+        assert_eq!(rec.summer_completeness.avg_valid_pct, Some(69.0));
+    }
+
+    #[test]
+    fn test_analyze_weights_stations_equally() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+
+        db.insert_station(1, &"Station 1".to_string(), -79.4, 43.7, None, None)
+            .unwrap();
+        db.insert_station(2, &"Station 2".to_string(), -79.5, 43.8, None, None)
+            .unwrap();
+
+        // Three years at station 1 all on April 10 (day 100)
+        for year in 2021..=2023 {
+            db.insert_data(1, year, Some(&format!("{}-04-10", year)), None)
+                .unwrap();
+        }
+        // One year at station 2 on April 30 (day 120)
+        db.insert_data(2, 2023, Some("2023-04-30"), None).unwrap();
+
+        let analyzer = Analyzer::new(&db).unwrap();
+        let rec = analyzer.analyze(43.7, -79.4, 2).unwrap();
+
+        assert_eq!(rec.summer_stations_with_data, 2);
+        assert_eq!(rec.switch_to_summer.as_deref(), Some("April 20"));
+    }
+
+    #[test]
+    fn test_analyze_shifts_dates_to_location_elevation() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+
+        db.insert_station(1, &"Station 1".to_string(), -116.0, 51.0, None, None)
+            .unwrap();
+        let metadata = crate::db::StationMetadata {
+            elevation_m: Some(900.0),
+            ..Default::default()
+        };
+        db.set_station_metadata(1, &metadata).unwrap();
+        // April 20 (day 110) and October 10 (day 283)
+        db.insert_data(1, 2023, Some("2023-04-20"), Some("2023-10-10"))
+            .unwrap();
+
+        let analyzer = Analyzer::new(&db).unwrap();
+        let rec = analyzer.analyze(51.0, -116.0, 1).unwrap();
+        assert_eq!(rec.switch_to_summer.as_deref(), Some("April 20"));
+        assert_eq!(rec.elevation_m, None);
+
+        // 400 m lower is 2.6°C warmer: 13 days at 0.2°C per day
+        let rec = analyzer
+            .analyze_with_elevation(51.0, -116.0, Some(500.0), 1)
+            .unwrap();
+        assert_eq!(rec.elevation_m, Some(500.0));
+        assert_eq!(rec.switch_to_summer.as_deref(), Some("April 7"));
+        assert_eq!(rec.switch_to_winter.as_deref(), Some("October 23"));
+
+        // Same elevation: no shift
+        let rec = analyzer
+            .analyze_with_elevation(51.0, -116.0, Some(900.0), 1)
+            .unwrap();
+        assert_eq!(rec.switch_to_summer.as_deref(), Some("April 20"));
+        assert_eq!(rec.switch_to_winter.as_deref(), Some("October 10"));
     }
 }
@@ -1,19 +1,21 @@
 use axum::{
     extract::{Query, State},
-    http::StatusCode,
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Json},
     routing::get,
     Router,
 };
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use std::{
     collections::HashMap,
     sync::{Arc, Mutex},
     time::{Duration, Instant},
 };
 
-use crate::analyzer::{Analyzer, Recommendation};
-use crate::db::Database;
+use crate::analyzer::{Analyzer, AnalyzerOptions, Recommendation, TransitionCriterion};
+use crate::db::{Database, IngestRun};
+use crate::elevation::Dem;
 
 const GEOCODE_CACHE_TTL: Duration = Duration::from_secs(60 * 60);
 
@@ -24,6 +26,12 @@ pub struct AppState {
     pub geocode_client: reqwest::Client,
     pub geocode_cache: Arc<Mutex<HashMap<String, CacheEntry>>>,
     pub geocode_base_url: String,
+    /// Bearer token required by `/api/admin/*`; those routes answer 403 when `None`
+    pub admin_token: Option<String>,
+    /// Elevation grid for requests that do not give an elevation
+    pub dem: Option<Arc<Dem>>,
+    /// Analyzer settings from the command line; requests only choose the criterion
+    pub analyzer_options: AnalyzerOptions,
 }
 
 /// Query parameters for the optimal dates endpoint
@@ -36,6 +44,13 @@ pub struct OptimalDatesQuery {
     /// Number of nearest stations to consider (default: 5)
     #[serde(default = "default_num_stations")]
     num_stations: usize,
+    /// Use the morning-commute frost criterion with this threshold (°C) instead of daily means
+    morning_threshold: Option<f64>,
+    /// Derive dates from the stations' climate normals instead of their daily data
+    #[serde(default)]
+    normals: bool,
+    /// Elevation of the location in metres; looked up in the DEM when omitted
+    elevation: Option<f64>,
 }
 
 fn default_num_stations() -> usize {
@@ -47,6 +62,8 @@ fn default_num_stations() -> usize {
 pub struct OptimalDatesResponse {
     pub latitude: f64,
     pub longitude: f64,
+    /// Elevation the dates were corrected to, if known
+    pub elevation_m: Option<f64>,
     pub switch_to_summer: Option<String>,
     pub switch_to_winter: Option<String>,
     pub stations_analyzed: usize,
@@ -63,6 +80,14 @@ impl From<Recommendation> for OptimalDatesResponse {
                 id: station.id,
                 name: station.name.clone(),
                 distance_km: station.distance_km,
+                province: station.metadata.province.clone(),
+                climate_id: station.metadata.climate_id.clone(),
+                wmo_id: station.metadata.wmo_id,
+                tc_id: station.metadata.tc_id.clone(),
+                elevation_m: station.metadata.elevation_m,
+                timezone: station.metadata.timezone.clone(),
+                source: station.metadata.source_name().to_string(),
+                source_id: station.metadata.source_id.clone(),
             })
             .collect();
 
@@ -74,6 +99,7 @@ impl From<Recommendation> for OptimalDatesResponse {
         Self {
             latitude: rec.latitude,
             longitude: rec.longitude,
+            elevation_m: rec.elevation_m,
             switch_to_summer: rec.switch_to_summer,
             switch_to_winter: rec.switch_to_winter,
             stations_analyzed: rec.stations_analyzed,
@@ -87,10 +113,18 @@ impl From<Recommendation> for OptimalDatesResponse {
                 summer: SeasonalQuality {
                     stations_with_data: rec.summer_stations_with_data,
                     coverage_pct: summer_coverage,
+                    years_used: rec.summer_completeness.years_used,
+                    years_excluded: rec.summer_completeness.years_excluded,
+                    valid_days_pct: rec.summer_completeness.avg_valid_pct,
+                    flagged_days: rec.summer_completeness.flagged_days,
                 },
                 winter: SeasonalQuality {
                     stations_with_data: rec.winter_stations_with_data,
                     coverage_pct: winter_coverage,
+                    years_used: rec.winter_completeness.years_used,
+                    years_excluded: rec.winter_completeness.years_excluded,
+                    valid_days_pct: rec.winter_completeness.avg_valid_pct,
+                    flagged_days: rec.winter_completeness.flagged_days,
                 },
                 data_years: DataYearsSummary {
                     min_span_years: rec.data_years.min_span_years,
@@ -102,6 +136,98 @@ impl From<Recommendation> for OptimalDatesResponse {
     }
 }
 
+/// Query parameters for the ingest runs endpoint
+#[derive(Debug, Deserialize)]
+pub struct IngestRunsQuery {
+    /// Number of most recent runs to return (default: 20)
+    #[serde(default = "default_runs_limit")]
+    limit: usize,
+}
+
+fn default_runs_limit() -> usize {
+    20
+}
+
+/// Response body for the ingest runs endpoint
+#[derive(Debug, Serialize)]
+pub struct IngestRunsResponse {
+    pub runs: Vec<IngestRunSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IngestRunSummary {
+    pub id: i64,
+    pub trigger: String,
+    pub status: String,
+    pub started_at: String,
+    pub finished_at: Option<String>,
+    pub sources: IngestRunSources,
+    pub stations: IngestRunStations,
+    pub months: IngestRunMonths,
+    pub error_count: i64,
+    pub errors: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IngestRunSources {
+    pub stations_base_url: String,
+    pub climate_base_url: String,
+    pub daily_source: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IngestRunStations {
+    pub seen: i64,
+    pub inserted: i64,
+    pub inactive: i64,
+    pub insufficient_data: i64,
+    pub skipped: i64,
+    pub failed: i64,
+    pub incomplete: i64,
+    pub resumed: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct IngestRunMonths {
+    pub fetched: i64,
+    pub failed: i64,
+    pub hourly_failed: i64,
+}
+
+impl From<IngestRun> for IngestRunSummary {
+    fn from(run: IngestRun) -> Self {
+        IngestRunSummary {
+            id: run.id,
+            trigger: run.trigger,
+            status: run.status,
+            started_at: run.started_at,
+            finished_at: run.finished_at,
+            sources: IngestRunSources {
+                stations_base_url: run.stations_base_url,
+                climate_base_url: run.climate_base_url,
+                daily_source: run.daily_source,
+            },
+            stations: IngestRunStations {
+                seen: run.stations_seen,
+                inserted: run.stations_inserted,
+                inactive: run.stations_inactive,
+                insufficient_data: run.stations_insufficient_data,
+                skipped: run.stations_skipped,
+                failed: run.stations_failed,
+                incomplete: run.stations_incomplete,
+                resumed: run.stations_resumed,
+            },
+            months: IngestRunMonths {
+                fetched: run.months_fetched,
+                failed: run.months_failed,
+                hourly_failed: run.hourly_months_failed,
+            },
+            error_count: run.error_count,
+            errors: run.errors,
+        }
+    }
+}
+
 /// Error response body
 #[derive(Debug, Serialize)]
 pub struct ErrorResponse {
@@ -164,6 +290,22 @@ pub struct StationSummary {
     pub id: i64,
     pub name: String,
     pub distance_km: f64,
+    /// Province or territory code, e.g. "ON"
+    pub province: Option<String>,
+    /// ECCC Climate ID
+    pub climate_id: Option<String>,
+    /// WMO station number
+    pub wmo_id: Option<i64>,
+    /// Transport Canada identifier; set for airport stations
+    pub tc_id: Option<String>,
+    /// Elevation above sea level in metres
+    pub elevation_m: Option<f64>,
+    /// Time zone the station reports in, e.g. "EST"
+    pub timezone: Option<String>,
+    /// Provider the station's data comes from: "eccc" or "ghcn"
+    pub source: String,
+    /// The provider's own station identifier, for GHCN-Daily stations
+    pub source_id: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -184,6 +326,14 @@ pub struct QualitySummary {
 pub struct SeasonalQuality {
     pub stations_with_data: usize,
     pub coverage_pct: f64,
+    /// Station-years averaged into the date
+    pub years_used: usize,
+    /// Station-years left out for too few valid days
+    pub years_excluded: usize,
+    /// Mean share of days with a valid mean temperature over the station-years used
+    pub valid_days_pct: Option<f64>,
+    /// Days flagged missing ("M") or estimated ("E") over the station-years used
+    pub flagged_days: i64,
 }
 
 #[derive(Debug, Serialize)]
@@ -292,8 +442,8 @@ fn normalize_postal_code(raw: &str) -> Option<String> {
             return None;
         }
         return Some(format!("{} {}",
-            compact[0..3].to_string(),
-            compact[3..6].to_string()
+            &compact[0..3],
+            &compact[3..6]
         ));
     }
 
@@ -393,6 +543,8 @@ async fn geocode_with_nominatim(
 /// - latitude: f64 (required)
 /// - longitude: f64 (required)
 /// - num_stations: usize (optional, default: 5)
+/// - morning_threshold: f64 (optional, switches to the 5-9am hourly criterion)
+/// - normals: bool (optional, switches to the climate normals criterion)
 async fn get_optimal_dates(
     State(state): State<AppState>,
     Query(query): Query<OptimalDatesQuery>,
@@ -407,6 +559,19 @@ async fn get_optimal_dates(
     if !(1..=20).contains(&query.num_stations) {
         violations.push("num_stations must be between 1 and 20".to_string());
     }
+    if let Some(threshold) = query.morning_threshold
+        && !(-40.0..=20.0).contains(&threshold)
+    {
+        violations.push("morning_threshold must be between -40 and 20".to_string());
+    }
+    if query.normals && query.morning_threshold.is_some() {
+        violations.push("normals cannot be combined with morning_threshold".to_string());
+    }
+    if let Some(elevation) = query.elevation
+        && !(-500.0..=6000.0).contains(&elevation)
+    {
+        violations.push("elevation must be between -500 and 6000".to_string());
+    }
     if !violations.is_empty() {
         return Err(error_response(
             StatusCode::BAD_REQUEST,
@@ -417,7 +582,15 @@ async fn get_optimal_dates(
     }
 
     // Create analyzer
-    let analyzer = Analyzer::new(&state.db).map_err(|e| {
+    let options = AnalyzerOptions {
+        criterion: match query.morning_threshold {
+            Some(threshold_c) => TransitionCriterion::MorningCold { threshold_c },
+            None if query.normals => TransitionCriterion::Normals,
+            None => TransitionCriterion::DailyMean,
+        },
+        ..state.analyzer_options.clone()
+    };
+    let analyzer = Analyzer::with_options(&state.db, options).map_err(|e| {
         error_response(
             StatusCode::INTERNAL_SERVER_ERROR,
             "INTERNAL_ERROR",
@@ -427,8 +600,19 @@ async fn get_optimal_dates(
     })?;
 
     // Analyze the location
+    let elevation_m = query.elevation.or_else(|| {
+        state
+            .dem
+            .as_ref()
+            .and_then(|dem| dem.elevation_at(query.latitude, query.longitude))
+    });
     let recommendation = analyzer
-        .analyze(query.latitude, query.longitude, query.num_stations)
+        .analyze_with_elevation(
+            query.latitude,
+            query.longitude,
+            elevation_m,
+            query.num_stations,
+        )
         .map_err(|e| {
             error_response(
                 StatusCode::INTERNAL_SERVER_ERROR,
@@ -463,12 +647,11 @@ async fn get_search(
     };
 
     let cache_key = cache_key(&kind);
-    if let Ok(cache) = state.geocode_cache.lock() {
-        if let Some(entry) = cache.get(&cache_key) {
-            if entry.cached_at.elapsed() < GEOCODE_CACHE_TTL {
-                return Ok(Json(entry.value.clone()));
-            }
-        }
+    if let Ok(cache) = state.geocode_cache.lock()
+        && let Some(entry) = cache.get(&cache_key)
+        && entry.cached_at.elapsed() < GEOCODE_CACHE_TTL
+    {
+        return Ok(Json(entry.value.clone()));
     }
 
     let results = geocode_with_nominatim(
@@ -514,6 +697,83 @@ async fn get_search(
     Ok(Json(response))
 }
 
+/// Handler for GET /api/admin/ingest-runs
+///
+/// Lists the most recent ingest runs, newest first, so on-call can check whether the
+/// last refresh succeeded
+async fn get_ingest_runs(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Query(query): Query<IngestRunsQuery>,
+) -> Result<Json<IngestRunsResponse>, (StatusCode, Json<ErrorResponse>)> {
+    check_admin_token(&state, &headers)?;
+
+    if !(1..=100).contains(&query.limit) {
+        return Err(error_response(
+            StatusCode::BAD_REQUEST,
+            "INVALID_QUERY",
+            "Invalid query parameters",
+            Some("limit must be between 1 and 100".to_string()),
+        ));
+    }
+
+    let runs = state.db.get_recent_ingest_runs(query.limit).map_err(|e| {
+        error_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "INTERNAL_ERROR",
+            "Failed to read ingest runs",
+            Some(e.to_string()),
+        )
+    })?;
+
+    Ok(Json(IngestRunsResponse {
+        runs: runs.into_iter().map(IngestRunSummary::from).collect(),
+    }))
+}
+
+/// Require `Authorization: Bearer <admin_token>`; without a configured token the
+/// admin routes are disabled rather than open
+fn check_admin_token(
+    state: &AppState,
+    headers: &HeaderMap,
+) -> Result<(), (StatusCode, Json<ErrorResponse>)> {
+    let Some(expected) = state.admin_token.as_deref().filter(|token| !token.is_empty()) else {
+        return Err(error_response(
+            StatusCode::FORBIDDEN,
+            "ADMIN_DISABLED",
+            "Admin endpoints are disabled; start the server with TIRESWAP_ADMIN_TOKEN set",
+            None,
+        ));
+    };
+    let provided = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+    if provided.is_some_and(|provided| tokens_match(provided, expected)) {
+        Ok(())
+    } else {
+        Err(error_response(
+            StatusCode::UNAUTHORIZED,
+            "UNAUTHORIZED",
+            "Missing or invalid admin token",
+            None,
+        ))
+    }
+}
+
+/// Compare two tokens in time independent of where they differ or how long they are
+///
+/// Both are hashed first, so the byte-by-byte comparison always covers 32 bytes.
+fn tokens_match(provided: &str, expected: &str) -> bool {
+    let provided = Sha256::digest(provided.as_bytes());
+    let expected = Sha256::digest(expected.as_bytes());
+    provided
+        .iter()
+        .zip(expected.iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
 /// Health check endpoint
 async fn health_check() -> impl IntoResponse {
     Json(serde_json::json!({
@@ -528,6 +788,7 @@ pub fn create_router(state: AppState) -> Router {
         .route("/health", get(health_check))
         .route("/api/optimal-dates", get(get_optimal_dates))
         .route("/api/search", get(get_search))
+        .route("/api/admin/ingest-runs", get(get_ingest_runs))
         .with_state(state)
 }
 
@@ -563,6 +824,14 @@ struct NominatimAddress {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_tokens_match() {
+        assert!(tokens_match("s3cret", "s3cret"));
+        assert!(!tokens_match("s3creT", "s3cret"));
+        assert!(!tokens_match("s3cret-and-more", "s3cret"));
+        assert!(!tokens_match("", "s3cret"));
+    }
+
     #[test]
     fn parse_search_query_city() {
         let kind = parse_search_query("  Toronto  ").unwrap();
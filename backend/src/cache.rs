@@ -0,0 +1,240 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// File in the cache directory that records when the responses were fetched
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Format of `as_of` in the manifest
+const AS_OF_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// How the aggregator uses a `ResponseCache`
+#[derive(Debug, Clone, PartialEq)]
+pub enum CacheMode {
+    /// Fetch from upstream as usual and write every response to the cache
+    Record(PathBuf),
+    /// Serve every request from the cache; a request it does not hold fails
+    Replay(PathBuf),
+}
+
+/// What the cache holds for one request
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct RequestEntry {
+    /// Full request URL, query included
+    url: String,
+    /// SHA-256 of the response body, naming its file under `objects/`
+    sha256: String,
+}
+
+/// Time the recorded ingest ran, so a replay sees the same "today"
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Manifest {
+    as_of: String,
+}
+
+/// Content-addressed store of upstream response bodies
+///
+/// Bodies are written once to `objects/<aa>/<sha256>`, named by the SHA-256 of
+/// their bytes; `requests/<sha256 of the URL>.json` maps each request URL to the
+/// body it returned. Recording into the same directory again replaces the
+/// mappings but never rewrites a body.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+}
+
+impl ResponseCache {
+    /// Use `dir` as the cache directory; it is created on the first write
+    pub fn new<P: AsRef<Path>>(dir: P) -> Self {
+        ResponseCache {
+            dir: dir.as_ref().to_path_buf(),
+        }
+    }
+
+    /// Full request URL used as the cache key, with the query parameters in request order
+    ///
+    /// # Arguments
+    /// * `url` - Request URL, possibly with a query already
+    /// * `query` - Additional query parameters
+    ///
+    /// # Returns
+    /// * `Result<String, String>` - The URL, or an error if it cannot be parsed
+    pub fn request_key(url: &str, query: &[(&str, &str)]) -> Result<String, String> {
+        reqwest::Url::parse_with_params(url, query)
+            .map(String::from)
+            .map_err(|e| format!("invalid URL '{}': {}", url, e))
+    }
+
+    /// Store the body returned for a request
+    ///
+    /// # Arguments
+    /// * `key` - Request URL from `request_key`
+    /// * `body` - Response body
+    ///
+    /// # Returns
+    /// * `std::io::Result<()>` - Success or error
+    pub fn store(&self, key: &str, body: &str) -> std::io::Result<()> {
+        let sha256 = sha256_hex(body.as_bytes());
+        let object = self.object_path(&sha256);
+        if !object.exists() {
+            write_atomically(&object, body.as_bytes())?;
+        }
+
+        let entry = RequestEntry {
+            url: key.to_string(),
+            sha256,
+        };
+        let json = serde_json::to_vec_pretty(&entry).map_err(std::io::Error::other)?;
+        write_atomically(&self.request_path(key), &json)
+    }
+
+    /// Body stored for a request
+    ///
+    /// # Arguments
+    /// * `key` - Request URL from `request_key`
+    ///
+    /// # Returns
+    /// * `Result<Option<String>, String>` - Body, `None` if the request was never
+    ///   recorded, or an error if the stored body is missing or does not match its hash
+    pub fn load(&self, key: &str) -> Result<Option<String>, String> {
+        let entry = match std::fs::read(self.request_path(key)) {
+            Ok(json) => serde_json::from_slice::<RequestEntry>(&json)
+                .map_err(|e| format!("corrupt cache entry for {}: {}", key, e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("failed to read cache entry for {}: {}", key, e)),
+        };
+
+        let body = std::fs::read(self.object_path(&entry.sha256))
+            .map_err(|e| format!("failed to read cached body {}: {}", entry.sha256, e))?;
+        if sha256_hex(&body) != entry.sha256 {
+            return Err(format!("cached body {} does not match its hash", entry.sha256));
+        }
+        String::from_utf8(body)
+            .map(Some)
+            .map_err(|_| format!("cached body {} is not UTF-8", entry.sha256))
+    }
+
+    /// Record the time the cached responses were fetched
+    ///
+    /// # Arguments
+    /// * `as_of` - Time of the recording run (UTC)
+    ///
+    /// # Returns
+    /// * `std::io::Result<()>` - Success or error
+    pub fn write_manifest(&self, as_of: NaiveDateTime) -> std::io::Result<()> {
+        let manifest = Manifest {
+            as_of: as_of.format(AS_OF_FORMAT).to_string(),
+        };
+        let json = serde_json::to_vec_pretty(&manifest).map_err(std::io::Error::other)?;
+        write_atomically(&self.dir.join(MANIFEST_FILE), &json)
+    }
+
+    /// Time recorded by `write_manifest`
+    ///
+    /// # Returns
+    /// * `Result<NaiveDateTime, Box<dyn std::error::Error>>` - Time of the recording run
+    pub fn read_manifest(&self) -> Result<NaiveDateTime, Box<dyn std::error::Error>> {
+        let json = std::fs::read(self.dir.join(MANIFEST_FILE))?;
+        let manifest: Manifest = serde_json::from_slice(&json)?;
+        Ok(NaiveDateTime::parse_from_str(&manifest.as_of, AS_OF_FORMAT)?)
+    }
+
+    fn object_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join("objects").join(&sha256[..2]).join(sha256)
+    }
+
+    fn request_path(&self, key: &str) -> PathBuf {
+        self.dir
+            .join("requests")
+            .join(format!("{}.json", sha256_hex(key.as_bytes())))
+    }
+}
+
+/// Lowercase hex SHA-256 of some bytes
+fn sha256_hex(bytes: &[u8]) -> String {
+    Sha256::digest(bytes)
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// Write a file through a temporary sibling and a rename, so readers never see half of it
+fn write_atomically(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let tmp = path.with_extension(format!("tmp-{}", std::process::id()));
+    std::fs::write(&tmp, bytes)?;
+    std::fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_cache(name: &str) -> (PathBuf, ResponseCache) {
+        let dir = std::env::temp_dir()
+            .join(format!("tireswap-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let cache = ResponseCache::new(&dir);
+        (dir, cache)
+    }
+
+    #[test]
+    fn test_store_and_load_by_request() {
+        let (dir, cache) = temp_cache("roundtrip");
+        let key = |month| {
+            ResponseCache::request_key("https://x/bulk", &[("Year", "2024"), ("Month", month)])
+                .unwrap()
+        };
+        let (march, april) = (key("3"), key("4"));
+        assert_eq!(march, "https://x/bulk?Year=2024&Month=3");
+        assert_eq!(
+            ResponseCache::request_key("https://x/items?limit=5", &[]).unwrap(),
+            "https://x/items?limit=5"
+        );
+
+        assert_eq!(cache.load(&march).unwrap(), None);
+        cache.store(&march, "same body").unwrap();
+        cache.store(&april, "same body").unwrap();
+        assert_eq!(cache.load(&march).unwrap().as_deref(), Some("same body"));
+
+        // Identical bodies are stored once
+        let objects: Vec<_> = walk(&dir.join("objects"));
+        assert_eq!(objects.len(), 1);
+        assert!(objects[0].ends_with(sha256_hex(b"same body")));
+
+        // A body that no longer matches its hash is reported, not served
+        std::fs::write(&objects[0], "tampered").unwrap();
+        assert!(cache.load(&april).is_err());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_manifest_round_trip() {
+        let (dir, cache) = temp_cache("manifest");
+        assert!(cache.read_manifest().is_err());
+
+        let as_of = chrono::NaiveDate::from_ymd_opt(2026, 10, 16)
+            .unwrap()
+            .and_hms_opt(7, 0, 0)
+            .unwrap();
+        cache.write_manifest(as_of).unwrap();
+        assert_eq!(cache.read_manifest().unwrap(), as_of);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn walk(dir: &Path) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                files.extend(walk(&path));
+            } else {
+                files.push(path);
+            }
+        }
+        files
+    }
+}
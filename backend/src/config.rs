@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// History window and station eligibility rules used during ingest
+///
+/// Can be loaded from a JSON file; any field left out keeps its default.
+///
+/// ```json
+/// { "history_years": 30, "active_within_days": 730, "min_record_days": 3650 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IngestConfig {
+    /// Number of past years (before the current one) to download and analyze
+    pub history_years: i32,
+    /// A station counts as active if it reported within this many days;
+    /// `null` keeps decommissioned stations as well
+    pub active_within_days: Option<i64>,
+    /// Minimum span between a station's first and last daily record
+    pub min_record_days: i64,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        IngestConfig {
+            history_years: 5,
+            active_within_days: Some(7),
+            // 5 years, accounting for leap years
+            min_record_days: 1825,
+        }
+    }
+}
+
+impl IngestConfig {
+    /// Load an ingest configuration from a JSON file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the JSON file
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn std::error::Error>>` - Configuration or error
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path.as_ref())?;
+        let config: IngestConfig = serde_json::from_str(&text)?;
+        if config.history_years < 1 {
+            return Err("history_years must be at least 1".into());
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(name: &str, contents: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir()
+            .join(format!("tireswap-{}-{}.json", name, std::process::id()));
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_partial_config_keeps_defaults() {
+        let path = write_config("partial", r#"{ "history_years": 30 }"#);
+        let config = IngestConfig::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.history_years, 30);
+        assert_eq!(config.active_within_days, Some(7));
+        assert_eq!(config.min_record_days, 1825);
+    }
+
+    #[test]
+    fn test_null_activity_window_includes_inactive_stations() {
+        let path = write_config("inactive", r#"{ "active_within_days": null }"#);
+        let config = IngestConfig::from_file(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.active_within_days, None);
+    }
+
+    #[test]
+    fn test_invalid_config_is_rejected() {
+        let path = write_config("invalid", r#"{ "history_years": 0, "typo": 1 }"#);
+        assert!(IngestConfig::from_file(&path).is_err());
+        std::fs::write(&path, r#"{ "history_years": 0 }"#).unwrap();
+        assert!(IngestConfig::from_file(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
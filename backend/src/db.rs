@@ -1,4 +1,7 @@
-use rusqlite::{Connection, Result, params};
+use crate::source::SourceKind;
+use chrono::NaiveDateTime;
+use rusqlite::{Connection, Result, Transaction, params};
+use std::collections::HashSet;
 use std::path::Path;
 use std::sync::Mutex;
 
@@ -11,8 +14,137 @@ pub struct ClimateData {
     pub year: i64,
     pub switch_to_summer: Option<String>,
     pub switch_to_winter: Option<String>,
+    /// Completeness of the spring window the summer date was computed from
+    pub spring_completeness: Option<SeasonCompleteness>,
+    /// Completeness of the fall window the winter date was computed from
+    pub fall_completeness: Option<SeasonCompleteness>,
 }
 
+/// How much of a season window a station actually reported
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SeasonCompleteness {
+    /// Percentage (0-100) of the window's days with a valid mean temperature
+    pub valid_pct: f64,
+    /// Days in the window whose mean temperature is flagged "M" (missing) or "E" (estimated)
+    pub flagged_days: i64,
+}
+
+/// Columns of `data`, in the order read by `climate_data_from_row`
+const DATA_COLUMNS: &str = "id, station_id, year, switch_to_summer, switch_to_winter,
+    spring_valid_pct, spring_flagged_days, fall_valid_pct, fall_flagged_days";
+
+/// Struct to represent one day of raw station observations
+///
+/// Each value has the ECCC flag that accompanied it (e.g. "M" missing,
+/// "E" estimated, "T" trace), if any.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DailyObservation {
+    pub station_id: i64,
+    pub date: String,
+    pub mean_temp: Option<f64>,
+    pub mean_temp_flag: Option<String>,
+    pub min_temp: Option<f64>,
+    pub min_temp_flag: Option<String>,
+    pub max_temp: Option<f64>,
+    pub max_temp_flag: Option<String>,
+    pub total_rain: Option<f64>,
+    pub total_rain_flag: Option<String>,
+    pub total_snow: Option<f64>,
+    pub total_snow_flag: Option<String>,
+    pub total_precip: Option<f64>,
+    pub total_precip_flag: Option<String>,
+    pub snow_on_ground: Option<f64>,
+    pub snow_on_ground_flag: Option<String>,
+    /// True when `mean_temp` was estimated by gap filling rather than reported
+    /// by the station
+    pub mean_temp_imputed: bool,
+    /// True when `min_temp` was estimated by gap filling
+    pub min_temp_imputed: bool,
+    /// True when `max_temp` was estimated by gap filling
+    pub max_temp_imputed: bool,
+}
+
+/// Struct to represent one hour of raw station observations
+///
+/// `date_time` is in local standard time, formatted "YYYY-MM-DD HH:MM".
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HourlyObservation {
+    pub station_id: i64,
+    pub date_time: String,
+    pub temp: Option<f64>,
+    pub temp_flag: Option<String>,
+    pub dew_point: Option<f64>,
+    pub dew_point_flag: Option<String>,
+}
+
+/// Normal monthly mean temperature of a station, from the ECCC Canadian Climate Normals
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClimateNormal {
+    pub station_id: i64,
+    /// Month (1-12)
+    pub month: u32,
+    /// Mean daily temperature over the normals period (°C)
+    pub mean_temp: f64,
+    /// ECCC normals code, "A" (most years reported) to "D"
+    pub normal_code: Option<String>,
+    /// First year of the normals period, e.g. 1991
+    pub first_year: Option<i32>,
+    /// Last year of the normals period, e.g. 2020
+    pub last_year: Option<i32>,
+}
+
+/// One ingest run (`--update-db` or a scheduled refresh), as recorded in `ingest_runs`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IngestRun {
+    pub id: i64,
+    /// What started the run: "cli" or "schedule"
+    pub trigger: String,
+    pub started_at: String,
+    /// `None` while the run is in progress, or if it never finished
+    pub finished_at: Option<String>,
+    /// "running", "ok", "partial" (some stations or months failed) or "failed"
+    pub status: String,
+    pub stations_base_url: String,
+    pub climate_base_url: String,
+    pub daily_source: String,
+    /// Catalogue features received with an ID, name and coordinates
+    pub stations_seen: i64,
+    pub stations_inserted: i64,
+    /// Catalogue stations left out because they stopped reporting
+    pub stations_inactive: i64,
+    /// Catalogue stations left out because their daily record is too short
+    pub stations_insufficient_data: i64,
+    /// Catalogue features missing an ID, name or coordinates
+    pub stations_skipped: i64,
+    /// Stations whose daily fetch returned an error
+    pub stations_failed: i64,
+    /// Stations marked incomplete because too many months failed
+    pub stations_incomplete: i64,
+    /// Stations finished before the run was interrupted and skipped by `--resume`
+    pub stations_resumed: i64,
+    pub months_fetched: i64,
+    pub months_failed: i64,
+    pub hourly_months_failed: i64,
+    /// Number of errors during the run; only the first few are kept in `errors`
+    pub error_count: i64,
+    pub errors: Vec<String>,
+}
+
+/// Columns of `ingest_runs`, in the order read by `ingest_run_from_row`
+const INGEST_RUN_COLUMNS: &str = "id, trigger, started_at, finished_at, status,
+    stations_base_url, climate_base_url, daily_source,
+    stations_seen, stations_inserted, stations_inactive, stations_insufficient_data,
+    stations_skipped, stations_failed, stations_incomplete,
+    months_fetched, months_failed, hourly_months_failed, error_count, errors,
+    stations_resumed";
+
+/// Columns of `daily_observations`, in the order read by `daily_observation_from_row`
+const DAILY_OBSERVATION_COLUMNS: &str = "station_id, date,
+    mean_temp, mean_temp_flag, min_temp, min_temp_flag, max_temp, max_temp_flag,
+    total_rain, total_rain_flag, total_snow, total_snow_flag,
+    total_precip, total_precip_flag, snow_on_ground, snow_on_ground_flag,
+    mean_temp_imputed, min_temp_imputed, max_temp_imputed";
+
 /// Database struct to manage SQLite connections
 pub struct Database {
     conn: Mutex<Connection>,
@@ -25,6 +157,37 @@ pub struct Station {
     pub lat_y: f64,
     pub dly_first_date: Option<String>,
     pub dly_last_date: Option<String>,
+    pub metadata: StationMetadata,
+}
+
+/// Catalogue details of a station beyond its name, location and record dates
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StationMetadata {
+    /// Province or territory code, e.g. "ON"
+    pub province: Option<String>,
+    /// ECCC Climate ID, e.g. "6158355", as found in the bulk CSV files
+    pub climate_id: Option<String>,
+    /// World Meteorological Organization station number, e.g. 71508
+    pub wmo_id: Option<i64>,
+    /// Transport Canada identifier, e.g. "XTO"; airports have one
+    pub tc_id: Option<String>,
+    /// Elevation above sea level in metres
+    pub elevation_m: Option<f64>,
+    /// Time zone the station reports in, e.g. "EST"
+    pub timezone: Option<String>,
+    /// Provider the station was registered from, e.g. "ghcn"; `None` for stations
+    /// stored before providers were tracked, which all came from ECCC
+    pub source: Option<String>,
+    /// The provider's own identifier when it is not a number, e.g. the GHCN-Daily
+    /// ID "USW00014822"; `id` is then derived from it
+    pub source_id: Option<String>,
+}
+
+impl StationMetadata {
+    /// Name of the provider the station came from ("eccc" when not recorded)
+    pub fn source_name(&self) -> &str {
+        self.source.as_deref().unwrap_or(SourceKind::Eccc.as_str())
+    }
 }
 
 impl Database {
@@ -47,6 +210,22 @@ impl Database {
         Ok(Database { conn: Mutex::new(conn) })
     }
 
+    /// Write a consistent copy of the whole database to a new file
+    ///
+    /// # Arguments
+    /// * `path` - Destination file; must not exist yet
+    ///
+    /// # Returns
+    /// * `Result<()>` - Success or error
+    pub fn copy_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "VACUUM INTO ?1",
+            params![path.as_ref().to_string_lossy()],
+        )?;
+        Ok(())
+    }
+
     /// Initialize the database schema
     /// Creates tables for weather stations and climate data
     pub fn initialize_schema(&self) -> Result<()> {
@@ -63,6 +242,20 @@ impl Database {
             [],
         )?;
 
+        // Catalogue metadata added after the table was first introduced
+        for (column, definition) in [
+            ("province", "TEXT"),
+            ("climate_id", "TEXT"),
+            ("wmo_id", "INTEGER"),
+            ("tc_id", "TEXT"),
+            ("elevation_m", "REAL"),
+            ("timezone", "TEXT"),
+            ("source", "TEXT"),
+            ("source_id", "TEXT"),
+        ] {
+            add_column_if_missing(&conn, "stations", column, definition)?;
+        }
+
         conn.execute(
             "CREATE TABLE IF NOT EXISTS data (
                 id INTEGER PRIMARY KEY AUTOINCREMENT,
@@ -75,6 +268,170 @@ impl Database {
             [],
         )?;
 
+        // Databases from before the per-year index appended one multi-year average
+        // per run, stamped with the run year. Those rows are not a season's dates,
+        // so they are dropped; the next update recomputes real per-year rows.
+        let per_year_index: bool = conn.query_row(
+            "SELECT EXISTS (
+                SELECT 1 FROM sqlite_master WHERE type = 'index' AND name = 'idx_data_station_year'
+            )",
+            [],
+            |row| row.get(0),
+        )?;
+        if !per_year_index {
+            conn.execute("DELETE FROM data", [])?;
+        }
+        // Completeness columns added after the table was first introduced
+        for (column, definition) in [
+            ("spring_valid_pct", "REAL"),
+            ("spring_flagged_days", "INTEGER"),
+            ("fall_valid_pct", "REAL"),
+            ("fall_flagged_days", "INTEGER"),
+        ] {
+            add_column_if_missing(&conn, "data", column, definition)?;
+        }
+
+        conn.execute(
+            "CREATE UNIQUE INDEX IF NOT EXISTS idx_data_station_year ON data (station_id, year)",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS daily_observations (
+                station_id INTEGER NOT NULL,
+                date TEXT NOT NULL,
+                mean_temp REAL,
+                mean_temp_flag TEXT,
+                min_temp REAL,
+                min_temp_flag TEXT,
+                max_temp REAL,
+                max_temp_flag TEXT,
+                total_rain REAL,
+                total_rain_flag TEXT,
+                total_snow REAL,
+                total_snow_flag TEXT,
+                total_precip REAL,
+                total_precip_flag TEXT,
+                snow_on_ground REAL,
+                snow_on_ground_flag TEXT,
+                mean_temp_imputed INTEGER NOT NULL DEFAULT 0,
+                min_temp_imputed INTEGER NOT NULL DEFAULT 0,
+                max_temp_imputed INTEGER NOT NULL DEFAULT 0,
+                PRIMARY KEY (station_id, date),
+                FOREIGN KEY (station_id) REFERENCES stations(id)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS station_ingest_status (
+                station_id INTEGER PRIMARY KEY,
+                complete INTEGER NOT NULL,
+                failed_months INTEGER NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY (station_id) REFERENCES stations(id)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS ingested_months (
+                station_id INTEGER NOT NULL,
+                year INTEGER NOT NULL,
+                month INTEGER NOT NULL,
+                complete INTEGER NOT NULL,
+                fetched_at TEXT NOT NULL,
+                PRIMARY KEY (station_id, year, month),
+                FOREIGN KEY (station_id) REFERENCES stations(id)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS hourly_observations (
+                station_id INTEGER NOT NULL,
+                date_time TEXT NOT NULL,
+                temp REAL,
+                temp_flag TEXT,
+                dew_point REAL,
+                dew_point_flag TEXT,
+                PRIMARY KEY (station_id, date_time),
+                FOREIGN KEY (station_id) REFERENCES stations(id)
+            )",
+            [],
+        )?;
+
+        // Hourly months are tracked separately: a month can be complete in one timeframe only
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS ingested_hourly_months (
+                station_id INTEGER NOT NULL,
+                year INTEGER NOT NULL,
+                month INTEGER NOT NULL,
+                complete INTEGER NOT NULL,
+                fetched_at TEXT NOT NULL,
+                PRIMARY KEY (station_id, year, month),
+                FOREIGN KEY (station_id) REFERENCES stations(id)
+            )",
+            [],
+        )?;
+
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS climate_normals (
+                station_id INTEGER NOT NULL,
+                month INTEGER NOT NULL,
+                mean_temp REAL NOT NULL,
+                normal_code TEXT,
+                first_year INTEGER,
+                last_year INTEGER,
+                fetched_at TEXT NOT NULL,
+                PRIMARY KEY (station_id, month),
+                FOREIGN KEY (station_id) REFERENCES stations(id)
+            )",
+            [],
+        )?;
+
+        // `errors` is a JSON array of messages
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS ingest_runs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                trigger TEXT NOT NULL,
+                started_at TEXT NOT NULL,
+                finished_at TEXT,
+                status TEXT NOT NULL,
+                stations_base_url TEXT NOT NULL,
+                climate_base_url TEXT NOT NULL,
+                daily_source TEXT NOT NULL,
+                stations_seen INTEGER NOT NULL DEFAULT 0,
+                stations_inserted INTEGER NOT NULL DEFAULT 0,
+                stations_inactive INTEGER NOT NULL DEFAULT 0,
+                stations_insufficient_data INTEGER NOT NULL DEFAULT 0,
+                stations_skipped INTEGER NOT NULL DEFAULT 0,
+                stations_failed INTEGER NOT NULL DEFAULT 0,
+                stations_incomplete INTEGER NOT NULL DEFAULT 0,
+                months_fetched INTEGER NOT NULL DEFAULT 0,
+                months_failed INTEGER NOT NULL DEFAULT 0,
+                hourly_months_failed INTEGER NOT NULL DEFAULT 0,
+                error_count INTEGER NOT NULL DEFAULT 0,
+                errors TEXT NOT NULL DEFAULT '[]',
+                stations_resumed INTEGER NOT NULL DEFAULT 0
+            )",
+            [],
+        )?;
+
+        // Stations (and, for `phase` "hourly", their hourly data) finished by a run,
+        // written in the same transaction as the station's data
+        conn.execute(
+            "CREATE TABLE IF NOT EXISTS ingest_checkpoints (
+                run_id INTEGER NOT NULL,
+                station_id INTEGER NOT NULL,
+                phase TEXT NOT NULL,
+                completed_at TEXT NOT NULL,
+                PRIMARY KEY (run_id, station_id, phase),
+                FOREIGN KEY (run_id) REFERENCES ingest_runs(id)
+            )",
+            [],
+        )?;
+
         Ok(())
     }
 
@@ -100,23 +457,31 @@ impl Database {
         dly_last_date: Option<&str>,
     ) -> Result<usize> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT OR REPLACE INTO stations (id, name, lon_x, lat_y, dly_first_date, dly_last_date) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            params![id, name, lon_x, lat_y, dly_first_date, dly_last_date],
-        )
+        insert_station(&conn, id, name, lon_x, lat_y, dly_first_date, dly_last_date)
+    }
+
+    /// Store the catalogue metadata of a station inserted with `insert_station`
+    ///
+    /// # Arguments
+    /// * `id` - Station ID
+    /// * `metadata` - Province, identifiers, elevation, time zone and source
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of rows affected
+    pub fn set_station_metadata(&self, id: i64, metadata: &StationMetadata) -> Result<usize> {
+        set_station_metadata(&self.conn.lock().unwrap(), id, metadata)
     }
 
-    /// Insert climate data into the database
+    /// Insert or update the switch dates of a station for one season year
     ///
     /// # Arguments
     /// * `station_id` - Station ID
-    /// * `year` - Year
+    /// * `year` - Season year the dates belong to
     /// * `switch_to_summer` - Switch to summer tires date
     /// * `switch_to_winter` - Switch to winter tires date
     ///
     /// # Returns
-    /// * `Result<i64>` - ID of the inserted data
-    #[allow(dead_code)]
+    /// * `Result<i64>` - ID of the inserted or updated data
     pub fn insert_data(
         &self,
         station_id: i64,
@@ -125,205 +490,682 @@ impl Database {
         switch_to_winter: Option<&str>,
     ) -> Result<i64> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(
-            "INSERT INTO data (station_id, year, switch_to_summer, switch_to_winter)
-             VALUES (?1, ?2, ?3, ?4)",
-            params![
-                station_id,
-                year,
-                switch_to_summer,
-                switch_to_winter
-            ],
-        )?;
-        Ok(conn.last_insert_rowid())
+        insert_data(&conn, station_id, year, switch_to_summer, switch_to_winter)
     }
 
-    /// Get a station by ID
+    /// Store the completeness of the season windows behind a station-year's switch dates
     ///
     /// # Arguments
     /// * `station_id` - Station ID
+    /// * `year` - Season year, as passed to `insert_data`
+    /// * `spring` - Completeness of the spring window
+    /// * `fall` - Completeness of the fall window
     ///
     /// # Returns
-    /// * `Result<Option<(i64, String, f64, f64)>>` - Station data (id, name, lon_x, lat_y) or None
-    #[allow(dead_code)]
-    pub fn get_station_by_id(&self, station_id: i64) -> Result<Option<(i64, String, f64, f64)>> {
-        let conn = self.conn.lock().unwrap();
-        let mut stmt = conn
-            .prepare("SELECT id, name, lon_x, lat_y FROM stations WHERE id = ?1")?;
+    /// * `Result<usize>` - Number of rows affected (0 if the station-year has no data row)
+    pub fn set_season_completeness(
+        &self,
+        station_id: i64,
+        year: i64,
+        spring: &SeasonCompleteness,
+        fall: &SeasonCompleteness,
+    ) -> Result<usize> {
+        set_season_completeness(&self.conn.lock().unwrap(), station_id, year, spring, fall)
+    }
 
-        let mut rows = stmt.query(params![station_id])?;
+    /// Insert or replace raw daily observations in a single transaction
+    ///
+    /// # Arguments
+    /// * `observations` - Daily observations keyed on (station_id, date)
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of rows written
+    pub fn insert_daily_observations(&self, observations: &[DailyObservation]) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let written = insert_daily_observations(&tx, observations)?;
+        tx.commit()?;
+        Ok(written)
+    }
 
-        if let Some(row) = rows.next()? {
-            let id: i64 = row.get(0)?;
-            let name: String = row.get(1)?;
-            let lon_x: f64 = row.get(2)?;
-            let lat_y: f64 = row.get(3)?;
-            Ok(Some((id, name, lon_x, lat_y)))
-        } else {
-            Ok(None)
-        }
+    /// Get daily observations for a station within an inclusive date range
+    ///
+    /// # Arguments
+    /// * `station_id` - Station ID
+    /// * `start_date` - First date (YYYY-MM-DD), inclusive
+    /// * `end_date` - Last date (YYYY-MM-DD), inclusive
+    ///
+    /// # Returns
+    /// * `Result<Vec<DailyObservation>>` - Observations ordered by date
+    pub fn get_daily_observations(
+        &self,
+        station_id: i64,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<DailyObservation>> {
+        get_daily_observations(&self.conn.lock().unwrap(), station_id, start_date, end_date)
     }
 
-    /// Query all stations
+    /// Get daily observations for all stations within an inclusive date range
+    ///
+    /// # Arguments
+    /// * `start_date` - First date (YYYY-MM-DD), inclusive
+    /// * `end_date` - Last date (YYYY-MM-DD), inclusive
     ///
     /// # Returns
-    /// * `Result<Vec<Station>>` - Vector of station data
-    pub fn get_all_stations(&self) -> Result<Vec<Station>> {
+    /// * `Result<Vec<DailyObservation>>` - Observations ordered by station then date
+    pub fn get_daily_observations_between(
+        &self,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<DailyObservation>> {
         let conn = self.conn.lock().unwrap();
-        let mut stmt = conn.prepare(
-            "SELECT id, name, lon_x, lat_y, dly_first_date, dly_last_date FROM stations",
-        )?;
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM daily_observations
+             WHERE date >= ?1 AND date <= ?2
+             ORDER BY station_id, date",
+            DAILY_OBSERVATION_COLUMNS
+        ))?;
 
-        let stations = stmt.query_map([], |row| {
-            Ok(Station {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                lon_x: row.get(2)?,
-                lat_y: row.get(3)?,
-                dly_first_date: row.get(4)?,
-                dly_last_date: row.get(5)?,
-            })
-        })?;
+        let rows = stmt.query_map(params![start_date, end_date], daily_observation_from_row)?;
 
         let mut result = Vec::new();
-        for station in stations {
-            result.push(station?);
+        for row in rows {
+            result.push(row?);
         }
         Ok(result)
     }
 
-    /// Get climate data by station ID
+    /// Insert or replace raw hourly observations in a single transaction
+    ///
+    /// # Arguments
+    /// * `observations` - Hourly observations keyed on (station_id, date_time)
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of rows written
+    pub fn insert_hourly_observations(&self, observations: &[HourlyObservation]) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let written = insert_hourly_observations(&tx, observations)?;
+        tx.commit()?;
+        Ok(written)
+    }
+
+    /// Get hourly observations for a station within an inclusive date range
     ///
     /// # Arguments
     /// * `station_id` - Station ID
+    /// * `start_date` - First date (YYYY-MM-DD), inclusive
+    /// * `end_date` - Last date (YYYY-MM-DD), inclusive
     ///
     /// # Returns
-    /// * `Result<Vec<ClimateData>>` - Vector of climate data for the station
-    #[allow(dead_code)]
-    pub fn get_data_by_station(&self, station_id: i64) -> Result<Vec<ClimateData>> {
+    /// * `Result<Vec<HourlyObservation>>` - Observations ordered by date and time
+    pub fn get_hourly_observations(
+        &self,
+        station_id: i64,
+        start_date: &str,
+        end_date: &str,
+    ) -> Result<Vec<HourlyObservation>> {
         let conn = self.conn.lock().unwrap();
+        // Every hour of end_date ("YYYY-MM-DD HH:MM") sorts before "YYYY-MM-DDZ"
         let mut stmt = conn.prepare(
-            "SELECT id, station_id, year, switch_to_summer, switch_to_winter
-             FROM data WHERE station_id = ?1
-             ORDER BY year DESC, id DESC",
+            "SELECT station_id, date_time, temp, temp_flag, dew_point, dew_point_flag
+             FROM hourly_observations
+             WHERE station_id = ?1 AND date_time >= ?2 AND date_time < ?3 || 'Z'
+             ORDER BY date_time",
         )?;
 
-        let data_entries = stmt.query_map(params![station_id], |row| {
-            Ok(ClimateData {
-                id: row.get(0)?,
-                station_id: row.get(1)?,
-                year: row.get(2)?,
-                switch_to_summer: row.get(3)?,
-                switch_to_winter: row.get(4)?,
+        let rows = stmt.query_map(params![station_id, start_date, end_date], |row| {
+            Ok(HourlyObservation {
+                station_id: row.get(0)?,
+                date_time: row.get(1)?,
+                temp: row.get(2)?,
+                temp_flag: row.get(3)?,
+                dew_point: row.get(4)?,
+                dew_point_flag: row.get(5)?,
             })
         })?;
 
         let mut result = Vec::new();
-        for entry in data_entries {
-            result.push(entry?);
+        for row in rows {
+            result.push(row?);
         }
         Ok(result)
     }
 
-    /// Get climate data by year
+    /// Replace the climate normals of a station
     ///
     /// # Arguments
-    /// * `year` - Year
+    /// * `station_id` - Station ID
+    /// * `normals` - Monthly normals of the station; months left out are removed
+    /// * `fetched_at` - Time the normals were fetched (UTC)
     ///
     /// # Returns
-    /// * `Result<Vec<ClimateData>>` - Vector of climate data for the year
-    #[allow(dead_code)]
-    pub fn get_data_by_year(&self, year: i64) -> Result<Vec<ClimateData>> {
+    /// * `Result<usize>` - Number of rows written
+    pub fn replace_climate_normals(
+        &self,
+        station_id: i64,
+        normals: &[ClimateNormal],
+        fetched_at: NaiveDateTime,
+    ) -> Result<usize> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        let written = replace_climate_normals(&tx, station_id, normals, fetched_at)?;
+        tx.commit()?;
+        Ok(written)
+    }
+
+    /// Get the climate normals of a station
+    ///
+    /// # Arguments
+    /// * `station_id` - Station ID
+    ///
+    /// # Returns
+    /// * `Result<Vec<ClimateNormal>>` - Monthly normals ordered by month
+    pub fn get_climate_normals(&self, station_id: i64) -> Result<Vec<ClimateNormal>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, station_id, year, switch_to_summer, switch_to_winter
-             FROM data WHERE year = ?1",
+            "SELECT station_id, month, mean_temp, normal_code, first_year, last_year
+             FROM climate_normals
+             WHERE station_id = ?1
+             ORDER BY month",
         )?;
 
-        let data_entries = stmt.query_map(params![year], |row| {
-            Ok(ClimateData {
-                id: row.get(0)?,
-                station_id: row.get(1)?,
-                year: row.get(2)?,
-                switch_to_summer: row.get(3)?,
-                switch_to_winter: row.get(4)?,
+        let rows = stmt.query_map(params![station_id], |row| {
+            Ok(ClimateNormal {
+                station_id: row.get(0)?,
+                month: row.get(1)?,
+                mean_temp: row.get(2)?,
+                normal_code: row.get(3)?,
+                first_year: row.get(4)?,
+                last_year: row.get(5)?,
             })
         })?;
 
         let mut result = Vec::new();
-        for entry in data_entries {
-            result.push(entry?);
+        for row in rows {
+            result.push(row?);
         }
         Ok(result)
     }
 
-    /// Query all climate data
+    /// Record that a (station, year, month) slice has been ingested
+    ///
+    /// # Arguments
+    /// * `station_id` - Station ID
+    /// * `year` - Year of the slice
+    /// * `month` - Month of the slice (1-12)
+    /// * `complete` - Whether the month had closed when it was fetched
+    /// * `fetched_at` - Time the month was fetched (UTC)
     ///
     /// # Returns
-    /// * `Result<Vec<ClimateData>>` - Vector of all climate data entries
-    #[allow(dead_code)]
-    pub fn get_all_data(&self) -> Result<Vec<ClimateData>> {
+    /// * `Result<usize>` - Number of rows affected
+    pub fn mark_month_ingested(
+        &self,
+        station_id: i64,
+        year: i32,
+        month: u32,
+        complete: bool,
+        fetched_at: NaiveDateTime,
+    ) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        mark_month_ingested(&conn, station_id, year, month, complete, fetched_at)
+    }
+
+    /// Get the months of a station that were ingested after they closed
+    ///
+    /// # Arguments
+    /// * `station_id` - Station ID
+    ///
+    /// # Returns
+    /// * `Result<HashSet<(i32, u32)>>` - Set of (year, month) pairs that need no refetch
+    pub fn get_complete_months(&self, station_id: i64) -> Result<HashSet<(i32, u32)>> {
         let conn = self.conn.lock().unwrap();
         let mut stmt = conn.prepare(
-            "SELECT id, station_id, year, switch_to_summer, switch_to_winter FROM data",
+            "SELECT year, month FROM ingested_months WHERE station_id = ?1 AND complete = 1",
         )?;
 
-        let data_entries = stmt.query_map([], |row| {
-            Ok(ClimateData {
-                id: row.get(0)?,
-                station_id: row.get(1)?,
-                year: row.get(2)?,
-                switch_to_summer: row.get(3)?,
-                switch_to_winter: row.get(4)?,
-            })
-        })?;
+        let months = stmt.query_map(params![station_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
 
-        let mut result = Vec::new();
-        for entry in data_entries {
-            result.push(entry?);
+        let mut result = HashSet::new();
+        for month in months {
+            result.insert(month?);
         }
         Ok(result)
     }
 
-    /// Delete a station and all its associated data
+    /// Record that a (station, year, month) slice of hourly data has been ingested
     ///
     /// # Arguments
     /// * `station_id` - Station ID
+    /// * `year` - Year of the slice
+    /// * `month` - Month of the slice (1-12)
+    /// * `complete` - Whether the month had closed when it was fetched
+    /// * `fetched_at` - Time the month was fetched (UTC)
     ///
     /// # Returns
     /// * `Result<usize>` - Number of rows affected
-    #[allow(dead_code)]
-    pub fn delete_station(&self, station_id: i64) -> Result<usize> {
+    pub fn mark_hourly_month_ingested(
+        &self,
+        station_id: i64,
+        year: i32,
+        month: u32,
+        complete: bool,
+        fetched_at: NaiveDateTime,
+    ) -> Result<usize> {
         let conn = self.conn.lock().unwrap();
-        // First delete associated data
-        conn.execute(
-            "DELETE FROM data WHERE station_id = ?1",
-            params![station_id],
-        )?;
-        // Then delete the station
-        conn
-            .execute("DELETE FROM stations WHERE id = ?1", params![station_id])
+        mark_hourly_month_ingested(&conn, station_id, year, month, complete, fetched_at)
     }
 
-    /// Execute a custom query
+    /// Get the months of a station whose hourly data was ingested after they closed
     ///
     /// # Arguments
-    /// * `query` - SQL query string
+    /// * `station_id` - Station ID
     ///
     /// # Returns
-    /// * `Result<usize>` - Number of rows affected
-    #[allow(dead_code)]
-    pub fn execute_query(&self, query: &str) -> Result<usize> {
+    /// * `Result<HashSet<(i32, u32)>>` - Set of (year, month) pairs that need no refetch
+    pub fn get_complete_hourly_months(&self, station_id: i64) -> Result<HashSet<(i32, u32)>> {
         let conn = self.conn.lock().unwrap();
-        conn.execute(query, [])
-    }
+        let mut stmt = conn.prepare(
+            "SELECT year, month FROM ingested_hourly_months WHERE station_id = ?1 AND complete = 1",
+        )?;
 
-    /// Begin a transaction
-    #[allow(dead_code)]
-    pub fn begin_transaction(&self) -> Result<()> {
-        let conn = self.conn.lock().unwrap();
-        conn.execute("BEGIN TRANSACTION", [])?;
-        Ok(())
+        let months = stmt.query_map(params![station_id], |row| Ok((row.get(0)?, row.get(1)?)))?;
+
+        let mut result = HashSet::new();
+        for month in months {
+            result.insert(month?);
+        }
+        Ok(result)
+    }
+
+    /// Record whether the last climate fetch for a station was complete
+    ///
+    /// # Arguments
+    /// * `station_id` - Station ID
+    /// * `complete` - False if too many months failed to download
+    /// * `failed_months` - Number of months that failed after all retries
+    /// * `updated_at` - Time of the fetch (UTC)
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of rows affected
+    pub fn set_station_ingest_status(
+        &self,
+        station_id: i64,
+        complete: bool,
+        failed_months: usize,
+        updated_at: NaiveDateTime,
+    ) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        set_station_ingest_status(&conn, station_id, complete, failed_months, updated_at)
+    }
+
+    /// Get the IDs of stations whose last climate fetch was incomplete
+    ///
+    /// # Returns
+    /// * `Result<Vec<i64>>` - Station IDs marked incomplete
+    pub fn get_incomplete_stations(&self) -> Result<Vec<i64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT station_id FROM station_ingest_status WHERE complete = 0 ORDER BY station_id",
+        )?;
+
+        let ids = stmt.query_map([], |row| row.get(0))?;
+
+        let mut result = Vec::new();
+        for id in ids {
+            result.push(id?);
+        }
+        Ok(result)
+    }
+
+    /// Record the start of an ingest run
+    ///
+    /// # Arguments
+    /// * `trigger` - What started the run ("cli" or "schedule")
+    /// * `stations_base_url` - Station catalogue endpoint used by the run
+    /// * `climate_base_url` - Bulk climate data endpoint used by the run
+    /// * `daily_source` - Source of daily observations ("csv" or "ogc")
+    /// * `started_at` - Time the run started (UTC)
+    ///
+    /// # Returns
+    /// * `Result<i64>` - ID of the new run, with status "running"
+    pub fn start_ingest_run(
+        &self,
+        trigger: &str,
+        stations_base_url: &str,
+        climate_base_url: &str,
+        daily_source: &str,
+        started_at: NaiveDateTime,
+    ) -> Result<i64> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO ingest_runs
+             (trigger, started_at, status, stations_base_url, climate_base_url, daily_source)
+             VALUES (?1, ?5, 'running', ?2, ?3, ?4)",
+            params![
+                trigger,
+                stations_base_url,
+                climate_base_url,
+                daily_source,
+                timestamp(started_at)
+            ],
+        )?;
+        Ok(conn.last_insert_rowid())
+    }
+
+    /// Store the outcome of a run started with `start_ingest_run` and mark it finished
+    ///
+    /// # Arguments
+    /// * `run` - Run statistics; `id` selects the row, `trigger`, `started_at` and the
+    ///   endpoints are left as recorded at the start
+    /// * `finished_at` - Time the run finished (UTC)
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of rows affected
+    pub fn finish_ingest_run(
+        &self,
+        run: &IngestRun,
+        finished_at: NaiveDateTime,
+    ) -> Result<usize> {
+        let errors = serde_json::to_string(&run.errors).unwrap_or_else(|_| "[]".to_string());
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE ingest_runs SET
+                finished_at = ?16, status = ?2,
+                stations_seen = ?3, stations_inserted = ?4, stations_inactive = ?5,
+                stations_insufficient_data = ?6, stations_skipped = ?7, stations_failed = ?8,
+                stations_incomplete = ?9, months_fetched = ?10, months_failed = ?11,
+                hourly_months_failed = ?12, error_count = ?13, errors = ?14,
+                stations_resumed = ?15
+             WHERE id = ?1",
+            params![
+                run.id,
+                run.status,
+                run.stations_seen,
+                run.stations_inserted,
+                run.stations_inactive,
+                run.stations_insufficient_data,
+                run.stations_skipped,
+                run.stations_failed,
+                run.stations_incomplete,
+                run.months_fetched,
+                run.months_failed,
+                run.hourly_months_failed,
+                run.error_count,
+                errors,
+                run.stations_resumed,
+                timestamp(finished_at),
+            ],
+        )?;
+        // A finished run is never resumed, so its checkpoints are no longer needed
+        conn.execute(
+            "DELETE FROM ingest_checkpoints WHERE run_id = ?1",
+            params![run.id],
+        )
+    }
+
+    /// Get the most recent run that never finished, e.g. because the process was killed
+    ///
+    /// # Returns
+    /// * `Result<Option<i64>>` - ID of the run, or `None` if every run finished
+    pub fn get_interrupted_ingest_run(&self) -> Result<Option<i64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id FROM ingest_runs WHERE finished_at IS NULL ORDER BY id DESC LIMIT 1",
+        )?;
+        let mut rows = stmt.query([])?;
+        match rows.next()? {
+            Some(row) => Ok(Some(row.get(0)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Record that a run finished one phase ("daily" or "hourly") of a station
+    ///
+    /// Inside the transaction that stores the station's data, call the
+    /// `mark_station_checkpoint` function on it instead, so the checkpoint exists
+    /// exactly when the data does.
+    ///
+    /// # Arguments
+    /// * `run_id` - Ingest run
+    /// * `station_id` - Station ID
+    /// * `phase` - "daily" or "hourly"
+    /// * `completed_at` - Time the phase finished (UTC)
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of rows affected
+    pub fn mark_station_checkpoint(
+        &self,
+        run_id: i64,
+        station_id: i64,
+        phase: &str,
+        completed_at: NaiveDateTime,
+    ) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        mark_station_checkpoint(&conn, run_id, station_id, phase, completed_at)
+    }
+
+    /// Get the stations a run has finished for one phase
+    ///
+    /// # Arguments
+    /// * `run_id` - Ingest run
+    /// * `phase` - "daily" or "hourly"
+    ///
+    /// # Returns
+    /// * `Result<HashSet<i64>>` - Station IDs with a checkpoint
+    pub fn get_checkpointed_stations(&self, run_id: i64, phase: &str) -> Result<HashSet<i64>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT station_id FROM ingest_checkpoints WHERE run_id = ?1 AND phase = ?2",
+        )?;
+        let ids = stmt.query_map(params![run_id, phase], |row| row.get(0))?;
+        ids.collect()
+    }
+
+    /// Get the most recent ingest runs, newest first
+    ///
+    /// # Arguments
+    /// * `limit` - Maximum number of runs to return
+    ///
+    /// # Returns
+    /// * `Result<Vec<IngestRun>>` - Runs, including any still in progress
+    pub fn get_recent_ingest_runs(&self, limit: usize) -> Result<Vec<IngestRun>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {} FROM ingest_runs ORDER BY id DESC LIMIT ?1",
+            INGEST_RUN_COLUMNS
+        ))?;
+
+        let runs = stmt.query_map(params![limit as i64], ingest_run_from_row)?;
+
+        let mut result = Vec::new();
+        for run in runs {
+            result.push(run?);
+        }
+        Ok(result)
+    }
+
+    /// Get a station by ID
+    ///
+    /// # Arguments
+    /// * `station_id` - Station ID
+    ///
+    /// # Returns
+    /// * `Result<Option<(i64, String, f64, f64)>>` - Station data (id, name, lon_x, lat_y) or None
+    #[allow(dead_code)]
+    pub fn get_station_by_id(&self, station_id: i64) -> Result<Option<(i64, String, f64, f64)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn
+            .prepare("SELECT id, name, lon_x, lat_y FROM stations WHERE id = ?1")?;
+
+        let mut rows = stmt.query(params![station_id])?;
+
+        if let Some(row) = rows.next()? {
+            let id: i64 = row.get(0)?;
+            let name: String = row.get(1)?;
+            let lon_x: f64 = row.get(2)?;
+            let lat_y: f64 = row.get(3)?;
+            Ok(Some((id, name, lon_x, lat_y)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Query all stations
+    ///
+    /// # Returns
+    /// * `Result<Vec<Station>>` - Vector of station data
+    pub fn get_all_stations(&self) -> Result<Vec<Station>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT id, name, lon_x, lat_y, dly_first_date, dly_last_date,
+                    province, climate_id, wmo_id, tc_id, elevation_m, timezone,
+                    source, source_id
+             FROM stations",
+        )?;
+
+        let stations = stmt.query_map([], |row| {
+            Ok(Station {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                lon_x: row.get(2)?,
+                lat_y: row.get(3)?,
+                dly_first_date: row.get(4)?,
+                dly_last_date: row.get(5)?,
+                metadata: StationMetadata {
+                    province: row.get(6)?,
+                    climate_id: row.get(7)?,
+                    wmo_id: row.get(8)?,
+                    tc_id: row.get(9)?,
+                    elevation_m: row.get(10)?,
+                    timezone: row.get(11)?,
+                    source: row.get(12)?,
+                    source_id: row.get(13)?,
+                },
+            })
+        })?;
+
+        let mut result = Vec::new();
+        for station in stations {
+            result.push(station?);
+        }
+        Ok(result)
+    }
+
+    /// Get climate data by station ID
+    ///
+    /// # Arguments
+    /// * `station_id` - Station ID
+    ///
+    /// # Returns
+    /// * `Result<Vec<ClimateData>>` - Vector of climate data for the station
+    #[allow(dead_code)]
+    pub fn get_data_by_station(&self, station_id: i64) -> Result<Vec<ClimateData>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            &format!(
+                "SELECT {} FROM data WHERE station_id = ?1
+                 ORDER BY year DESC, id DESC",
+                DATA_COLUMNS
+            ),
+        )?;
+
+        let data_entries = stmt.query_map(params![station_id], climate_data_from_row)?;
+
+        let mut result = Vec::new();
+        for entry in data_entries {
+            result.push(entry?);
+        }
+        Ok(result)
+    }
+
+    /// Get climate data by year
+    ///
+    /// # Arguments
+    /// * `year` - Year
+    ///
+    /// # Returns
+    /// * `Result<Vec<ClimateData>>` - Vector of climate data for the year
+    #[allow(dead_code)]
+    pub fn get_data_by_year(&self, year: i64) -> Result<Vec<ClimateData>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            &format!("SELECT {} FROM data WHERE year = ?1", DATA_COLUMNS),
+        )?;
+
+        let data_entries = stmt.query_map(params![year], climate_data_from_row)?;
+
+        let mut result = Vec::new();
+        for entry in data_entries {
+            result.push(entry?);
+        }
+        Ok(result)
+    }
+
+    /// Query all climate data
+    ///
+    /// # Returns
+    /// * `Result<Vec<ClimateData>>` - Vector of all climate data entries
+    #[allow(dead_code)]
+    pub fn get_all_data(&self) -> Result<Vec<ClimateData>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            &format!("SELECT {} FROM data", DATA_COLUMNS),
+        )?;
+
+        let data_entries = stmt.query_map([], climate_data_from_row)?;
+
+        let mut result = Vec::new();
+        for entry in data_entries {
+            result.push(entry?);
+        }
+        Ok(result)
+    }
+
+    /// Move the stations `Aggregator::import_csv_dir` created for a Climate ID onto
+    /// the catalogue's station for it; see the `merge_csv_stations` function
+    ///
+    /// # Arguments
+    /// * `climate_id` - ECCC Climate ID
+    /// * `station_id` - Catalogue station ID of the Climate ID
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of stations merged
+    pub fn merge_csv_stations(&self, climate_id: &str, station_id: i64) -> Result<usize> {
+        self.with_transaction(|tx| merge_csv_stations(tx, climate_id, station_id))
+    }
+
+    /// Delete a station and all its associated data, in one transaction
+    ///
+    /// # Arguments
+    /// * `station_id` - Station ID
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of rows affected
+    pub fn delete_station(&self, station_id: i64) -> Result<usize> {
+        self.with_transaction(|tx| delete_station(tx, station_id))
+    }
+
+    /// Execute a custom query
+    ///
+    /// # Arguments
+    /// * `query` - SQL query string
+    ///
+    /// # Returns
+    /// * `Result<usize>` - Number of rows affected
+    #[allow(dead_code)]
+    pub fn execute_query(&self, query: &str) -> Result<usize> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(query, [])
+    }
+
+    /// Begin a transaction
+    #[allow(dead_code)]
+    pub fn begin_transaction(&self) -> Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute("BEGIN TRANSACTION", [])?;
+        Ok(())
     }
 
     /// Commit a transaction
@@ -342,13 +1184,488 @@ impl Database {
         Ok(())
     }
 
+    /// Run `write` inside one transaction: commit if it succeeds, roll back if it fails
+    ///
+    /// The connection stays locked until the transaction ends, so readers on other
+    /// threads (e.g. API handlers during a scheduled refresh) never see its rows
+    /// before the commit, nor rows that are rolled back. `write` must run its
+    /// statements on `tx`, through the functions of this module that take a
+    /// connection; calling `Database` methods from it would wait for the lock forever.
+    ///
+    /// # Arguments
+    /// * `write` - Writes to perform atomically
+    ///
+    /// # Returns
+    /// * `Result<T, E>` - Result of `write`, or the error of a failed commit
+    pub fn with_transaction<T, E: From<rusqlite::Error>>(
+        &self,
+        write: impl FnOnce(&Transaction) -> std::result::Result<T, E>,
+    ) -> std::result::Result<T, E> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        // Dropping `tx` without committing rolls it back
+        let value = write(&tx)?;
+        tx.commit()?;
+        Ok(value)
+    }
+}
+
+// Statements run by the `Database` methods above on their locked connection, and by
+// `Database::with_transaction` callers on the transaction, which already holds the lock
+
+/// Move the stations `Aggregator::import_csv_dir` created for a Climate ID onto the
+/// catalogue's station for it, then delete them
+///
+/// Called once the station catalogue lists the Climate ID under ECCC's own station
+/// ID, so the station is not stored twice. The imported observations, switch dates
+/// and ingested months are reassigned to `station_id` rather than downloaded again;
+/// where the catalogue station already has a row for the same date, year or month,
+/// its own row is kept.
+///
+/// # Arguments
+/// * `climate_id` - ECCC Climate ID
+/// * `station_id` - Catalogue station ID of the Climate ID
+///
+/// # Returns
+/// * `Result<usize>` - Number of stations merged
+pub fn merge_csv_stations(conn: &Connection, climate_id: &str, station_id: i64) -> Result<usize> {
+    let ids: Vec<i64> = conn
+        .prepare("SELECT id FROM stations WHERE source = ?1 AND source_id = ?2 AND id != ?3")?
+        .query_map(params![SourceKind::Eccc.as_str(), climate_id, station_id], |row| {
+            row.get(0)
+        })?
+        .collect::<Result<_>>()?;
+    for id in &ids {
+        for table in ["daily_observations", "data", "ingested_months"] {
+            conn.execute(
+                &format!("UPDATE OR IGNORE {} SET station_id = ?1 WHERE station_id = ?2", table),
+                params![station_id, id],
+            )?;
+        }
+        delete_station(conn, *id)?;
+    }
+    Ok(ids.len())
+}
+
+/// Delete a station and all its associated data
+pub fn delete_station(conn: &Connection, station_id: i64) -> Result<usize> {
+    // First delete associated data
+    for table in [
+        "data",
+        "daily_observations",
+        "ingested_months",
+        "hourly_observations",
+        "ingested_hourly_months",
+        "station_ingest_status",
+        "climate_normals",
+        "ingest_checkpoints",
+    ] {
+        conn.execute(
+            &format!("DELETE FROM {} WHERE station_id = ?1", table),
+            params![station_id],
+        )?;
+    }
+    // Then delete the station
+    conn.execute("DELETE FROM stations WHERE id = ?1", params![station_id])
+}
+
+/// Insert a new station into the database
+pub fn insert_station(
+    conn: &Connection,
+    id: i64,
+    name: &String,
+    lon_x: f64,
+    lat_y: f64,
+    dly_first_date: Option<&str>,
+    dly_last_date: Option<&str>,
+) -> Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO stations (id, name, lon_x, lat_y, dly_first_date, dly_last_date) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        params![id, name, lon_x, lat_y, dly_first_date, dly_last_date],
+    )
+}
+
+/// Store the catalogue metadata of a station inserted with `insert_station`
+pub fn set_station_metadata(
+    conn: &Connection,
+    id: i64,
+    metadata: &StationMetadata,
+) -> Result<usize> {
+    conn.execute(
+        "UPDATE stations SET
+            province = ?2, climate_id = ?3, wmo_id = ?4, tc_id = ?5,
+            elevation_m = ?6, timezone = ?7, source = ?8, source_id = ?9
+         WHERE id = ?1",
+        params![
+            id,
+            metadata.province,
+            metadata.climate_id,
+            metadata.wmo_id,
+            metadata.tc_id,
+            metadata.elevation_m,
+            metadata.timezone,
+            metadata.source,
+            metadata.source_id,
+        ],
+    )
+}
+
+/// Insert or update the switch dates of a station for one season year
+pub fn insert_data(
+    conn: &Connection,
+    station_id: i64,
+    year: i64,
+    switch_to_summer: Option<&str>,
+    switch_to_winter: Option<&str>,
+) -> Result<i64> {
+    conn.query_row(
+        "INSERT INTO data (station_id, year, switch_to_summer, switch_to_winter)
+         VALUES (?1, ?2, ?3, ?4)
+         ON CONFLICT (station_id, year) DO UPDATE SET
+            switch_to_summer = excluded.switch_to_summer,
+            switch_to_winter = excluded.switch_to_winter
+         RETURNING id",
+        params![
+            station_id,
+            year,
+            switch_to_summer,
+            switch_to_winter
+        ],
+        |row| row.get(0),
+    )
+}
+
+/// Delete a station's switch dates for season years before `first_year`
+pub fn delete_data_before(conn: &Connection, station_id: i64, first_year: i64) -> Result<usize> {
+    conn.execute(
+        "DELETE FROM data WHERE station_id = ?1 AND year < ?2",
+        params![station_id, first_year],
+    )
+}
+
+/// Store the completeness of the season windows behind a station-year's switch dates
+pub fn set_season_completeness(
+    conn: &Connection,
+    station_id: i64,
+    year: i64,
+    spring: &SeasonCompleteness,
+    fall: &SeasonCompleteness,
+) -> Result<usize> {
+    conn.execute(
+        "UPDATE data SET
+            spring_valid_pct = ?3, spring_flagged_days = ?4,
+            fall_valid_pct = ?5, fall_flagged_days = ?6
+         WHERE station_id = ?1 AND year = ?2",
+        params![
+            station_id,
+            year,
+            spring.valid_pct,
+            spring.flagged_days,
+            fall.valid_pct,
+            fall.flagged_days
+        ],
+    )
+}
+
+/// Insert or replace raw daily observations
+pub fn insert_daily_observations(
+    conn: &Connection,
+    observations: &[DailyObservation],
+) -> Result<usize> {
+    let mut written = 0;
+    let mut stmt = conn.prepare(&format!(
+        "INSERT OR REPLACE INTO daily_observations ({})
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17,
+                 ?18, ?19)",
+        DAILY_OBSERVATION_COLUMNS
+    ))?;
+    for obs in observations {
+        written += stmt.execute(params![
+            obs.station_id,
+            obs.date,
+            obs.mean_temp,
+            obs.mean_temp_flag,
+            obs.min_temp,
+            obs.min_temp_flag,
+            obs.max_temp,
+            obs.max_temp_flag,
+            obs.total_rain,
+            obs.total_rain_flag,
+            obs.total_snow,
+            obs.total_snow_flag,
+            obs.total_precip,
+            obs.total_precip_flag,
+            obs.snow_on_ground,
+            obs.snow_on_ground_flag,
+            obs.mean_temp_imputed,
+            obs.min_temp_imputed,
+            obs.max_temp_imputed
+        ])?;
+    }
+    Ok(written)
+}
+
+/// Get daily observations for a station within an inclusive date range
+pub fn get_daily_observations(
+    conn: &Connection,
+    station_id: i64,
+    start_date: &str,
+    end_date: &str,
+) -> Result<Vec<DailyObservation>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM daily_observations
+         WHERE station_id = ?1 AND date >= ?2 AND date <= ?3
+         ORDER BY date",
+        DAILY_OBSERVATION_COLUMNS
+    ))?;
 
+    let rows = stmt.query_map(
+        params![station_id, start_date, end_date],
+        daily_observation_from_row,
+    )?;
+
+    let mut result = Vec::new();
+    for row in rows {
+        result.push(row?);
+    }
+    Ok(result)
+}
+
+/// Insert or replace raw hourly observations
+pub fn insert_hourly_observations(
+    conn: &Connection,
+    observations: &[HourlyObservation],
+) -> Result<usize> {
+    let mut written = 0;
+    let mut stmt = conn.prepare(
+        "INSERT OR REPLACE INTO hourly_observations
+         (station_id, date_time, temp, temp_flag, dew_point, dew_point_flag)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+    )?;
+    for obs in observations {
+        written += stmt.execute(params![
+            obs.station_id,
+            obs.date_time,
+            obs.temp,
+            obs.temp_flag,
+            obs.dew_point,
+            obs.dew_point_flag
+        ])?;
+    }
+    Ok(written)
+}
+
+/// Record that a (station, year, month) slice has been ingested
+pub fn mark_month_ingested(
+    conn: &Connection,
+    station_id: i64,
+    year: i32,
+    month: u32,
+    complete: bool,
+    fetched_at: NaiveDateTime,
+) -> Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO ingested_months (station_id, year, month, complete, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![station_id, year, month, complete, timestamp(fetched_at)],
+    )
+}
+
+/// Record that a (station, year, month) slice of hourly data has been ingested
+pub fn mark_hourly_month_ingested(
+    conn: &Connection,
+    station_id: i64,
+    year: i32,
+    month: u32,
+    complete: bool,
+    fetched_at: NaiveDateTime,
+) -> Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO ingested_hourly_months
+         (station_id, year, month, complete, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)",
+        params![station_id, year, month, complete, timestamp(fetched_at)],
+    )
+}
+
+/// Record whether the last climate fetch for a station was complete
+pub fn set_station_ingest_status(
+    conn: &Connection,
+    station_id: i64,
+    complete: bool,
+    failed_months: usize,
+    updated_at: NaiveDateTime,
+) -> Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO station_ingest_status
+         (station_id, complete, failed_months, updated_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![station_id, complete, failed_months as i64, timestamp(updated_at)],
+    )
+}
+
+/// Record that a run finished one phase ("daily" or "hourly") of a station
+pub fn mark_station_checkpoint(
+    conn: &Connection,
+    run_id: i64,
+    station_id: i64,
+    phase: &str,
+    completed_at: NaiveDateTime,
+) -> Result<usize> {
+    conn.execute(
+        "INSERT OR REPLACE INTO ingest_checkpoints (run_id, station_id, phase, completed_at)
+         VALUES (?1, ?2, ?3, ?4)",
+        params![run_id, station_id, phase, timestamp(completed_at)],
+    )
+}
+
+/// Replace the climate normals of a station
+pub fn replace_climate_normals(
+    conn: &Connection,
+    station_id: i64,
+    normals: &[ClimateNormal],
+    fetched_at: NaiveDateTime,
+) -> Result<usize> {
+    conn.execute(
+        "DELETE FROM climate_normals WHERE station_id = ?1",
+        params![station_id],
+    )?;
+    let mut written = 0;
+    let mut stmt = conn.prepare(
+        "INSERT OR REPLACE INTO climate_normals
+         (station_id, month, mean_temp, normal_code, first_year, last_year, fetched_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+    )?;
+    let fetched_at = timestamp(fetched_at);
+    for normal in normals {
+        written += stmt.execute(params![
+            station_id,
+            normal.month,
+            normal.mean_temp,
+            normal.normal_code,
+            normal.first_year,
+            normal.last_year,
+            fetched_at
+        ])?;
+    }
+    Ok(written)
+}
+
+/// Stored form of an ingest timestamp, the same as SQLite's `datetime()`
+///
+/// Every timestamp is passed in rather than taken from `datetime('now')`, so an
+/// ingest replayed with the time it was recorded writes the same rows.
+fn timestamp(at: NaiveDateTime) -> String {
+    at.format("%Y-%m-%d %H:%M:%S").to_string()
+}
+
+/// Whether a table has a column
+fn has_column(conn: &Connection, table: &str, column: &str) -> Result<bool> {
+    let mut stmt = conn.prepare(&format!("PRAGMA table_info({})", table))?;
+    Ok(stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<_>>>()?
+        .iter()
+        .any(|name| name == column))
+}
+
+/// Add a column to an existing table unless it is already there
+fn add_column_if_missing(
+    conn: &Connection,
+    table: &str,
+    column: &str,
+    definition: &str,
+) -> Result<()> {
+    if !has_column(conn, table, column)? {
+        conn.execute(
+            &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+            [],
+        )?;
+    }
+    Ok(())
+}
+
+fn ingest_run_from_row(row: &rusqlite::Row) -> Result<IngestRun> {
+    let errors: String = row.get(19)?;
+    Ok(IngestRun {
+        id: row.get(0)?,
+        trigger: row.get(1)?,
+        started_at: row.get(2)?,
+        finished_at: row.get(3)?,
+        status: row.get(4)?,
+        stations_base_url: row.get(5)?,
+        climate_base_url: row.get(6)?,
+        daily_source: row.get(7)?,
+        stations_seen: row.get(8)?,
+        stations_inserted: row.get(9)?,
+        stations_inactive: row.get(10)?,
+        stations_insufficient_data: row.get(11)?,
+        stations_skipped: row.get(12)?,
+        stations_failed: row.get(13)?,
+        stations_incomplete: row.get(14)?,
+        months_fetched: row.get(15)?,
+        months_failed: row.get(16)?,
+        hourly_months_failed: row.get(17)?,
+        error_count: row.get(18)?,
+        errors: serde_json::from_str(&errors).unwrap_or_default(),
+        stations_resumed: row.get(20)?,
+    })
+}
+
+fn climate_data_from_row(row: &rusqlite::Row) -> Result<ClimateData> {
+    let completeness = |pct: Option<f64>, flagged: Option<i64>| {
+        pct.map(|valid_pct| SeasonCompleteness {
+            valid_pct,
+            flagged_days: flagged.unwrap_or(0),
+        })
+    };
+    Ok(ClimateData {
+        id: row.get(0)?,
+        station_id: row.get(1)?,
+        year: row.get(2)?,
+        switch_to_summer: row.get(3)?,
+        switch_to_winter: row.get(4)?,
+        spring_completeness: completeness(row.get(5)?, row.get(6)?),
+        fall_completeness: completeness(row.get(7)?, row.get(8)?),
+    })
+}
+
+fn daily_observation_from_row(row: &rusqlite::Row) -> Result<DailyObservation> {
+    Ok(DailyObservation {
+        station_id: row.get(0)?,
+        date: row.get(1)?,
+        mean_temp: row.get(2)?,
+        mean_temp_flag: row.get(3)?,
+        min_temp: row.get(4)?,
+        min_temp_flag: row.get(5)?,
+        max_temp: row.get(6)?,
+        max_temp_flag: row.get(7)?,
+        total_rain: row.get(8)?,
+        total_rain_flag: row.get(9)?,
+        total_snow: row.get(10)?,
+        total_snow_flag: row.get(11)?,
+        total_precip: row.get(12)?,
+        total_precip_flag: row.get(13)?,
+        snow_on_ground: row.get(14)?,
+        snow_on_ground_flag: row.get(15)?,
+        mean_temp_imputed: row.get(16)?,
+        min_temp_imputed: row.get(17)?,
+        max_temp_imputed: row.get(18)?,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Fixed time for the rows the tests write
+    fn at() -> NaiveDateTime {
+        chrono::NaiveDate::from_ymd_opt(2026, 10, 16)
+            .unwrap()
+            .and_hms_opt(12, 0, 0)
+            .unwrap()
+    }
+
     #[test]
     fn test_database_initialization() {
         let db = Database::new_in_memory().unwrap();
@@ -403,6 +1720,93 @@ mod tests {
         assert_eq!(data[0].switch_to_summer, Some("2023-10-20".to_string()));
     }
 
+    #[test]
+    fn test_insert_data_updates_existing_year() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+
+        db.insert_station(4607, &"Test Station".to_string(), -79.4, 43.7, None, None)
+            .unwrap();
+        let first_id = db
+            .insert_data(4607, 2023, Some("2023-04-20"), None)
+            .unwrap();
+        let second_id = db
+            .insert_data(4607, 2023, Some("2023-04-18"), Some("2023-10-30"))
+            .unwrap();
+        db.insert_data(4607, 2022, Some("2022-04-10"), Some("2022-10-21"))
+            .unwrap();
+
+        assert_eq!(first_id, second_id);
+        let data = db.get_data_by_station(4607).unwrap();
+        assert_eq!(data.len(), 2);
+        assert_eq!(data[0].year, 2023);
+        assert_eq!(data[0].switch_to_summer.as_deref(), Some("2023-04-18"));
+        assert_eq!(data[0].switch_to_winter.as_deref(), Some("2023-10-30"));
+    }
+
+    #[test]
+    fn test_season_completeness_round_trip() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+
+        db.insert_station(4607, &"Test Station".to_string(), -79.4, 43.7, None, None)
+            .unwrap();
+        db.insert_data(4607, 2022, Some("2022-04-10"), None).unwrap();
+        db.insert_data(4607, 2023, Some("2023-04-12"), Some("2023-10-20"))
+            .unwrap();
+
+        let spring = SeasonCompleteness { valid_pct: 62.5, flagged_days: 40 };
+        let fall = SeasonCompleteness { valid_pct: 100.0, flagged_days: 0 };
+        assert_eq!(db.set_season_completeness(4607, 2023, &spring, &fall).unwrap(), 1);
+        assert_eq!(db.set_season_completeness(4607, 2019, &spring, &fall).unwrap(), 0);
+
+        let data = db.get_data_by_station(4607).unwrap();
+        assert_eq!(data[0].year, 2023);
+        assert_eq!(data[0].spring_completeness, Some(spring));
+        assert_eq!(data[0].fall_completeness, Some(fall));
+        // Rows written before completeness was tracked have none
+        assert_eq!(data[1].spring_completeness, None);
+
+        // Recomputing the dates keeps the stored completeness until it is replaced
+        db.insert_data(4607, 2023, Some("2023-04-13"), Some("2023-10-20"))
+            .unwrap();
+        assert_eq!(db.get_data_by_year(2023).unwrap()[0].spring_completeness, Some(spring));
+    }
+
+    #[test]
+    fn test_legacy_averaged_data_rows_are_dropped() {
+        let db = Database::new_in_memory().unwrap();
+        db.execute_query(
+            "CREATE TABLE data (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                station_id INTEGER NOT NULL,
+                year INTEGER NOT NULL,
+                switch_to_summer TEXT,
+                switch_to_winter TEXT
+            )",
+        )
+        .unwrap();
+        // One averaged row per run, the second run appending a duplicate
+        db.execute_query(
+            "INSERT INTO data (station_id, year, switch_to_summer, switch_to_winter) VALUES
+                (4607, 2024, '2024-04-10', '2024-11-02'),
+                (4607, 2024, '2024-04-12', '2024-11-01'),
+                (5051, 2023, '2023-04-15', '2023-10-28')",
+        )
+        .unwrap();
+        db.initialize_schema().unwrap();
+
+        assert!(db.get_all_data().unwrap().is_empty());
+
+        // Per-year rows stored after the migration survive the next start
+        db.insert_data(4607, 2024, Some("2024-04-11"), Some("2024-11-03"))
+            .unwrap();
+        db.initialize_schema().unwrap();
+        let data = db.get_data_by_station(4607).unwrap();
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].switch_to_summer.as_deref(), Some("2024-04-11"));
+    }
+
     #[test]
     fn test_get_all_stations() {
         let db = Database::new_in_memory().unwrap();
@@ -433,6 +1837,10 @@ mod tests {
             .unwrap();
         db.insert_data(4607, 2023, None, None)
             .unwrap();
+        let run_id = db
+            .start_ingest_run("cli", "https://stations", "https://climate", "csv", at())
+            .unwrap();
+        db.mark_station_checkpoint(run_id, 4607, "daily", at()).unwrap();
 
         db.delete_station(4607).unwrap();
 
@@ -441,5 +1849,372 @@ mod tests {
 
         let data = db.get_data_by_station(4607).unwrap();
         assert_eq!(data.len(), 0);
+        assert!(db.get_checkpointed_stations(run_id, "daily").unwrap().is_empty());
+    }
+
+    fn observation(station_id: i64, date: &str, mean_temp: Option<f64>) -> DailyObservation {
+        DailyObservation {
+            station_id,
+            date: date.to_string(),
+            mean_temp,
+            mean_temp_flag: mean_temp.is_none().then(|| "M".to_string()),
+            min_temp: mean_temp.map(|t| t - 4.0),
+            max_temp: mean_temp.map(|t| t + 4.0),
+            total_precip: Some(0.0),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_daily_observations_date_range() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+
+        db.insert_station(4607, &"Test Station".to_string(), -79.4, 43.7, None, None)
+            .unwrap();
+        db.insert_station(5051, &"Other Station".to_string(), -80.0, 44.0, None, None)
+            .unwrap();
+
+        let written = db
+            .insert_daily_observations(&[
+                observation(4607, "2023-03-31", Some(2.5)),
+                observation(4607, "2023-04-01", Some(6.0)),
+                observation(4607, "2023-04-02", None),
+                observation(5051, "2023-04-01", Some(5.0)),
+            ])
+            .unwrap();
+        assert_eq!(written, 4);
+
+        let april = db
+            .get_daily_observations(4607, "2023-04-01", "2023-04-30")
+            .unwrap();
+        assert_eq!(april.len(), 2);
+        assert_eq!(april[0].date, "2023-04-01");
+        assert_eq!(april[0].min_temp, Some(2.0));
+        assert_eq!(april[1].mean_temp, None);
+        assert_eq!(april[1].mean_temp_flag.as_deref(), Some("M"));
+
+        let all = db
+            .get_daily_observations_between("2023-04-01", "2023-04-01")
+            .unwrap();
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[1].station_id, 5051);
+    }
+
+    #[test]
+    fn test_daily_observations_replace_existing_day() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+
+        db.insert_station(4607, &"Test Station".to_string(), -79.4, 43.7, None, None)
+            .unwrap();
+
+        db.insert_daily_observations(&[observation(4607, "2023-04-01", None)])
+            .unwrap();
+        db.insert_daily_observations(&[observation(4607, "2023-04-01", Some(8.5))])
+            .unwrap();
+
+        let rows = db
+            .get_daily_observations(4607, "2023-01-01", "2023-12-31")
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].mean_temp, Some(8.5));
+    }
+
+    #[test]
+    fn test_complete_months_exclude_open_months() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+
+        db.insert_station(4607, &"Test Station".to_string(), -79.4, 43.7, None, None)
+            .unwrap();
+        db.mark_month_ingested(4607, 2023, 1, true, at()).unwrap();
+        db.mark_month_ingested(4607, 2023, 2, false, at()).unwrap();
+
+        let complete = db.get_complete_months(4607).unwrap();
+        assert!(complete.contains(&(2023, 1)));
+        assert!(!complete.contains(&(2023, 2)));
+
+        // A later fetch after the month closed marks it complete
+        db.mark_month_ingested(4607, 2023, 2, true, at()).unwrap();
+        assert_eq!(db.get_complete_months(4607).unwrap().len(), 2);
+        assert!(db.get_complete_months(5051).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_ingest_runs_round_trip() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+
+        let first = db
+            .start_ingest_run("cli", "https://stations", "https://climate", "csv", at())
+            .unwrap();
+        let second = db
+            .start_ingest_run("schedule", "https://stations", "https://climate", "ogc", at())
+            .unwrap();
+        db.finish_ingest_run(
+            &IngestRun {
+                id: first,
+                status: "partial".to_string(),
+                stations_seen: 10,
+                stations_inserted: 8,
+                months_failed: 2,
+                error_count: 3,
+                errors: vec!["STATION A: timeout".to_string()],
+                ..Default::default()
+            },
+            at(),
+        )
+        .unwrap();
+
+        let runs = db.get_recent_ingest_runs(10).unwrap();
+        assert_eq!(runs.len(), 2);
+        // Newest first; the second run has not finished yet
+        assert_eq!(runs[0].id, second);
+        assert_eq!(runs[0].status, "running");
+        assert_eq!(runs[0].trigger, "schedule");
+        assert_eq!(runs[0].daily_source, "ogc");
+        assert!(runs[0].finished_at.is_none());
+        assert!(runs[0].errors.is_empty());
+
+        assert_eq!(runs[1].status, "partial");
+        assert_eq!(runs[1].trigger, "cli");
+        assert_eq!(runs[1].stations_base_url, "https://stations");
+        assert_eq!(runs[1].started_at, "2026-10-16 12:00:00");
+        assert_eq!(runs[1].finished_at.as_deref(), Some("2026-10-16 12:00:00"));
+        assert_eq!(runs[1].stations_inserted, 8);
+        assert_eq!(runs[1].months_failed, 2);
+        assert_eq!(runs[1].error_count, 3);
+        assert_eq!(runs[1].errors, vec!["STATION A: timeout".to_string()]);
+
+        assert_eq!(db.get_recent_ingest_runs(1).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_hourly_observations_date_range() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+
+        db.insert_station(4607, &"Test Station".to_string(), -79.4, 43.7, None, None)
+            .unwrap();
+        let hour = |date_time: &str, temp: Option<f64>| HourlyObservation {
+            station_id: 4607,
+            date_time: date_time.to_string(),
+            temp,
+            temp_flag: temp.is_none().then(|| "M".to_string()),
+            dew_point: temp.map(|t| t - 2.0),
+            dew_point_flag: None,
+        };
+        db.insert_hourly_observations(&[
+            hour("2023-03-31 23:00", Some(1.0)),
+            hour("2023-04-01 00:00", Some(-0.5)),
+            hour("2023-04-01 23:00", None),
+            hour("2023-04-02 00:00", Some(3.0)),
+        ])
+        .unwrap();
+        db.insert_hourly_observations(&[hour("2023-04-01 00:00", Some(-1.5))])
+            .unwrap();
+
+        let april_first = db
+            .get_hourly_observations(4607, "2023-04-01", "2023-04-01")
+            .unwrap();
+        assert_eq!(april_first.len(), 2);
+        assert_eq!(april_first[0].temp, Some(-1.5));
+        assert_eq!(april_first[0].dew_point, Some(-3.5));
+        assert_eq!(april_first[1].temp_flag.as_deref(), Some("M"));
+
+        db.mark_hourly_month_ingested(4607, 2023, 3, true, at()).unwrap();
+        db.mark_hourly_month_ingested(4607, 2023, 4, false, at()).unwrap();
+        let complete = db.get_complete_hourly_months(4607).unwrap();
+        assert!(complete.contains(&(2023, 3)));
+        assert!(!complete.contains(&(2023, 4)));
+        // Daily and hourly months are tracked independently
+        assert!(db.get_complete_months(4607).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_station_metadata_migrates_legacy_table() {
+        let db = Database::new_in_memory().unwrap();
+        db.execute_query(
+            "CREATE TABLE stations (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL,
+                lon_x REAL NOT NULL,
+                lat_y REAL NOT NULL,
+                dly_first_date TEXT,
+                dly_last_date TEXT
+            )",
+        )
+        .unwrap();
+        db.execute_query(
+            "INSERT INTO stations VALUES (5051, 'Old Station', -80.0, 44.0, NULL, NULL)",
+        )
+        .unwrap();
+        db.initialize_schema().unwrap();
+
+        db.insert_station(4607, &"TORONTO CITY".to_string(), -79.4, 43.67, None, None)
+            .unwrap();
+        let metadata = StationMetadata {
+            province: Some("ON".to_string()),
+            climate_id: Some("6158355".to_string()),
+            wmo_id: Some(71508),
+            tc_id: Some("XTO".to_string()),
+            elevation_m: Some(112.5),
+            timezone: Some("EST".to_string()),
+            source: Some("eccc".to_string()),
+            source_id: None,
+        };
+        assert_eq!(db.set_station_metadata(4607, &metadata).unwrap(), 1);
+
+        let stations = db.get_all_stations().unwrap();
+        let toronto = stations.iter().find(|s| s.id == 4607).unwrap();
+        assert_eq!(toronto.metadata, metadata);
+        // Stations stored before the migration simply have no metadata yet
+        let old = stations.iter().find(|s| s.id == 5051).unwrap();
+        assert_eq!(old.metadata, StationMetadata::default());
+    }
+
+    #[test]
+    fn test_climate_normals_are_replaced_per_station() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+        db.insert_station(1, &"A".to_string(), -79.4, 43.7, None, None)
+            .unwrap();
+        let normal = |month, mean_temp| ClimateNormal {
+            station_id: 1,
+            month,
+            mean_temp,
+            normal_code: Some("A".to_string()),
+            first_year: Some(1991),
+            last_year: Some(2020),
+        };
+
+        db.replace_climate_normals(1, &[normal(2, -5.0), normal(1, -6.0)], at())
+            .unwrap();
+        assert_eq!(
+            db.get_climate_normals(1).unwrap(),
+            vec![normal(1, -6.0), normal(2, -5.0)]
+        );
+
+        db.replace_climate_normals(1, &[normal(1, -5.5)], at()).unwrap();
+        assert_eq!(db.get_climate_normals(1).unwrap(), vec![normal(1, -5.5)]);
+
+        db.delete_station(1).unwrap();
+        assert!(db.get_climate_normals(1).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_checkpoints_belong_to_unfinished_runs() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+        assert_eq!(db.get_interrupted_ingest_run().unwrap(), None);
+
+        let run = db
+            .start_ingest_run("cli", "https://stations", "https://climate", "csv", at())
+            .unwrap();
+        db.mark_station_checkpoint(run, 4607, "daily", at()).unwrap();
+        db.mark_station_checkpoint(run, 4607, "daily", at()).unwrap();
+        db.mark_station_checkpoint(run, 5051, "hourly", at()).unwrap();
+        assert_eq!(db.get_interrupted_ingest_run().unwrap(), Some(run));
+        assert_eq!(
+            db.get_checkpointed_stations(run, "daily").unwrap(),
+            HashSet::from([4607])
+        );
+
+        db.finish_ingest_run(
+            &IngestRun {
+                id: run,
+                status: "ok".to_string(),
+                stations_resumed: 1,
+                ..Default::default()
+            },
+            at(),
+        )
+        .unwrap();
+        assert_eq!(db.get_interrupted_ingest_run().unwrap(), None);
+        assert!(db.get_checkpointed_stations(run, "hourly").unwrap().is_empty());
+        assert_eq!(db.get_recent_ingest_runs(1).unwrap()[0].stations_resumed, 1);
+    }
+
+    #[test]
+    fn test_with_transaction_rolls_back_on_error() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+        db.insert_station(4607, &"TORONTO CITY".to_string(), -79.4, 43.67, None, None)
+            .unwrap();
+        let observation = |date: &str| DailyObservation {
+            station_id: 4607,
+            date: date.to_string(),
+            mean_temp: Some(5.0),
+            ..Default::default()
+        };
+
+        let failed: std::result::Result<(), rusqlite::Error> = db.with_transaction(|tx| {
+            insert_daily_observations(tx, &[observation("2024-03-01")])?;
+            mark_month_ingested(tx, 4607, 2024, 3, true, at())?;
+            Err(rusqlite::Error::InvalidQuery)
+        });
+        assert!(failed.is_err());
+        assert!(db.get_complete_months(4607).unwrap().is_empty());
+        assert!(db
+            .get_daily_observations(4607, "2024-03-01", "2024-03-31")
+            .unwrap()
+            .is_empty());
+
+        db.with_transaction(|tx| {
+            insert_daily_observations(tx, &[observation("2024-03-01")])?;
+            mark_month_ingested(tx, 4607, 2024, 3, true, at())
+        })
+        .unwrap();
+        assert_eq!(db.get_complete_months(4607).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_readers_wait_for_a_transaction_and_never_see_its_rolled_back_rows() {
+        let db = std::sync::Arc::new(Database::new_in_memory().unwrap());
+        db.initialize_schema().unwrap();
+        let (written, read) = std::sync::mpsc::channel();
+
+        let writer = {
+            let db = std::sync::Arc::clone(&db);
+            std::thread::spawn(move || {
+                db.with_transaction(|tx| {
+                    let name = "TORONTO CITY".to_string();
+                    insert_station(tx, 4607, &name, -79.4, 43.67, None, None)?;
+                    mark_month_ingested(tx, 4607, 2024, 3, true, at())?;
+                    // Let the reader try while the rows are written but not committed
+                    written.send(()).unwrap();
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                    Err::<(), _>(rusqlite::Error::InvalidQuery)
+                })
+            })
+        };
+
+        read.recv().unwrap();
+        // Blocks until the writer's transaction has been rolled back
+        assert!(db.get_all_stations().unwrap().is_empty());
+        assert!(db.get_complete_months(4607).unwrap().is_empty());
+        assert!(writer.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_copy_to_leaves_the_original_untouched() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+        db.insert_station(4607, &"TORONTO CITY".to_string(), -79.4, 43.67, None, None)
+            .unwrap();
+
+        let path = std::env::temp_dir()
+            .join(format!("tireswap-copy-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        db.copy_to(&path).unwrap();
+        let copy = Database::new(&path).unwrap();
+        copy.delete_station(4607).unwrap();
+        // A copy is never written over an existing file
+        assert!(db.copy_to(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(copy.get_all_stations().unwrap().is_empty());
+        assert_eq!(db.get_all_stations().unwrap().len(), 1);
     }
 }
@@ -0,0 +1,283 @@
+use crate::aggregator::StationFilter;
+use crate::analyzer::{Analyzer, AnalyzerOptions};
+use crate::db::Database;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Season a recommended date belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Season {
+    /// Switch to summer tires
+    Spring,
+    /// Switch to winter tires
+    Fall,
+}
+
+impl Season {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Season::Spring => "spring",
+            Season::Fall => "fall",
+        }
+    }
+}
+
+/// A station whose recommended date moved by more than the allowed number of days,
+/// or lost its date altogether
+#[derive(Debug, Clone, PartialEq)]
+pub struct DateChange {
+    pub station_id: i64,
+    pub station_name: String,
+    pub season: Season,
+    /// Day of year in the current database
+    pub before: u32,
+    /// Day of year in the updated database; `None` if the station no longer has one
+    pub after: Option<u32>,
+}
+
+impl DateChange {
+    /// Signed shift in days; `None` when the date was lost
+    pub fn shift_days(&self) -> Option<i64> {
+        self.after.map(|after| after as i64 - self.before as i64)
+    }
+}
+
+/// Limits a dry run must stay within for its update to be considered safe
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffThresholds {
+    /// A recommendation that moves by more than this many days counts as changed
+    pub max_date_shift_days: u32,
+    /// Changed recommendations allowed
+    pub max_changed_dates: usize,
+    /// Stations allowed to disappear
+    pub max_removed_stations: usize,
+}
+
+impl Default for DiffThresholds {
+    fn default() -> Self {
+        DiffThresholds {
+            max_date_shift_days: 7,
+            max_changed_dates: 0,
+            max_removed_stations: 0,
+        }
+    }
+}
+
+/// Differences between the current database and the result of an update
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DatabaseDiff {
+    /// (ID, name) of stations only in the updated database
+    pub added_stations: Vec<(i64, String)>,
+    /// (ID, name) of stations only in the current database, or no longer in the
+    /// catalogue (see `add_catalogue_removals`)
+    pub removed_stations: Vec<(i64, String)>,
+    /// Per-station recommendations that moved by more than `max_date_shift_days`
+    pub changed_dates: Vec<DateChange>,
+}
+
+impl DatabaseDiff {
+    /// Compare the stations and per-station recommendations of two databases
+    ///
+    /// A station's recommendation is the date it produces on its own (see
+    /// `Analyzer::station_days`). Only stations present in both databases are
+    /// compared, and a date that first appears in the update is not a change.
+    ///
+    /// # Arguments
+    /// * `current` - Database currently served to users
+    /// * `updated` - Database produced by the update
+    /// * `options` - Analyzer settings used to compute recommendations in both
+    /// * `max_date_shift_days` - Moves of up to this many days are ignored
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn std::error::Error>>` - Differences or error
+    pub fn compare(
+        current: &Database,
+        updated: &Database,
+        options: &AnalyzerOptions,
+        max_date_shift_days: u32,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let names = |db: &Database| -> Result<BTreeMap<i64, String>, rusqlite::Error> {
+            Ok(db.get_all_stations()?.into_iter().map(|s| (s.id, s.name)).collect())
+        };
+        let before = names(current)?;
+        let after = names(updated)?;
+
+        let mut diff = DatabaseDiff::default();
+        for (id, name) in &after {
+            if !before.contains_key(id) {
+                diff.added_stations.push((*id, name.clone()));
+            }
+        }
+        for (id, name) in &before {
+            if !after.contains_key(id) {
+                diff.removed_stations.push((*id, name.clone()));
+            }
+        }
+
+        let current_analyzer = Analyzer::with_options(current, options.clone())?;
+        let updated_analyzer = Analyzer::with_options(updated, options.clone())?;
+        for (id, name) in before.iter().filter(|(id, _)| after.contains_key(id)) {
+            let (spring_before, fall_before) = current_analyzer.station_days(*id)?;
+            let (spring_after, fall_after) = updated_analyzer.station_days(*id)?;
+            for (season, before, after) in [
+                (Season::Spring, spring_before, spring_after),
+                (Season::Fall, fall_before, fall_after),
+            ] {
+                let Some(before) = before else {
+                    continue;
+                };
+                let moved = after.is_none_or(|after| before.abs_diff(after) > max_date_shift_days);
+                if moved {
+                    diff.changed_dates.push(DateChange {
+                        station_id: *id,
+                        station_name: name.clone(),
+                        season,
+                        before,
+                        after,
+                    });
+                }
+            }
+        }
+
+        Ok(diff)
+    }
+
+    /// Count as removed the stations of `current` that the fetched catalogue dropped
+    ///
+    /// An update keeps stations that disappear from the catalogue (or become inactive)
+    /// but no longer refreshes them, so comparing databases alone never shows them.
+    /// Stations from other sources or outside `filter` were not part of the update and
+    /// are left out.
+    ///
+    /// # Arguments
+    /// * `current` - Database currently served to users
+    /// * `source` - Name of the source the update listed, e.g. "eccc"
+    /// * `filter` - Station filter the update ran with
+    /// * `stored_ids` - Stations the catalogue listed and the update stored
+    ///
+    /// # Returns
+    /// * `Result<(), rusqlite::Error>` - Success or error
+    pub fn add_catalogue_removals(
+        &mut self,
+        current: &Database,
+        source: &str,
+        filter: &StationFilter,
+        stored_ids: &BTreeSet<i64>,
+    ) -> Result<(), rusqlite::Error> {
+        let already: BTreeSet<i64> = self.removed_stations.iter().map(|(id, _)| *id).collect();
+        for station in current.get_all_stations()? {
+            let province = station.metadata.province.as_deref();
+            if station.metadata.source_name() == source
+                && filter.matches(station.id, province, station.lon_x, station.lat_y)
+                && !stored_ids.contains(&station.id)
+                && !already.contains(&station.id)
+            {
+                self.removed_stations.push((station.id, station.name));
+            }
+        }
+        self.removed_stations.sort();
+        Ok(())
+    }
+
+    /// Thresholds the diff exceeds, as messages; empty when the update looks safe
+    ///
+    /// # Arguments
+    /// * `thresholds` - Limits to check against
+    ///
+    /// # Returns
+    /// * `Vec<String>` - One message per exceeded threshold
+    pub fn exceeded(&self, thresholds: &DiffThresholds) -> Vec<String> {
+        let mut exceeded = Vec::new();
+        if self.removed_stations.len() > thresholds.max_removed_stations {
+            exceeded.push(format!(
+                "{} stations removed (allowed: {})",
+                self.removed_stations.len(),
+                thresholds.max_removed_stations
+            ));
+        }
+        if self.changed_dates.len() > thresholds.max_changed_dates {
+            exceeded.push(format!(
+                "{} recommendations moved by more than {} days (allowed: {})",
+                self.changed_dates.len(),
+                thresholds.max_date_shift_days,
+                thresholds.max_changed_dates
+            ));
+        }
+        exceeded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_db(stations: &[(i64, &str)]) -> Database {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+        for (id, name) in stations {
+            db.insert_station(*id, &name.to_string(), -79.4, 43.7, None, None)
+                .unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_compare_reports_stations_and_moved_dates() {
+        let current = build_db(&[(1, "KEPT"), (2, "REMOVED")]);
+        current
+            .insert_data(1, 2024, Some("2024-04-10"), Some("2024-10-20"))
+            .unwrap();
+        let updated = build_db(&[(1, "KEPT"), (3, "ADDED")]);
+        // Spring moves by 5 days, fall by 12
+        updated
+            .insert_data(1, 2024, Some("2024-04-15"), Some("2024-11-01"))
+            .unwrap();
+
+        let diff = DatabaseDiff::compare(&current, &updated, &AnalyzerOptions::default(), 7)
+            .unwrap();
+
+        assert_eq!(diff.added_stations, vec![(3, "ADDED".to_string())]);
+        assert_eq!(diff.removed_stations, vec![(2, "REMOVED".to_string())]);
+        assert_eq!(diff.changed_dates.len(), 1);
+        let change = &diff.changed_dates[0];
+        assert_eq!(change.season, Season::Fall);
+        assert_eq!(change.shift_days(), Some(12));
+    }
+
+    #[test]
+    fn test_lost_dates_count_and_new_dates_do_not() {
+        let current = build_db(&[(1, "LOST"), (2, "NEW DATA")]);
+        current
+            .insert_data(1, 2024, Some("2024-04-10"), None)
+            .unwrap();
+        let updated = build_db(&[(1, "LOST"), (2, "NEW DATA")]);
+        updated
+            .insert_data(2, 2024, Some("2024-04-10"), Some("2024-10-20"))
+            .unwrap();
+
+        let diff = DatabaseDiff::compare(&current, &updated, &AnalyzerOptions::default(), 7)
+            .unwrap();
+
+        assert_eq!(diff.changed_dates.len(), 1);
+        assert_eq!(diff.changed_dates[0].station_id, 1);
+        assert_eq!(diff.changed_dates[0].shift_days(), None);
+    }
+
+    #[test]
+    fn test_exceeded_thresholds() {
+        let diff = DatabaseDiff {
+            added_stations: vec![(3, "ADDED".to_string())],
+            removed_stations: vec![(2, "REMOVED".to_string())],
+            changed_dates: Vec::new(),
+        };
+
+        let exceeded = diff.exceeded(&DiffThresholds::default());
+        assert_eq!(exceeded.len(), 1);
+        assert!(exceeded[0].contains("1 stations removed"));
+
+        let lenient = DiffThresholds {
+            max_removed_stations: 1,
+            ..DiffThresholds::default()
+        };
+        assert!(diff.exceeded(&lenient).is_empty());
+    }
+}
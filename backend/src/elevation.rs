@@ -0,0 +1,190 @@
+use std::path::Path;
+
+/// Digital elevation model loaded from an ESRI ASCII grid (`.asc`) in WGS84 degrees
+///
+/// The header gives the grid size, the lower-left corner (`xllcorner`/`yllcorner`,
+/// or `xllcenter`/`yllcenter`) and the cell size; values follow row by row from
+/// the northern edge. Any national or provincial DEM exported in this format
+/// can be used, e.g. a downsampled CDEM tile set.
+#[derive(Debug, Clone)]
+pub struct Dem {
+    ncols: usize,
+    nrows: usize,
+    /// Longitude of the western edge
+    west: f64,
+    /// Latitude of the southern edge
+    south: f64,
+    cellsize: f64,
+    /// Values row-major from the northernmost row; `None` for NODATA cells
+    values: Vec<Option<f64>>,
+}
+
+impl Dem {
+    /// Load a DEM from an ESRI ASCII grid file
+    ///
+    /// # Arguments
+    /// * `path` - Path to the `.asc` file
+    ///
+    /// # Returns
+    /// * `Result<Self, Box<dyn std::error::Error>>` - DEM or error
+    pub fn from_asc_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(path.as_ref())?;
+        Ok(Self::parse_asc(&text)?)
+    }
+
+    /// Parse the text of an ESRI ASCII grid
+    ///
+    /// # Arguments
+    /// * `text` - Header lines followed by `nrows` x `ncols` values
+    ///
+    /// # Returns
+    /// * `Result<Self, String>` - DEM or a description of the problem
+    pub fn parse_asc(text: &str) -> Result<Self, String> {
+        let mut tokens = text.split_whitespace().peekable();
+        let mut header = std::collections::HashMap::new();
+        // Header keys are words; the first numeric token starts the values
+        while let Some(key) = tokens.peek() {
+            if key.parse::<f64>().is_ok() {
+                break;
+            }
+            let key = tokens.next().unwrap_or_default().to_ascii_lowercase();
+            let value: f64 = tokens
+                .next()
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| format!("missing value for header '{}'", key))?;
+            header.insert(key, value);
+        }
+
+        let field = |key: &str| {
+            header
+                .get(key)
+                .copied()
+                .ok_or_else(|| format!("missing header '{}'", key))
+        };
+        let ncols = field("ncols")? as usize;
+        let nrows = field("nrows")? as usize;
+        let cellsize = field("cellsize")?;
+        if ncols == 0 || nrows == 0 || cellsize <= 0.0 {
+            return Err("grid must have rows, columns and a positive cellsize".to_string());
+        }
+        let (west, south) = match (header.get("xllcorner"), header.get("yllcorner")) {
+            (Some(&x), Some(&y)) => (x, y),
+            _ => (
+                field("xllcenter")? - cellsize / 2.0,
+                field("yllcenter")? - cellsize / 2.0,
+            ),
+        };
+        let nodata = header.get("nodata_value").copied();
+
+        let values = tokens
+            .map(|token| {
+                token
+                    .parse::<f64>()
+                    .map(|v| (Some(v) != nodata).then_some(v))
+                    .map_err(|_| format!("invalid value '{}'", token))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if values.len() != ncols * nrows {
+            return Err(format!(
+                "expected {} values, found {}",
+                ncols * nrows,
+                values.len()
+            ));
+        }
+
+        Ok(Dem {
+            ncols,
+            nrows,
+            west,
+            south,
+            cellsize,
+            values,
+        })
+    }
+
+    /// Elevation at a point, interpolated bilinearly between cell centres
+    ///
+    /// # Arguments
+    /// * `lat` - Latitude in degrees
+    /// * `lon` - Longitude in degrees
+    ///
+    /// # Returns
+    /// * `Option<f64>` - Elevation in metres, or `None` outside the grid or over NODATA cells
+    pub fn elevation_at(&self, lat: f64, lon: f64) -> Option<f64> {
+        let east = self.west + self.ncols as f64 * self.cellsize;
+        let north = self.south + self.nrows as f64 * self.cellsize;
+        if !(self.west..=east).contains(&lon) || !(self.south..=north).contains(&lat) {
+            return None;
+        }
+
+        // Fractional cell coordinates, measured between cell centres
+        let col = ((lon - self.west) / self.cellsize - 0.5).clamp(0.0, (self.ncols - 1) as f64);
+        let row = ((north - lat) / self.cellsize - 0.5).clamp(0.0, (self.nrows - 1) as f64);
+        let (col0, row0) = (col.floor() as usize, row.floor() as usize);
+        let (col1, row1) = ((col0 + 1).min(self.ncols - 1), (row0 + 1).min(self.nrows - 1));
+        let (dx, dy) = (col - col0 as f64, row - row0 as f64);
+
+        let value = |row: usize, col: usize| self.values[row * self.ncols + col];
+        match (value(row0, col0), value(row0, col1), value(row1, col0), value(row1, col1)) {
+            (Some(nw), Some(ne), Some(sw), Some(se)) => {
+                let top = nw + (ne - nw) * dx;
+                let bottom = sw + (se - sw) * dx;
+                Some(top + (bottom - top) * dy)
+            }
+            // Next to NODATA, use the closest cell only
+            _ => value(row.round() as usize, col.round() as usize),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GRID: &str = "ncols 3
+nrows 2
+xllcorner -80.0
+yllcorner 43.0
+cellsize 1.0
+NODATA_value -9999
+300 400 -9999
+100 200 250
+";
+
+    #[test]
+    fn test_elevation_is_interpolated_between_cell_centres() {
+        let dem = Dem::parse_asc(GRID).unwrap();
+
+        // Cell centres
+        assert_eq!(dem.elevation_at(44.5, -79.5), Some(300.0));
+        assert_eq!(dem.elevation_at(43.5, -78.5), Some(200.0));
+        // Halfway between the four western centres
+        assert_eq!(dem.elevation_at(44.0, -79.0), Some(250.0));
+        // Beyond the outer centres the edge value holds
+        assert_eq!(dem.elevation_at(43.1, -79.9), Some(100.0));
+    }
+
+    #[test]
+    fn test_nodata_and_outside_points() {
+        let dem = Dem::parse_asc(GRID).unwrap();
+
+        assert_eq!(dem.elevation_at(44.5, -77.5), None);
+        // Next to the NODATA cell, the closest valid cell is used
+        assert_eq!(dem.elevation_at(43.6, -77.6), Some(250.0));
+        assert_eq!(dem.elevation_at(42.9, -79.5), None);
+        assert_eq!(dem.elevation_at(44.5, -76.9), None);
+    }
+
+    #[test]
+    fn test_invalid_grids_are_rejected() {
+        assert!(Dem::parse_asc("ncols 2\nnrows 1\nxllcorner 0\nyllcorner 0\n1 2").is_err());
+        assert!(Dem::parse_asc(&GRID.replace("250", "")).is_err());
+        assert!(Dem::parse_asc(&GRID.replace("cellsize 1.0", "cellsize 0")).is_err());
+
+        let centred = GRID
+            .replace("xllcorner -80.0", "xllcenter -79.5")
+            .replace("yllcorner 43.0", "yllcenter 43.5");
+        let dem = Dem::parse_asc(&centred).unwrap();
+        assert_eq!(dem.elevation_at(44.5, -79.5), Some(300.0));
+    }
+}
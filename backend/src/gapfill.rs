@@ -0,0 +1,425 @@
+use crate::db::{DailyObservation, Database};
+use crate::nearest::NearestStationFinder;
+use chrono::{Duration, NaiveDate};
+use std::collections::BTreeMap;
+
+/// Settings for estimating missing daily temperatures from neighbouring stations
+#[derive(Debug, Clone)]
+pub struct GapFillOptions {
+    /// Number of nearest neighbours considered per station
+    pub neighbours: usize,
+    /// Neighbours further away than this are ignored
+    pub max_distance_km: f64,
+    /// Days both stations must have reported for a regression to be fitted
+    pub min_overlap_days: usize,
+    /// Regressions explaining less of the target's variance than this are discarded
+    pub min_r_squared: f64,
+}
+
+impl Default for GapFillOptions {
+    fn default() -> Self {
+        GapFillOptions {
+            neighbours: 5,
+            max_distance_km: 100.0,
+            min_overlap_days: 60,
+            min_r_squared: 0.8,
+        }
+    }
+}
+
+/// Outcome of gap filling one station
+#[derive(Debug, Clone, Default)]
+pub struct GapFillReport {
+    pub station_id: i64,
+    /// Neighbours with at least one usable regression
+    pub neighbours_used: usize,
+    /// Days within the station's record without a mean temperature
+    pub days_missing: usize,
+    /// Days that received an estimated mean temperature
+    pub days_filled: usize,
+}
+
+/// Linear fit `target = intercept + slope * neighbour` over overlapping days
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Regression {
+    intercept: f64,
+    slope: f64,
+    r_squared: f64,
+}
+
+impl Regression {
+    /// Ordinary least squares fit over (neighbour, target) pairs
+    fn fit(pairs: &[(f64, f64)]) -> Option<Self> {
+        if pairs.len() < 2 {
+            return None;
+        }
+        let n = pairs.len() as f64;
+        let mean_x = pairs.iter().map(|(x, _)| x).sum::<f64>() / n;
+        let mean_y = pairs.iter().map(|(_, y)| y).sum::<f64>() / n;
+
+        let mut sxx = 0.0;
+        let mut sxy = 0.0;
+        let mut syy = 0.0;
+        for (x, y) in pairs {
+            sxx += (x - mean_x).powi(2);
+            sxy += (x - mean_x) * (y - mean_y);
+            syy += (y - mean_y).powi(2);
+        }
+        if sxx == 0.0 || syy == 0.0 {
+            return None;
+        }
+
+        let slope = sxy / sxx;
+        Some(Regression {
+            intercept: mean_y - slope * mean_x,
+            slope,
+            r_squared: (sxy * sxy) / (sxx * syy),
+        })
+    }
+
+    fn predict(&self, x: f64) -> f64 {
+        self.intercept + self.slope * x
+    }
+}
+
+/// Daily temperature fields that gap filling estimates
+#[derive(Debug, Clone, Copy)]
+enum TempField {
+    Mean,
+    Min,
+    Max,
+}
+
+impl TempField {
+    const ALL: [TempField; 3] = [TempField::Mean, TempField::Min, TempField::Max];
+
+    fn get(self, obs: &DailyObservation) -> Option<f64> {
+        match self {
+            TempField::Mean => obs.mean_temp,
+            TempField::Min => obs.min_temp,
+            TempField::Max => obs.max_temp,
+        }
+    }
+
+    /// The value only if the station reported it, not if it was imputed
+    fn reported(self, obs: &DailyObservation) -> Option<f64> {
+        let imputed = match self {
+            TempField::Mean => obs.mean_temp_imputed,
+            TempField::Min => obs.min_temp_imputed,
+            TempField::Max => obs.max_temp_imputed,
+        };
+        self.get(obs).filter(|_| !imputed)
+    }
+
+    /// Store an estimated value and mark it as imputed
+    fn set_imputed(self, obs: &mut DailyObservation, value: f64) {
+        // Rounded to the 0.1°C precision ECCC reports
+        let value = Some((value * 10.0).round() / 10.0);
+        match self {
+            TempField::Mean => (obs.mean_temp, obs.mean_temp_imputed) = (value, true),
+            TempField::Min => (obs.min_temp, obs.min_temp_imputed) = (value, true),
+            TempField::Max => (obs.max_temp, obs.max_temp_imputed) = (value, true),
+        }
+    }
+}
+
+/// Estimates missing daily temperatures of a station from its neighbours
+pub struct GapFiller<'a> {
+    db: &'a Database,
+    finder: NearestStationFinder,
+    options: GapFillOptions,
+}
+
+impl<'a> GapFiller<'a> {
+    /// Create a gap filler over all stations currently in the database
+    pub fn new(
+        db: &'a Database,
+        options: GapFillOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let finder = NearestStationFinder::new(db)?;
+        Ok(GapFiller {
+            db,
+            finder,
+            options,
+        })
+    }
+
+    /// Fill days without a mean temperature for one station between two dates
+    ///
+    /// For every neighbour within `max_distance_km`, each temperature field of
+    /// the station is regressed on the neighbour's over the days both reported.
+    /// On each day missing its mean temperature, the mean, min and max values
+    /// that are missing are estimated from every neighbour that reported that
+    /// day, weighting each prediction by its regression's R². Only days between
+    /// the station's first and last stored observation are filled. Each estimated
+    /// value is marked in its own column (`mean_temp_imputed` and so on), so the
+    /// values a filled row already had still count as reported. Estimated values
+    /// are neither filled again nor used to fit later regressions. Storing the
+    /// month again (a download while it is still open, or `--import-csv`)
+    /// replaces them, but `--update-db` does not download months recorded as
+    /// complete, so imputed rows in closed months stay.
+    ///
+    /// # Arguments
+    /// * `station_id` - Station to fill
+    /// * `start` - First date considered, inclusive
+    /// * `end` - Last date considered, inclusive
+    ///
+    /// # Returns
+    /// * `Result<GapFillReport, Box<dyn std::error::Error>>` - Counts of missing and filled days
+    pub fn fill_station(
+        &self,
+        station_id: i64,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Result<GapFillReport, Box<dyn std::error::Error>> {
+        let mut report = GapFillReport {
+            station_id,
+            ..GapFillReport::default()
+        };
+
+        let start_str = start.format("%Y-%m-%d").to_string();
+        let end_str = end.format("%Y-%m-%d").to_string();
+        let stored = self.observations_by_date(station_id, &start_str, &end_str)?;
+        let (Some(&first), Some(&last)) = (stored.keys().next(), stored.keys().next_back()) else {
+            return Ok(report);
+        };
+
+        // Days inside the station's record without a mean temperature, reported or imputed
+        let mut missing: Vec<NaiveDate> = Vec::new();
+        let mut day = first;
+        while day <= last {
+            if stored.get(&day).is_none_or(|obs| obs.mean_temp.is_none()) {
+                missing.push(day);
+            }
+            day += Duration::days(1);
+        }
+        report.days_missing = missing.len();
+        if missing.is_empty() {
+            return Ok(report);
+        }
+
+        let Some((_, _, lon, lat)) = self.db.get_station_by_id(station_id)? else {
+            return Ok(report);
+        };
+        let neighbours = self
+            .finder
+            .find_k_nearest(lat, lon, self.options.neighbours + 1)
+            .into_iter()
+            .filter(|n| n.id != station_id && n.distance_km <= self.options.max_distance_km);
+
+        // Per neighbour: its stored days and one regression per temperature field,
+        // fitted on reported values only
+        let mut fitted = Vec::new();
+        for neighbour in neighbours {
+            let values = self.observations_by_date(neighbour.id, &start_str, &end_str)?;
+            let regressions: Vec<Option<Regression>> = TempField::ALL
+                .iter()
+                .map(|&field| {
+                    let pairs: Vec<(f64, f64)> = values
+                        .iter()
+                        .filter_map(|(date, obs)| {
+                            Some((field.reported(obs)?, field.reported(stored.get(date)?)?))
+                        })
+                        .collect();
+                    if pairs.len() < self.options.min_overlap_days {
+                        return None;
+                    }
+                    Regression::fit(&pairs)
+                        .filter(|fit| fit.r_squared >= self.options.min_r_squared)
+                })
+                .collect();
+            if regressions.iter().any(Option::is_some) {
+                fitted.push((values, regressions));
+            }
+        }
+        report.neighbours_used = fitted.len();
+        if fitted.is_empty() {
+            return Ok(report);
+        }
+
+        let mut filled = Vec::new();
+        for date in missing {
+            let mut obs = stored.get(&date).cloned().unwrap_or_else(|| DailyObservation {
+                station_id,
+                date: date.format("%Y-%m-%d").to_string(),
+                ..DailyObservation::default()
+            });
+
+            for (i, field) in TempField::ALL.iter().enumerate() {
+                if field.get(&obs).is_some() {
+                    continue;
+                }
+                let mut weighted = 0.0;
+                let mut weights = 0.0;
+                for (values, regressions) in &fitted {
+                    if let Some(fit) = regressions[i]
+                        && let Some(x) = values.get(&date).and_then(|n| field.reported(n))
+                    {
+                        weighted += fit.predict(x) * fit.r_squared;
+                        weights += fit.r_squared;
+                    }
+                }
+                if weights > 0.0 {
+                    field.set_imputed(&mut obs, weighted / weights);
+                }
+            }
+
+            // Only days that gained a mean temperature help transition detection
+            if obs.mean_temp.is_some() {
+                filled.push(obs);
+            }
+        }
+
+        report.days_filled = filled.len();
+        self.db.insert_daily_observations(&filled)?;
+        Ok(report)
+    }
+
+    /// Stored observations of a station, keyed by date
+    fn observations_by_date(
+        &self,
+        station_id: i64,
+        start: &str,
+        end: &str,
+    ) -> Result<BTreeMap<NaiveDate, DailyObservation>, Box<dyn std::error::Error>> {
+        Ok(self
+            .db
+            .get_daily_observations(station_id, start, end)?
+            .into_iter()
+            .filter_map(|obs| {
+                let date = NaiveDate::parse_from_str(&obs.date, "%Y-%m-%d").ok()?;
+                Some((date, obs))
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_regression_recovers_linear_relation() {
+        let pairs: Vec<(f64, f64)> = (0..30)
+            .map(|i| {
+                let x = i as f64 - 10.0;
+                (x, 1.5 + 0.9 * x)
+            })
+            .collect();
+        let fit = Regression::fit(&pairs).unwrap();
+        assert!((fit.slope - 0.9).abs() < 1e-9);
+        assert!((fit.intercept - 1.5).abs() < 1e-9);
+        assert!((fit.r_squared - 1.0).abs() < 1e-9);
+        assert!((fit.predict(10.0) - 10.5).abs() < 1e-9);
+
+        assert!(Regression::fit(&[(1.0, 2.0)]).is_none());
+        assert!(Regression::fit(&[(1.0, 2.0), (1.0, 3.0)]).is_none());
+    }
+
+    fn seasonal_temp(day: i64) -> f64 {
+        // Roughly a Canadian annual cycle, with a little day-to-day wobble
+        let phase = (day as f64 - 200.0) / 365.0 * std::f64::consts::TAU;
+        8.0 + 14.0 * phase.cos() + ((day * 7919) % 11) as f64 / 5.0
+    }
+
+    fn daily_rows(
+        station_id: i64,
+        start: NaiveDate,
+        days: i64,
+        offset: f64,
+    ) -> Vec<DailyObservation> {
+        (0..days)
+            .map(|i| {
+                let mean = seasonal_temp(i) + offset;
+                DailyObservation {
+                    station_id,
+                    date: (start + Duration::days(i)).format("%Y-%m-%d").to_string(),
+                    mean_temp: Some(mean),
+                    min_temp: Some(mean - 5.0),
+                    max_temp: Some(mean + 5.0),
+                    ..DailyObservation::default()
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fill_station_from_neighbour() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+        db.insert_station(1, &"Target".to_string(), -79.40, 43.70, None, None)
+            .unwrap();
+        db.insert_station(2, &"Neighbour".to_string(), -79.50, 43.75, None, None)
+            .unwrap();
+        db.insert_station(3, &"Far Away".to_string(), -123.1, 49.2, None, None)
+            .unwrap();
+
+        let start = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+        // The target runs 2°C colder than its neighbour and misses ten days in April,
+        // plus the mean temperature on one day in May
+        let mut target: Vec<DailyObservation> = daily_rows(1, start, 365, -2.0)
+            .into_iter()
+            .filter(|obs| !("2023-04-10".."2023-04-20").contains(&obs.date.as_str()))
+            .collect();
+        let may = target.iter_mut().find(|obs| obs.date == "2023-05-05").unwrap();
+        may.mean_temp = None;
+        may.mean_temp_flag = Some("M".to_string());
+        db.insert_daily_observations(&target).unwrap();
+        db.insert_daily_observations(&daily_rows(2, start, 365, 0.0))
+            .unwrap();
+        db.insert_daily_observations(&daily_rows(3, start, 365, 40.0))
+            .unwrap();
+
+        let filler = GapFiller::new(&db, GapFillOptions::default()).unwrap();
+        let end = NaiveDate::from_ymd_opt(2023, 12, 31).unwrap();
+        let report = filler.fill_station(1, start, end).unwrap();
+
+        assert_eq!(report.neighbours_used, 1);
+        assert_eq!(report.days_missing, 11);
+        assert_eq!(report.days_filled, 11);
+
+        let april = db.get_daily_observations(1, "2023-04-15", "2023-04-15").unwrap();
+        let expected = seasonal_temp(104) - 2.0;
+        assert!(april[0].mean_temp_imputed);
+        assert!(april[0].min_temp_imputed && april[0].max_temp_imputed);
+        assert!((april[0].mean_temp.unwrap() - expected).abs() <= 0.05 + 1e-9);
+        assert!((april[0].max_temp.unwrap() - (expected + 5.0)).abs() <= 0.05 + 1e-9);
+
+        // Only the missing field is estimated; reported values and flags are kept
+        // and stay marked as reported
+        let may = db.get_daily_observations(1, "2023-05-05", "2023-05-05").unwrap();
+        assert!(may[0].mean_temp_imputed);
+        assert!(may[0].mean_temp.is_some());
+        assert_eq!(may[0].mean_temp_flag.as_deref(), Some("M"));
+        assert_eq!(may[0].min_temp, Some(seasonal_temp(124) - 7.0));
+        assert!(!may[0].min_temp_imputed && !may[0].max_temp_imputed);
+
+        // A second pass has nothing left to fill
+        let again = filler.fill_station(1, start, end).unwrap();
+        assert_eq!(again.days_missing, 0);
+        assert_eq!(again.days_filled, 0);
+    }
+
+    #[test]
+    fn test_fill_station_without_neighbours_leaves_gaps() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+        db.insert_station(1, &"Lonely".to_string(), -79.40, 43.70, None, None)
+            .unwrap();
+
+        let start = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+        let rows: Vec<DailyObservation> = daily_rows(1, start, 90, 0.0)
+            .into_iter()
+            .filter(|obs| obs.date != "2023-02-01")
+            .collect();
+        db.insert_daily_observations(&rows).unwrap();
+
+        let filler = GapFiller::new(&db, GapFillOptions::default()).unwrap();
+        let report = filler
+            .fill_station(1, start, NaiveDate::from_ymd_opt(2023, 12, 31).unwrap())
+            .unwrap();
+        assert_eq!(report.days_missing, 1);
+        assert_eq!(report.days_filled, 0);
+        assert!(db.get_daily_observations(1, "2023-02-01", "2023-02-01").unwrap().is_empty());
+    }
+}
@@ -0,0 +1,304 @@
+use crate::db::{DailyObservation, StationMetadata};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+
+/// Name stored as the `source` of stations imported from GHCN-Daily
+pub const SOURCE_NAME: &str = "ghcn";
+
+/// Station catalogue of the GHCN-Daily distribution
+pub const STATIONS_FILE: &str = "ghcnd-stations.txt";
+
+/// Value GHCN-Daily stores for a day without an observation
+const MISSING_VALUE: i32 = -9999;
+
+/// Days per line of a `.dly` file; shorter months leave the last ones missing
+const DAYS_PER_LINE: usize = 31;
+
+/// A station from `ghcnd-stations.txt`
+#[derive(Debug, Clone, PartialEq)]
+pub struct GhcnStation {
+    /// GHCN-Daily ID, e.g. "USW00014822"
+    pub ghcn_id: String,
+    pub name: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Elevation above sea level in metres
+    pub elevation_m: Option<f64>,
+    /// US state or Canadian province code, e.g. "MI"
+    pub state: Option<String>,
+    /// World Meteorological Organization station number
+    pub wmo_id: Option<i64>,
+}
+
+impl GhcnStation {
+    /// Station ID used in the database (see `station_id`)
+    pub fn id(&self) -> i64 {
+        station_id(&self.ghcn_id)
+    }
+
+    /// Metadata stored with the station, tagged with `SOURCE_NAME`
+    pub fn metadata(&self) -> StationMetadata {
+        StationMetadata {
+            province: self.state.clone(),
+            wmo_id: self.wmo_id,
+            elevation_m: self.elevation_m,
+            source: Some(SOURCE_NAME.to_string()),
+            source_id: Some(self.ghcn_id.clone()),
+            ..StationMetadata::default()
+        }
+    }
+}
+
+/// Temperatures of one `.dly` file
+#[derive(Debug, Clone, PartialEq)]
+pub struct DlyFile {
+    /// GHCN-Daily ID of the station the file belongs to
+    pub ghcn_id: String,
+    /// One observation per day with at least one valid temperature, in date order
+    pub observations: Vec<DailyObservation>,
+}
+
+/// Database station ID for a GHCN-Daily station
+///
+/// GHCN-Daily IDs are alphanumeric, while ECCC station IDs are positive
+/// integers. The GHCN ID is hashed (64-bit FNV-1a) into a negative number, so
+/// the two never collide and re-importing a station replaces it in place.
+///
+/// # Arguments
+/// * `ghcn_id` - GHCN-Daily ID, e.g. "USW00014822"
+///
+/// # Returns
+/// * `i64` - Negative station ID, the same for every import
+pub fn station_id(ghcn_id: &str) -> i64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in ghcn_id.bytes() {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    -((hash >> 1) as i64) - 1
+}
+
+/// Parse the fixed-width `ghcnd-stations.txt` catalogue
+///
+/// Lines without an ID, name or valid coordinates are left out.
+///
+/// # Arguments
+/// * `text` - Contents of the catalogue
+///
+/// # Returns
+/// * `Vec<GhcnStation>` - Stations in file order
+pub fn parse_stations(text: &str) -> Vec<GhcnStation> {
+    text.lines()
+        .filter_map(|line| {
+            let latitude: f64 = field(line, 13, 20)?.parse().ok()?;
+            let longitude: f64 = field(line, 22, 30)?.parse().ok()?;
+            if !(-90.0..=90.0).contains(&latitude) || !(-180.0..=180.0).contains(&longitude) {
+                return None;
+            }
+            Some(GhcnStation {
+                ghcn_id: field(line, 1, 11)?.to_string(),
+                name: field(line, 42, 71)?.to_string(),
+                latitude,
+                longitude,
+                // -999.9 marks an unknown elevation
+                elevation_m: field(line, 32, 37)
+                    .and_then(|value| value.parse().ok())
+                    .filter(|elevation: &f64| *elevation > -999.0),
+                state: field(line, 39, 40).map(str::to_string),
+                wmo_id: field(line, 81, 85).and_then(|value| value.parse().ok()),
+            })
+        })
+        .collect()
+}
+
+/// Parse the temperatures of a fixed-width `.dly` file
+///
+/// TMAX, TMIN and TAVG (tenths of a degree Celsius) become the maximum,
+/// minimum and mean temperature of the day. As in the ECCC data, the mean is
+/// the midpoint of the maximum and minimum; TAVG is only used on days missing
+/// one of them. Missing values and values with a quality flag (failed a GHCN
+/// quality check) are left out; other elements are ignored.
+///
+/// # Arguments
+/// * `text` - Contents of the file
+///
+/// # Returns
+/// * `Result<DlyFile, String>` - Station and observations, or an error if the file
+///   has no station ID or mixes several stations
+pub fn parse_dly(text: &str) -> Result<DlyFile, String> {
+    let mut ghcn_id: Option<&str> = None;
+    // (max, min, avg) per day
+    let mut days: BTreeMap<NaiveDate, [Option<f64>; 3]> = BTreeMap::new();
+
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let id = field(line, 1, 11).ok_or_else(|| format!("line {}: no station ID", number + 1))?;
+        match ghcn_id {
+            None => ghcn_id = Some(id),
+            Some(first) if first != id => {
+                return Err(format!("line {}: station {} in a file of {}", number + 1, id, first));
+            }
+            Some(_) => {}
+        }
+
+        let slot = match field(line, 18, 21) {
+            Some("TMAX") => 0,
+            Some("TMIN") => 1,
+            Some("TAVG") => 2,
+            _ => continue,
+        };
+        let year: i32 = field(line, 12, 15)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| format!("line {}: invalid year", number + 1))?;
+        let month: u32 = field(line, 16, 17)
+            .and_then(|value| value.parse().ok())
+            .ok_or_else(|| format!("line {}: invalid month", number + 1))?;
+
+        for day in 1..=DAYS_PER_LINE {
+            let column = 22 + 8 * (day - 1);
+            let Some(value) = field(line, column, column + 4)
+                .and_then(|value| value.parse::<i32>().ok())
+                .filter(|value| *value != MISSING_VALUE)
+            else {
+                continue;
+            };
+            if field(line, column + 6, column + 6).is_some() {
+                continue;
+            }
+            // Days past the end of the month are always missing, but guard anyway
+            let Some(date) = NaiveDate::from_ymd_opt(year, month, day as u32) else {
+                continue;
+            };
+            days.entry(date).or_default()[slot] = Some(value as f64 / 10.0);
+        }
+    }
+
+    let ghcn_id = ghcn_id.ok_or("empty .dly file")?.to_string();
+    let station_id = station_id(&ghcn_id);
+    let observations = days
+        .into_iter()
+        .map(|(date, [max_temp, min_temp, avg_temp])| {
+            let mean_temp = match (max_temp, min_temp) {
+                (Some(max), Some(min)) => Some((max + min) / 2.0),
+                _ => avg_temp,
+            };
+            DailyObservation {
+                station_id,
+                date: date.format("%Y-%m-%d").to_string(),
+                mean_temp,
+                min_temp,
+                max_temp,
+                ..DailyObservation::default()
+            }
+        })
+        .collect();
+
+    Ok(DlyFile {
+        ghcn_id,
+        observations,
+    })
+}
+
+/// Trimmed contents of the 1-based, inclusive columns `first..=last` of a line;
+/// `None` when they are blank or past the end of the line
+fn field(line: &str, first: usize, last: usize) -> Option<&str> {
+    let end = last.min(line.len());
+    let value = line.get(first - 1..end)?.trim();
+    (!value.is_empty()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A `.dly` line for one element, with `values` from day 1 on and the rest missing
+    fn dly_line(id: &str, year: i32, month: u32, element: &str, values: &[(i32, char)]) -> String {
+        let mut line = format!("{:<11}{}{:02}{}", id, year, month, element);
+        for day in 0..DAYS_PER_LINE {
+            let (value, qflag) = values.get(day).copied().unwrap_or((MISSING_VALUE, ' '));
+            line.push_str(&format!("{:>5} {} ", value, qflag));
+        }
+        line
+    }
+
+    #[test]
+    fn test_parse_stations() {
+        let text = "\
+USW00014822  42.2314  -83.3308  192.3 MI DETROIT METRO AP               GSN     72537
+CA006158355  43.6667  -79.4000  112.5 ON TORONTO CITY
+USC00200000  99.0000  -83.0000 -999.9 MI OUT OF RANGE
+";
+        let stations = parse_stations(text);
+
+        assert_eq!(stations.len(), 2);
+        assert_eq!(
+            stations[0],
+            GhcnStation {
+                ghcn_id: "USW00014822".to_string(),
+                name: "DETROIT METRO AP".to_string(),
+                latitude: 42.2314,
+                longitude: -83.3308,
+                elevation_m: Some(192.3),
+                state: Some("MI".to_string()),
+                wmo_id: Some(72537),
+            }
+        );
+        assert_eq!(stations[1].wmo_id, None);
+        assert_eq!(stations[1].metadata().source_name(), "ghcn");
+    }
+
+    #[test]
+    fn test_parse_dly_temperatures() {
+        let id = "USW00014822";
+        let text = [
+            dly_line(id, 2024, 2, "TMAX", &[(50, ' '), (80, ' '), (999, 'X')]),
+            dly_line(id, 2024, 2, "TMIN", &[(-10, ' '), (MISSING_VALUE, ' '), (-20, ' ')]),
+            dly_line(id, 2024, 2, "TAVG", &[(25, ' '), (41, ' ')]),
+            dly_line(id, 2024, 2, "PRCP", &[(120, ' ')]),
+        ]
+        .join("\n");
+
+        let file = parse_dly(&text).unwrap();
+
+        assert_eq!(file.ghcn_id, id);
+        let days: Vec<_> = file
+            .observations
+            .iter()
+            .map(|obs| (obs.date.as_str(), obs.max_temp, obs.min_temp, obs.mean_temp))
+            .collect();
+        assert_eq!(
+            days,
+            vec![
+                // Mean from TMAX and TMIN, not TAVG
+                ("2024-02-01", Some(5.0), Some(-1.0), Some(2.0)),
+                // TMIN missing: mean from TAVG
+                ("2024-02-02", Some(8.0), None, Some(4.1)),
+                // TMAX failed a quality check
+                ("2024-02-03", None, Some(-2.0), None),
+            ]
+        );
+        assert!(file.observations.iter().all(|obs| obs.station_id == station_id(id)));
+    }
+
+    #[test]
+    fn test_parse_dly_rejects_mixed_stations() {
+        let text = [
+            dly_line("USW00014822", 2024, 1, "TMAX", &[(10, ' ')]),
+            dly_line("USW00094847", 2024, 1, "TMAX", &[(10, ' ')]),
+        ]
+        .join("\n");
+
+        assert!(parse_dly(&text).is_err());
+        assert!(parse_dly("").is_err());
+    }
+
+    #[test]
+    fn test_station_id_is_negative_and_stable() {
+        let detroit = station_id("USW00014822");
+        assert!(detroit < 0);
+        assert_eq!(detroit, station_id("USW00014822"));
+        assert_ne!(detroit, station_id("USW00094847"));
+    }
+}
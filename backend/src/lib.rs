@@ -1,5 +1,14 @@
 pub mod aggregator;
 pub mod analyzer;
 pub mod api;
+pub mod cache;
+pub mod config;
 pub mod db;
+pub mod diff;
+pub mod elevation;
+pub mod gapfill;
+pub mod ghcn;
+pub mod limiter;
 pub mod nearest;
+pub mod scheduler;
+pub mod source;
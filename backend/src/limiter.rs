@@ -0,0 +1,140 @@
+use std::sync::Mutex;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::time::{Duration, Instant};
+
+/// Limits on outbound requests to the ECCC servers
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestLimits {
+    /// Requests allowed in flight at the same time
+    pub max_concurrent: usize,
+    /// Requests started per second; `None` for no rate limit
+    pub requests_per_second: Option<f64>,
+}
+
+impl Default for RequestLimits {
+    fn default() -> Self {
+        RequestLimits {
+            max_concurrent: 8,
+            requests_per_second: Some(5.0),
+        }
+    }
+}
+
+/// Shared gate for outbound requests: a cap on concurrent requests plus an even
+/// spacing between request starts
+///
+/// Every fetch takes a permit before it sends and holds it until the response body
+/// has been read, so the limits hold no matter how many stations or months are
+/// processed concurrently.
+#[derive(Debug)]
+pub struct RequestLimiter {
+    permits: Semaphore,
+    /// Minimum time between two request starts
+    interval: Option<Duration>,
+    /// Earliest instant the next request may start
+    next_start: Mutex<Instant>,
+}
+
+impl RequestLimiter {
+    /// Create a limiter
+    ///
+    /// # Arguments
+    /// * `limits` - Concurrency and rate limits; a concurrency of 0 is treated as 1,
+    ///   and a rate that is not positive disables the rate limit
+    pub fn new(limits: &RequestLimits) -> Self {
+        let interval = limits
+            .requests_per_second
+            .filter(|rate| rate.is_finite() && *rate > 0.0)
+            .map(|rate| Duration::from_secs_f64(1.0 / rate));
+        RequestLimiter {
+            permits: Semaphore::new(limits.max_concurrent.max(1)),
+            interval,
+            next_start: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for a free slot and the next start time allowed by the rate limit
+    ///
+    /// # Returns
+    /// * `SemaphorePermit` - Permit to hold for the duration of the request
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        let permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("request limiter semaphore is never closed");
+        if let Some(interval) = self.interval {
+            let start = {
+                let mut next_start = self.next_start.lock().unwrap();
+                let start = (*next_start).max(Instant::now());
+                *next_start = start + interval;
+                start
+            };
+            tokio::time::sleep_until(start).await;
+        }
+        permit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn test_concurrent_requests_are_capped() {
+        let limiter = Arc::new(RequestLimiter::new(&RequestLimits {
+            max_concurrent: 3,
+            requests_per_second: None,
+        }));
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+
+        let tasks: Vec<_> = (0..12)
+            .map(|_| {
+                let (limiter, in_flight, peak) =
+                    (Arc::clone(&limiter), Arc::clone(&in_flight), Arc::clone(&peak));
+                tokio::spawn(async move {
+                    let _permit = limiter.acquire().await;
+                    let now = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        assert_eq!(peak.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_request_starts_are_spaced_by_the_rate() {
+        let limiter = RequestLimiter::new(&RequestLimits {
+            max_concurrent: 10,
+            requests_per_second: Some(50.0),
+        });
+
+        let started = Instant::now();
+        for _ in 0..6 {
+            drop(limiter.acquire().await);
+        }
+
+        // The first request starts immediately, the other five 20 ms apart
+        assert!(started.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn test_invalid_limits_fall_back_to_safe_values() {
+        let limiter = RequestLimiter::new(&RequestLimits {
+            max_concurrent: 0,
+            requests_per_second: Some(0.0),
+        });
+
+        assert_eq!(limiter.permits.available_permits(), 1);
+        assert_eq!(limiter.interval, None);
+    }
+}
@@ -1,11 +1,22 @@
+use chrono::{Datelike, NaiveDate, Utc};
 use clap::Parser;
-use indicatif::{ProgressBar, ProgressStyle};
 use std::sync::Arc;
 
-use backend::aggregator::Aggregator;
-use backend::analyzer::Analyzer;
+use backend::aggregator::{
+    Aggregator, AggregatorOptions, BoundingBox, DailySource, RunTrigger, StationFilter,
+    UpdateReport, DEFAULT_CLIMATE_BASE_URL, DEFAULT_STATIONS_BASE_URL, DEFAULT_USER_AGENT,
+};
+use backend::analyzer::{day_of_year_to_date, Analyzer, AnalyzerOptions, TransitionCriterion};
 use backend::api::{create_router, AppState};
-use backend::db::Database;
+use backend::cache::{CacheMode, ResponseCache};
+use backend::config::IngestConfig;
+use backend::db::{Database, IngestRun};
+use backend::diff::{DatabaseDiff, DiffThresholds};
+use backend::elevation::Dem;
+use backend::gapfill::{GapFillOptions, GapFiller};
+use backend::limiter::RequestLimits;
+use backend::scheduler::{spawn_scheduled_refresh, RefreshSchedule, Refresher};
+use backend::source::SourceKind;
 
 /// Tire Swap Weather Station Finder
 #[derive(Parser, Debug)]
@@ -16,6 +27,29 @@ struct Args {
     #[arg(long)]
     update_db: bool,
 
+    /// With --update-db, continue the most recent interrupted run, skipping the stations
+    /// it already finished
+    #[arg(long, requires = "update_db", conflicts_with = "dry_run")]
+    resume: bool,
+
+    /// With --update-db, run the update against a temporary copy of the database and
+    /// print how it would change the data instead of applying it
+    #[arg(long, requires = "update_db")]
+    dry_run: bool,
+
+    /// With --dry-run, per-station recommendations that move by more than this many
+    /// days count as changed
+    #[arg(long, value_name = "DAYS", default_value_t = 7, requires = "dry_run")]
+    max_date_shift: u32,
+
+    /// With --dry-run, exit with status 3 when more recommendations than this changed
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "dry_run")]
+    max_changed_dates: usize,
+
+    /// With --dry-run, exit with status 3 when more stations than this were removed
+    #[arg(long, value_name = "N", default_value_t = 0, requires = "dry_run")]
+    max_removed_stations: usize,
+
     /// Latitude of the location to analyze
     #[arg(long)]
     latitude: Option<f64>,
@@ -39,6 +73,224 @@ struct Args {
     /// Database file path
     #[arg(long, default_value = "tireswap.db")]
     db_path: String,
+
+    /// Import ECCC daily bulk CSV files from a directory instead of downloading them;
+    /// stations missing from the database are created from the files' columns
+    #[arg(long, value_name = "DIR")]
+    import_csv: Option<std::path::PathBuf>,
+
+    /// Import NOAA GHCN-Daily stations from a directory holding ghcnd-stations.txt and
+    /// .dly files, e.g. for US stations near the border
+    #[arg(long, value_name = "DIR")]
+    import_ghcn: Option<std::path::PathBuf>,
+
+    /// Base URL of the ECCC station catalogue API [env: TIRESWAP_STATIONS_BASE_URL]
+    #[arg(long)]
+    stations_base_url: Option<String>,
+
+    /// Base URL of the ECCC bulk climate data site [env: TIRESWAP_CLIMATE_BASE_URL]
+    #[arg(long)]
+    climate_base_url: Option<String>,
+
+    /// Weather data provider to ingest stations and daily observations from: `eccc`
+    #[arg(long, value_name = "NAME", default_value = "eccc")]
+    source: SourceKind,
+
+    /// Where to download daily observations from: `csv` (bulk CSV, one request per
+    /// station-month) or `ogc` (the paged `climate-daily` OGC API collection)
+    #[arg(long, value_name = "SOURCE", default_value = "csv")]
+    daily_source: DailySource,
+
+    /// Maximum number of requests to ECCC in flight at the same time
+    #[arg(long, value_name = "N", default_value_t = 8)]
+    max_concurrent_requests: usize,
+
+    /// Maximum number of requests to ECCC started per second (0 for no limit)
+    #[arg(long, value_name = "RATE", default_value_t = 5.0)]
+    requests_per_second: f64,
+
+    /// Only fetch and update these station IDs (comma-separated)
+    #[arg(long = "station", value_name = "ID,...", value_delimiter = ',')]
+    stations: Vec<i64>,
+
+    /// Only fetch and update stations in these provinces or territories, e.g. ON,QC
+    #[arg(long = "province", value_name = "CODE,...", value_delimiter = ',')]
+    provinces: Vec<String>,
+
+    /// Only fetch and update stations inside minLon,minLat,maxLon,maxLat (degrees)
+    #[arg(long, value_name = "MINLON,MINLAT,MAXLON,MAXLAT", allow_hyphen_values = true)]
+    bbox: Option<BoundingBox>,
+
+    /// Write every response from ECCC to this content-addressed cache directory
+    #[arg(long, value_name = "DIR", conflicts_with = "replay")]
+    cache_dir: Option<std::path::PathBuf>,
+
+    /// With --update-db, serve every request from a cache written with --cache-dir
+    /// instead of contacting ECCC, as of the time the cache was recorded
+    #[arg(long, value_name = "DIR", requires = "update_db")]
+    replay: Option<std::path::PathBuf>,
+
+    /// JSON file with ingest settings (history window and station eligibility)
+    #[arg(long, value_name = "FILE")]
+    ingest_config: Option<std::path::PathBuf>,
+
+    /// Number of past years of daily data to ingest [default: 5]
+    #[arg(long)]
+    history_years: Option<i32>,
+
+    /// Only keep stations that reported within this many days [default: 7]
+    #[arg(long, conflicts_with = "include_inactive")]
+    active_within_days: Option<i64>,
+
+    /// Keep stations regardless of when they last reported
+    #[arg(long)]
+    include_inactive: bool,
+
+    /// Minimum span of a station's daily record in days [default: 1825]
+    #[arg(long)]
+    min_record_days: Option<i64>,
+
+    /// With --update-db, also download hourly temperature and dew point (timeframe=1)
+    #[arg(long)]
+    hourly: bool,
+
+    /// With --update-db, also download the stations' climate normals (1991-2020 monthly
+    /// mean temperatures)
+    #[arg(long)]
+    normals: bool,
+
+    /// Define transitions by morning-commute frost instead of daily means: a day is
+    /// cold when an hourly temperature between 5 and 9am is below this value (°C)
+    #[arg(long, value_name = "CELSIUS", allow_hyphen_values = true)]
+    morning_threshold: Option<f64>,
+
+    /// Derive dates from the 7°C crossings of the stations' climate normals instead of
+    /// their recent daily data
+    #[arg(long, conflicts_with = "morning_threshold")]
+    use_normals: bool,
+
+    /// Estimate missing daily temperatures from neighbouring stations, then recompute
+    /// switch dates (runs after --update-db / --import-csv when combined with them)
+    #[arg(long)]
+    gap_fill: bool,
+
+    /// Leave out station-years whose season has a lower percentage of valid days
+    #[arg(long, value_name = "PCT", default_value_t = 80.0)]
+    min_valid_pct: f64,
+
+    /// With --serve, refresh the data every day at this local time (HH:MM), using the
+    /// same settings as --update-db
+    #[arg(long, value_name = "HH:MM")]
+    refresh_at: Option<String>,
+
+    /// Time zone of --refresh-at
+    #[arg(long, value_name = "TZ", default_value = "America/Toronto")]
+    refresh_tz: String,
+
+    /// Elevation of the location in metres; stations at a similar height are preferred
+    /// and station dates are corrected for the difference
+    #[arg(long, value_name = "METRES", allow_hyphen_values = true)]
+    elevation: Option<f64>,
+
+    /// ESRI ASCII grid (.asc) used to look up the elevation of locations given without one
+    #[arg(long, value_name = "FILE")]
+    dem: Option<std::path::PathBuf>,
+
+    /// List the most recent ingest runs (default: 10) and their statistics
+    #[arg(long, value_name = "N", num_args = 0..=1, default_missing_value = "10")]
+    list_runs: Option<usize>,
+}
+
+/// Build the ingest configuration from the optional config file, then CLI overrides
+fn ingest_config(args: &Args) -> Result<IngestConfig, Box<dyn std::error::Error>> {
+    let mut config = match &args.ingest_config {
+        Some(path) => IngestConfig::from_file(path)?,
+        None => IngestConfig::default(),
+    };
+    if let Some(years) = args.history_years {
+        if years < 1 {
+            return Err("--history-years must be at least 1".into());
+        }
+        config.history_years = years;
+    }
+    if let Some(days) = args.active_within_days {
+        config.active_within_days = Some(days);
+    }
+    if args.include_inactive {
+        config.active_within_days = None;
+    }
+    if let Some(days) = args.min_record_days {
+        config.min_record_days = days;
+    }
+    Ok(config)
+}
+
+/// Aggregator options for `--update-db` and the `--serve` refresh schedule
+///
+/// Fails when the `--replay` cache has no readable manifest.
+fn aggregator_options(
+    args: &Args,
+    ingest: &IngestConfig,
+) -> Result<AggregatorOptions, Box<dyn std::error::Error>> {
+    let (http_cache, as_of) = match (&args.cache_dir, &args.replay) {
+        (_, Some(dir)) => (
+            Some(CacheMode::Replay(dir.clone())),
+            Some(ResponseCache::new(dir).read_manifest()?),
+        ),
+        (Some(dir), None) => (Some(CacheMode::Record(dir.clone())), None),
+        (None, None) => (None, None),
+    };
+    Ok(AggregatorOptions {
+        stations_base_url: resolve_base_url(
+            args.stations_base_url.clone(),
+            "TIRESWAP_STATIONS_BASE_URL",
+            DEFAULT_STATIONS_BASE_URL,
+        ),
+        climate_base_url: resolve_base_url(
+            args.climate_base_url.clone(),
+            "TIRESWAP_CLIMATE_BASE_URL",
+            DEFAULT_CLIMATE_BASE_URL,
+        ),
+        source: args.source,
+        daily_source: args.daily_source,
+        normals: args.normals,
+        ingest: ingest.clone(),
+        limits: RequestLimits {
+            max_concurrent: args.max_concurrent_requests,
+            requests_per_second: (args.requests_per_second > 0.0)
+                .then_some(args.requests_per_second),
+        },
+        user_agent: std::env::var("TIRESWAP_INGEST_UA")
+            .unwrap_or_else(|_| DEFAULT_USER_AGENT.to_string()),
+        filter: StationFilter {
+            station_ids: args.stations.clone(),
+            provinces: args.provinces.clone(),
+            bbox: args.bbox,
+        },
+        http_cache,
+        as_of,
+        ..AggregatorOptions::default()
+    })
+}
+
+/// Analyzer options for the CLI analysis, `--dry-run` comparisons and the API server
+fn analyzer_options(args: &Args, ingest: &IngestConfig) -> AnalyzerOptions {
+    AnalyzerOptions {
+        criterion: match args.morning_threshold {
+            Some(threshold_c) => TransitionCriterion::MorningCold { threshold_c },
+            None if args.use_normals => TransitionCriterion::Normals,
+            None => TransitionCriterion::DailyMean,
+        },
+        min_valid_pct: args.min_valid_pct,
+        history_years: ingest.history_years,
+        ..AnalyzerOptions::default()
+    }
+}
+
+/// Resolve an endpoint from its CLI flag, then its environment variable, then the default
+fn resolve_base_url(arg: Option<String>, env_var: &str, default: &str) -> String {
+    arg.or_else(|| std::env::var(env_var).ok())
+        .unwrap_or_else(|| default.to_string())
 }
 
 #[tokio::main]
@@ -49,83 +301,197 @@ async fn main() {
     let db = Database::new(&args.db_path).unwrap();
     db.initialize_schema().unwrap();
 
+    let ingest = match ingest_config(&args) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Error loading ingest configuration: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let options = match aggregator_options(&args, &ingest) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("Error reading replay cache: {}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let dem = match &args.dem {
+        Some(path) => match Dem::from_asc_file(path) {
+            Ok(dem) => Some(dem),
+            Err(e) => {
+                eprintln!("Error loading DEM {}: {}", path.display(), e);
+                std::process::exit(2);
+            }
+        },
+        None => None,
+    };
+
     // If serve mode is enabled, start the API server
     if args.serve {
-        run_server(db, args.port).await;
+        let schedule = match args.refresh_at.as_deref() {
+            Some(at) => match RefreshSchedule::parse(at, &args.refresh_tz) {
+                Ok(schedule) => Some(schedule),
+                Err(e) => {
+                    eprintln!("Error in refresh schedule: {}", e);
+                    std::process::exit(2);
+                }
+            },
+            None => None,
+        };
+        let refresh = schedule.map(|s| (s, options, args.hourly));
+        run_server(db, args.port, refresh, dem, analyzer_options(&args, &ingest)).await;
         return;
     }
 
+    // Try the update on a copy first; the real database is left untouched
+    if args.dry_run {
+        let thresholds = DiffThresholds {
+            max_date_shift_days: args.max_date_shift,
+            max_changed_dates: args.max_changed_dates,
+            max_removed_stations: args.max_removed_stations,
+        };
+        std::process::exit(dry_run_update(&db, &args, &ingest, &options, &thresholds).await);
+    }
+
     // Fetch and store stations using aggregator if --update-db flag is passed
     if args.update_db {
-        let aggregator = Arc::new(Aggregator::new(&db));
-
-        println!("\nFetching stations from API...");
-        match aggregator.fetch_and_store_stations().await {
-            Ok(count) => {
-                println!("Successfully inserted {} stations into database", count);
-
-                // Fetch climate data for all stations
-                println!("\nFetching climate data for all stations...");
-                match db.get_all_stations() {
-                    Ok(stations) => {
-                        let pb = ProgressBar::new(stations.len() as u64);
-                        pb.set_style(
-                            ProgressStyle::default_bar()
-                                .template(
-                                    "[{elapsed_precise}] {bar:40.cyan/blue} {pos}/{len} {msg}",
-                                )
-                                .unwrap()
-                                .progress_chars("##-"),
-                        );
+        let aggregator = Aggregator::with_options(&db, options);
+        aggregator.update_all(args.hourly, RunTrigger::Cli, args.resume).await;
+    }
+
+    if let Some(limit) = args.list_runs {
+        match db.get_recent_ingest_runs(limit) {
+            Ok(runs) if runs.is_empty() => println!("No ingest runs recorded yet."),
+            Ok(runs) => print_ingest_runs(&runs),
+            Err(e) => eprintln!("Error reading ingest runs: {}", e),
+        }
+    }
+
+    // Import pre-downloaded bulk CSV files (no network access needed)
+    if let Some(dir) = &args.import_csv {
+        println!("\nImporting bulk CSV files from {}...", dir.display());
+        let options = AggregatorOptions {
+            ingest: ingest.clone(),
+            ..AggregatorOptions::default()
+        };
+        let aggregator = Aggregator::with_options(&db, options);
+        match aggregator.import_csv_dir(dir) {
+            Ok(report) => {
+                println!("Files read: {}", report.files_read);
+                println!("Observations stored: {}", report.observations_stored);
+                println!("Stations updated: {}", report.stations_updated);
+                println!("Stations created from CSV columns: {}", report.stations_created);
+                for path in &report.unmatched_files {
+                    eprintln!("  ✗ No station columns or Climate ID in {}", path.display());
+                }
+                for (path, error) in &report.failed_files {
+                    eprintln!("  ✗ Could not read {}: {}", path.display(), error);
+                }
+            }
+            Err(e) => eprintln!("Error importing CSV files: {}", e),
+        }
+    }
+
+    // Import NOAA GHCN-Daily files (no network access needed)
+    if let Some(dir) = &args.import_ghcn {
+        println!("\nImporting GHCN-Daily files from {}...", dir.display());
+        let options = AggregatorOptions {
+            ingest: ingest.clone(),
+            ..AggregatorOptions::default()
+        };
+        let aggregator = Aggregator::with_options(&db, options);
+        match aggregator.import_ghcn_dir(dir) {
+            Ok(report) => {
+                println!("Files read: {}", report.files_read);
+                println!("Observations stored: {}", report.observations_stored);
+                println!("Stations updated: {}", report.stations_updated);
+                if report.files_without_recent_data > 0 {
+                    println!(
+                        "Files without data in the history window: {}",
+                        report.files_without_recent_data
+                    );
+                }
+                for path in &report.unmatched_files {
+                    eprintln!("  ✗ Station not in ghcnd-stations.txt for {}", path.display());
+                }
+                for (path, error) in &report.failed_files {
+                    eprintln!("  ✗ Could not import {}: {}", path.display(), error);
+                }
+            }
+            Err(e) => eprintln!("Error importing GHCN-Daily files: {}", e),
+        }
+    }
 
-                        // Process stations with controlled concurrency using buffered stream
-                        let concurrent_limit = 10;
-                        use futures::stream::{self, StreamExt};
-
-                        let agg = Arc::clone(&aggregator);
-                        let mut stream = stream::iter(stations)
-                            .map(|station| {
-                                let station_id = station.id;
-                                let station_name = station.name.clone();
-                                let agg = Arc::clone(&agg);
-                                async move {
-                                    let result = agg
-                                        .fetch_and_store_climate_data(station_id, &station_name)
-                                        .await;
-                                    (result, station_name)
-                                }
-                            })
-                            .buffer_unordered(concurrent_limit);
-
-                        // Process results as they complete
-                        while let Some((result, name)) = stream.next().await {
-                            if let Err(e) = result {
-                                pb.println(format!("  ✗ Error for {}: {}", name, e));
+    // Fill gaps once every station's own data is in, so neighbours are up to date
+    if args.gap_fill {
+        println!("\nFilling gaps from neighbouring stations...");
+        let aggregator = Aggregator::with_options(
+            &db,
+            AggregatorOptions {
+                ingest: ingest.clone(),
+                ..AggregatorOptions::default()
+            },
+        );
+        let today = Utc::now().date_naive();
+        let start =
+            NaiveDate::from_ymd_opt(today.year() - ingest.history_years, 1, 1).unwrap_or(today);
+        match (GapFiller::new(&db, GapFillOptions::default()), db.get_all_stations()) {
+            (Ok(filler), Ok(stations)) => {
+                let mut days_missing = 0;
+                let mut days_filled = 0;
+                let mut stations_filled = 0;
+                for station in stations {
+                    match filler.fill_station(station.id, start, today) {
+                        Ok(report) => {
+                            days_missing += report.days_missing;
+                            days_filled += report.days_filled;
+                            if report.days_filled == 0 {
+                                continue;
+                            }
+                            stations_filled += 1;
+                            if let Err(e) = aggregator.refresh_switch_dates(station.id) {
+                                eprintln!("  ✗ Error recomputing {}: {}", station.name, e);
                             }
-                            pb.inc(1);
                         }
-
-                        pb.finish_with_message("Climate data collection complete!");
-                        println!();
+                        Err(e) => eprintln!("  ✗ Error filling {}: {}", station.name, e),
                     }
-                    Err(e) => eprintln!("Error retrieving stations: {}", e),
                 }
+                println!(
+                    "Filled {} of {} missing days across {} stations",
+                    days_filled, days_missing, stations_filled
+                );
             }
-            Err(e) => eprintln!("Error fetching/storing stations: {}", e),
+            (Err(e), _) => eprintln!("Error creating gap filler: {}", e),
+            (_, Err(e)) => eprintln!("Error retrieving stations: {}", e),
         }
     }
 
     // Analyze tire swap dates for a location (if coordinates provided)
     if let (Some(latitude), Some(longitude)) = (args.latitude, args.longitude) {
         println!("\n--- Tire Swap Analysis ---");
-        match Analyzer::new(&db) {
+        let options = analyzer_options(&args, &ingest);
+        let elevation_m = args.elevation.or_else(|| {
+            dem.as_ref()
+                .and_then(|dem| dem.elevation_at(latitude, longitude))
+        });
+        match Analyzer::with_options(&db, options) {
             Ok(analyzer) => {
                 println!(
                     "Analyzing tire swap dates for location ({}, {})...\n",
                     latitude, longitude
                 );
+                if let Some(elevation_m) = elevation_m {
+                    println!("Location elevation: {:.0} m\n", elevation_m);
+                }
 
-                match analyzer.analyze(latitude, longitude, args.num_stations) {
+                match analyzer.analyze_with_elevation(
+                    latitude,
+                    longitude,
+                    elevation_m,
+                    args.num_stations,
+                ) {
                     Ok(recommendation) => {
                         println!(
                             "Based on {} nearest weather stations:",
@@ -144,6 +510,15 @@ async fn main() {
                         } else {
                             println!("❄️  Switch to winter tires: No data available");
                         }
+
+                        let excluded = recommendation.summer_completeness.years_excluded
+                            + recommendation.winter_completeness.years_excluded;
+                        if excluded > 0 {
+                            println!(
+                                "\nLeft out {} station-seasons with under {}% valid days",
+                                excluded, args.min_valid_pct
+                            );
+                        }
                         println!();
                     }
                     Err(e) => eprintln!("Error analyzing tire swap dates: {}", e),
@@ -151,17 +526,190 @@ async fn main() {
             }
             Err(e) => eprintln!("Error creating tire swap analyzer: {}", e),
         }
-    } else if !args.update_db {
+    } else if !args.update_db
+        && args.import_csv.is_none()
+        && args.import_ghcn.is_none()
+        && !args.gap_fill
+        && args.list_runs.is_none()
+    {
         eprintln!("\nError: Please provide --latitude and --longitude to analyze a location.");
         eprintln!("Or use --update-db to update the database.");
+        eprintln!("Or use --import-csv <DIR> to import downloaded bulk CSV files.");
+        eprintln!("Or use --import-ghcn <DIR> to import NOAA GHCN-Daily files.");
         eprintln!("Or use --serve to start the API server.\n");
         eprintln!("For help, run: cargo run -- --help");
     }
 }
 
-/// Run the API server
-async fn run_server(db: Database, port: u16) {
+/// Print ingest runs, newest first, with their first few errors
+fn print_ingest_runs(runs: &[IngestRun]) {
+    println!("\n--- Ingest Runs ---");
+    for run in runs {
+        println!(
+            "#{} {} [{}] started {}, finished {}",
+            run.id,
+            run.status,
+            run.trigger,
+            run.started_at,
+            run.finished_at.as_deref().unwrap_or("-")
+        );
+        println!(
+            "    stations: {} seen, {} inserted, {} inactive, {} insufficient data, {} failed, \
+             {} incomplete, {} resumed",
+            run.stations_seen,
+            run.stations_inserted,
+            run.stations_inactive,
+            run.stations_insufficient_data,
+            run.stations_failed,
+            run.stations_incomplete,
+            run.stations_resumed
+        );
+        println!(
+            "    months: {} fetched, {} failed, {} hourly failed ({} daily source)",
+            run.months_fetched, run.months_failed, run.hourly_months_failed, run.daily_source
+        );
+        for error in run.errors.iter().take(5) {
+            println!("    ✗ {}", error);
+        }
+        if run.error_count > 5 {
+            println!("    ... {} errors in total", run.error_count);
+        }
+    }
+    println!();
+}
+
+/// Run `--update-db` against a temporary copy of `db` and print how it would change the data
+///
+/// # Returns
+/// * `i32` - Exit code: 0 when the changes stay within `thresholds`, 3 when they exceed
+///   them, 1 when the update could not be carried out
+async fn dry_run_update(
+    db: &Database,
+    args: &Args,
+    ingest: &IngestConfig,
+    options: &AggregatorOptions,
+    thresholds: &DiffThresholds,
+) -> i32 {
+    let path = std::env::temp_dir().join(format!("tireswap-dry-run-{}.db", std::process::id()));
+    // VACUUM INTO refuses to overwrite a leftover file
+    let _ = std::fs::remove_file(&path);
+    let result = dry_run_diff(db, &path, args, ingest, options, thresholds).await;
+    let _ = std::fs::remove_file(&path);
+
+    let (report, diff) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Error during dry run: {}", e);
+            return 1;
+        }
+    };
+    if report.status() == "failed" {
+        eprintln!("✗ Dry run failed: the update could not fetch the station catalogue");
+        return 1;
+    }
+    print_database_diff(&diff, thresholds.max_date_shift_days);
+    let exceeded = diff.exceeded(thresholds);
+    if exceeded.is_empty() {
+        println!("✓ Changes are within the thresholds ({} update)", report.status());
+        return 0;
+    }
+    for message in exceeded {
+        eprintln!("✗ Threshold exceeded: {}", message);
+    }
+    3
+}
+
+/// Copy `db` to `path`, run the update there and compare the copy with `db`
+async fn dry_run_diff(
+    db: &Database,
+    path: &std::path::Path,
+    args: &Args,
+    ingest: &IngestConfig,
+    options: &AggregatorOptions,
+    thresholds: &DiffThresholds,
+) -> Result<(UpdateReport, DatabaseDiff), Box<dyn std::error::Error>> {
+    println!("\nDry run: updating a copy of the database at {}", path.display());
+    db.copy_to(path)?;
+    let copy = Database::new(path)?;
+    copy.initialize_schema()?;
+
+    let aggregator = Aggregator::with_options(&copy, options.clone());
+    let report = aggregator.update_all(args.hourly, RunTrigger::Cli, false).await;
+    let mut diff = DatabaseDiff::compare(
+        db,
+        &copy,
+        &analyzer_options(args, ingest),
+        thresholds.max_date_shift_days,
+    )?;
+    if let Some(stations) = &report.stations {
+        diff.add_catalogue_removals(
+            db,
+            options.source.as_str(),
+            &options.filter,
+            &stations.stored_ids,
+        )?;
+    }
+    Ok((report, diff))
+}
+
+/// Print the stations and recommendations a dry run would change
+fn print_database_diff(diff: &DatabaseDiff, max_date_shift_days: u32) {
+    const MAX_LISTED: usize = 20;
+    println!("\n--- Dry Run Changes ---");
+
+    println!("Stations added: {}", diff.added_stations.len());
+    for (id, name) in diff.added_stations.iter().take(MAX_LISTED) {
+        println!("  + {} ({})", name, id);
+    }
+    println!("Stations removed: {}", diff.removed_stations.len());
+    for (id, name) in diff.removed_stations.iter().take(MAX_LISTED) {
+        println!("  - {} ({})", name, id);
+    }
+    println!(
+        "Recommendations moved by more than {} days: {}",
+        max_date_shift_days,
+        diff.changed_dates.len()
+    );
+    for change in diff.changed_dates.iter().take(MAX_LISTED) {
+        let after = match (change.after, change.shift_days()) {
+            (Some(after), Some(shift)) => {
+                format!("{} ({:+} days)", day_of_year_to_date(after), shift)
+            }
+            _ => "no date".to_string(),
+        };
+        println!(
+            "  ~ {} ({}) {}: {} -> {}",
+            change.station_name,
+            change.station_id,
+            change.season.as_str(),
+            day_of_year_to_date(change.before),
+            after
+        );
+    }
+    let longest = diff
+        .added_stations
+        .len()
+        .max(diff.removed_stations.len())
+        .max(diff.changed_dates.len());
+    if longest > MAX_LISTED {
+        println!("  (only the first {} of each list are shown)", MAX_LISTED);
+    }
+    println!();
+}
+
+/// Run the API server, optionally refreshing its data on a daily schedule
+async fn run_server(
+    db: Database,
+    port: u16,
+    refresh: Option<(RefreshSchedule, AggregatorOptions, bool)>,
+    dem: Option<Dem>,
+    analyzer_options: AnalyzerOptions,
+) {
     let db_arc = Arc::new(db);
+    if let Some((schedule, options, hourly)) = refresh {
+        let refresher = Arc::new(Refresher::new(Arc::clone(&db_arc), options, hourly));
+        spawn_scheduled_refresh(refresher, schedule);
+    }
     let user_agent = std::env::var("TIRESWAP_NOMINATIM_UA")
         .unwrap_or_else(|_| "TireSwap/0.1 (tireswap backend)".to_string());
     let geocode_client = reqwest::Client::builder()
@@ -169,11 +717,21 @@ async fn run_server(db: Database, port: u16) {
         .build()
         .expect("Failed to build geocoding client");
 
+    let admin_token = std::env::var("TIRESWAP_ADMIN_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty());
+    if admin_token.is_none() {
+        eprintln!("TIRESWAP_ADMIN_TOKEN is not set; /api/admin/* endpoints are disabled");
+    }
+
     let state = AppState {
         db: db_arc,
         geocode_client,
         geocode_cache: Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
         geocode_base_url: "https://nominatim.openstreetmap.org".to_string(),
+        admin_token,
+        dem: dem.map(Arc::new),
+        analyzer_options,
     };
     let app = create_router(state);
 
@@ -1,4 +1,4 @@
-use crate::db::Database;
+use crate::db::{Database, StationMetadata};
 use kiddo::{KdTree, SquaredEuclidean};
 use rusqlite::Result;
 
@@ -19,13 +19,26 @@ pub struct StationWithDistance {
     pub dly_first_date: Option<String>,
     #[allow(dead_code)]
     pub dly_last_date: Option<String>,
+    /// Province, identifiers, elevation and time zone from the station catalogue
+    pub metadata: StationMetadata,
 }
 
+/// Cached station fields: (id, name, lon, lat, dly_first_date, dly_last_date, metadata)
+type StationEntry = (
+    i64,
+    String,
+    f64,
+    f64,
+    Option<String>,
+    Option<String>,
+    StationMetadata,
+);
+
 /// NearestStationFinder uses a k-d tree to efficiently find the closest weather station
 /// to a given latitude and longitude using haversine distance.
 pub struct NearestStationFinder {
     kdtree: KdTree<f64, 2>,
-    stations: Vec<(i64, String, f64, f64, Option<String>, Option<String>)>, // (id, name, lon, lat, dly_first_date, dly_last_date)
+    stations: Vec<StationEntry>,
 }
 
 impl NearestStationFinder {
@@ -51,6 +64,7 @@ impl NearestStationFinder {
                 station.lat_y,
                 station.dly_first_date.clone(),
                 station.dly_last_date.clone(),
+                station.metadata.clone(),
             ));
 
             // Insert into k-d tree using [longitude, latitude] as coordinates
@@ -114,7 +128,9 @@ impl NearestStationFinder {
         for neighbour in nearest {
             let idx = neighbour.item as usize;
             // Get station by index
-            if let Some((id, name, s_lon, s_lat, dly_first, dly_last)) = self.stations.get(idx) {
+            if let Some((id, name, s_lon, s_lat, dly_first, dly_last, metadata)) =
+                self.stations.get(idx)
+            {
                 let distance = Self::haversine_distance(lat, lon, *s_lat, *s_lon);
 
                 if distance < best_distance {
@@ -127,6 +143,7 @@ impl NearestStationFinder {
                         distance_km: distance,
                         dly_first_date: dly_first.clone(),
                         dly_last_date: dly_last.clone(),
+                        metadata: metadata.clone(),
                     });
                 }
             }
@@ -162,7 +179,7 @@ impl NearestStationFinder {
                 let idx = neighbour.item as usize;
                 self.stations
                     .get(idx)
-                    .map(|(id, name, s_lon, s_lat, dly_first, dly_last)| {
+                    .map(|(id, name, s_lon, s_lat, dly_first, dly_last, metadata)| {
                         let distance = Self::haversine_distance(lat, lon, *s_lat, *s_lon);
                         StationWithDistance {
                             id: *id,
@@ -172,6 +189,7 @@ impl NearestStationFinder {
                             distance_km: distance,
                             dly_first_date: dly_first.clone(),
                             dly_last_date: dly_last.clone(),
+                            metadata: metadata.clone(),
                         }
                     })
             })
@@ -183,6 +201,55 @@ impl NearestStationFinder {
 
         stations_with_dist
     }
+
+    /// Find the k nearest stations, ranking them by horizontal distance plus a penalty
+    /// for the difference between their elevation and `elevation_m`
+    ///
+    /// Stations without a known elevation rank after every station with one, since
+    /// their climate may differ by any height. The returned `distance_km` is still
+    /// the horizontal distance.
+    ///
+    /// # Arguments
+    /// * `lat` - Target latitude in degrees
+    /// * `lon` - Target longitude in degrees
+    /// * `elevation_m` - Target elevation in metres; `None` ranks by distance alone
+    /// * `k` - Number of stations to return
+    /// * `penalty_km_per_m` - Ranking distance added per metre of elevation difference
+    ///
+    /// # Returns
+    /// * `Vec<StationWithDistance>` - Vector of k best-ranked stations, best first
+    pub fn find_k_nearest_with_elevation(
+        &self,
+        lat: f64,
+        lon: f64,
+        elevation_m: Option<f64>,
+        k: usize,
+        penalty_km_per_m: f64,
+    ) -> Vec<StationWithDistance> {
+        // A NaN target, e.g. from a DEM cell without data, is the same as no target
+        let Some(elevation_m) = elevation_m.filter(|m| m.is_finite() && penalty_km_per_m > 0.0)
+        else {
+            return self.find_k_nearest(lat, lon, k);
+        };
+
+        // A penalised station may be beaten by one much further away, so widen the pool
+        let mut stations = self.find_k_nearest(lat, lon, (k * 10).max(50));
+        // (unknown elevation, ranking distance), so unknown heights sort last; a
+        // malformed catalogue elevation counts as unknown
+        let score = |station: &StationWithDistance| match station.metadata.elevation_m {
+            Some(station_m) if station_m.is_finite() => {
+                let difference = (station_m - elevation_m).abs();
+                (false, station.distance_km + penalty_km_per_m * difference)
+            }
+            _ => (true, station.distance_km),
+        };
+        stations.sort_by(|a, b| {
+            let ((a_unknown, a_km), (b_unknown, b_km)) = (score(a), score(b));
+            a_unknown.cmp(&b_unknown).then(a_km.total_cmp(&b_km))
+        });
+        stations.truncate(k);
+        stations
+    }
 }
 
 #[cfg(test)]
@@ -221,4 +288,56 @@ mod tests {
             distance
         );
     }
+
+    #[test]
+    fn test_elevation_penalty_prefers_stations_at_similar_height() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+        // Close but 800 m up the mountain, and further away in the valley
+        db.insert_station(1, &"Mountain".to_string(), -116.00, 51.00, None, None)
+            .unwrap();
+        db.insert_station(2, &"Valley".to_string(), -116.40, 51.00, None, None)
+            .unwrap();
+        for (id, elevation_m) in [(1, 1900.0), (2, 1100.0)] {
+            let metadata = StationMetadata {
+                elevation_m: Some(elevation_m),
+                ..Default::default()
+            };
+            db.set_station_metadata(id, &metadata).unwrap();
+        }
+        let finder = NearestStationFinder::new(&db).unwrap();
+
+        let by_distance = finder.find_k_nearest(51.0, -116.05, 1);
+        assert_eq!(by_distance[0].id, 1);
+
+        let by_elevation =
+            finder.find_k_nearest_with_elevation(51.0, -116.05, Some(1100.0), 1, 0.1);
+        assert_eq!(by_elevation[0].id, 2);
+        assert!(by_elevation[0].distance_km > by_distance[0].distance_km);
+
+        // Without a target elevation the ranking is unchanged
+        let unknown = finder.find_k_nearest_with_elevation(51.0, -116.05, None, 1, 0.1);
+        assert_eq!(unknown[0].id, 1);
+
+        // A station of unknown height ranks last, however close it is
+        db.insert_station(3, &"Unsurveyed".to_string(), -116.05, 51.00, None, None)
+            .unwrap();
+        let finder = NearestStationFinder::new(&db).unwrap();
+        let ranked = finder.find_k_nearest_with_elevation(51.0, -116.05, Some(1100.0), 3, 0.1);
+        let ids: Vec<i64> = ranked.iter().map(|station| station.id).collect();
+        assert_eq!(ids, vec![2, 1, 3]);
+
+        // NaN heights rank like unknown ones instead of panicking
+        let metadata = StationMetadata {
+            elevation_m: Some(f64::NAN),
+            ..Default::default()
+        };
+        db.set_station_metadata(3, &metadata).unwrap();
+        let finder = NearestStationFinder::new(&db).unwrap();
+        let ranked = finder.find_k_nearest_with_elevation(51.0, -116.05, Some(1100.0), 3, 0.1);
+        let ids: Vec<i64> = ranked.iter().map(|station| station.id).collect();
+        assert_eq!(ids, vec![2, 1, 3]);
+        let ranked = finder.find_k_nearest_with_elevation(51.0, -116.05, Some(f64::NAN), 1, 0.1);
+        assert_eq!(ranked[0].id, 3);
+    }
 }
@@ -0,0 +1,212 @@
+use crate::aggregator::{Aggregator, AggregatorOptions, RunTrigger, UpdateReport};
+use crate::db::Database;
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// Daily wall-clock time, in a given time zone, at which `--serve` refreshes its data
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RefreshSchedule {
+    pub at: NaiveTime,
+    pub tz: Tz,
+}
+
+impl RefreshSchedule {
+    /// Parse a schedule from an `HH:MM` time and an IANA time zone name
+    ///
+    /// # Arguments
+    /// * `at` - Local time of day, e.g. `03:00`
+    /// * `tz` - Time zone name, e.g. `America/Toronto`
+    ///
+    /// # Returns
+    /// * `Result<Self, String>` - Schedule or a description of the invalid part
+    pub fn parse(at: &str, tz: &str) -> Result<Self, String> {
+        let at = NaiveTime::parse_from_str(at, "%H:%M")
+            .map_err(|_| format!("invalid refresh time '{}', expected HH:MM", at))?;
+        let tz = tz
+            .parse::<Tz>()
+            .map_err(|_| format!("unknown time zone '{}'", tz))?;
+        Ok(RefreshSchedule { at, tz })
+    }
+
+    /// First scheduled run strictly after `now`
+    ///
+    /// When the local time does not exist on a day (spring-forward gap), the run
+    /// happens an hour later; when it occurs twice (fall-back), the first one is used.
+    ///
+    /// # Arguments
+    /// * `now` - Current instant
+    ///
+    /// # Returns
+    /// * `DateTime<Utc>` - Instant of the next run
+    pub fn next_after(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        let mut date = now.with_timezone(&self.tz).date_naive();
+        loop {
+            let local = date.and_time(self.at);
+            let run = self
+                .tz
+                .from_local_datetime(&local)
+                .earliest()
+                .or_else(|| {
+                    self.tz
+                        .from_local_datetime(&(local + Duration::hours(1)))
+                        .earliest()
+                });
+            if let Some(run) = run
+                && run.with_timezone(&Utc) > now
+            {
+                return run.with_timezone(&Utc);
+            }
+            date = date.succ_opt().expect("date out of range");
+        }
+    }
+}
+
+/// Runs the ingest pipeline against a shared database, one run at a time
+///
+/// The API handlers read from the same `Database`, so stations and switch dates
+/// written by a refresh are served as soon as they are stored, without a restart.
+pub struct Refresher {
+    db: Arc<Database>,
+    options: AggregatorOptions,
+    hourly: bool,
+    running: Mutex<()>,
+}
+
+impl Refresher {
+    /// Create a refresher
+    ///
+    /// # Arguments
+    /// * `db` - Database shared with the API server
+    /// * `options` - Upstream endpoints and ingest settings for each run
+    /// * `hourly` - Also download hourly observations on each run
+    pub fn new(db: Arc<Database>, options: AggregatorOptions, hourly: bool) -> Self {
+        Refresher {
+            db,
+            options,
+            hourly,
+            running: Mutex::new(()),
+        }
+    }
+
+    /// Whether a run is in progress
+    pub fn is_running(&self) -> bool {
+        self.running.try_lock().is_err()
+    }
+
+    /// Run a full update unless one is already in progress
+    ///
+    /// # Returns
+    /// * `Option<UpdateReport>` - Report of the run, or `None` if another run held the lock
+    pub async fn try_run(&self) -> Option<UpdateReport> {
+        let _guard = self.running.try_lock().ok()?;
+        let aggregator = Aggregator::with_options(&self.db, self.options.clone());
+        Some(aggregator.update_all(self.hourly, RunTrigger::Schedule, false).await)
+    }
+}
+
+/// Spawn a background task that refreshes data on `schedule` until the runtime shuts down
+///
+/// A scheduled run that finds another run still in progress is skipped.
+///
+/// # Arguments
+/// * `refresher` - Refresher shared with anything else that may trigger a run
+/// * `schedule` - Daily refresh time
+///
+/// # Returns
+/// * `JoinHandle<()>` - Handle of the background task
+pub fn spawn_scheduled_refresh(
+    refresher: Arc<Refresher>,
+    schedule: RefreshSchedule,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        loop {
+            let now = Utc::now();
+            let next = schedule.next_after(now);
+            println!("Next data refresh at {}", next.with_timezone(&schedule.tz));
+            tokio::time::sleep((next - now).to_std().unwrap_or_default()).await;
+
+            match refresher.try_run().await {
+                Some(report) => println!(
+                    "Data refresh finished ({}): {} stations updated, {} failed, {} months failed",
+                    report.status(),
+                    report.stations_updated,
+                    report.stations_failed,
+                    report.months_failed
+                ),
+                None => println!("Skipping scheduled data refresh: a refresh is still running"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_input() {
+        assert!(RefreshSchedule::parse("03:00", "America/Toronto").is_ok());
+        assert!(RefreshSchedule::parse("3am", "America/Toronto").is_err());
+        assert!(RefreshSchedule::parse("03:00", "Mars/Olympus").is_err());
+    }
+
+    #[test]
+    fn test_next_after_uses_local_time() {
+        let schedule = RefreshSchedule::parse("03:00", "America/Toronto").unwrap();
+
+        // 01:00 EDT, so 03:00 local is later the same day
+        assert_eq!(
+            schedule.next_after(utc("2026-07-01T05:00:00Z")),
+            utc("2026-07-01T07:00:00Z")
+        );
+        // Exactly at the scheduled time moves to the next day
+        assert_eq!(
+            schedule.next_after(utc("2026-07-01T07:00:00Z")),
+            utc("2026-07-02T07:00:00Z")
+        );
+        // Winter: EST is UTC-5
+        assert_eq!(
+            schedule.next_after(utc("2026-01-15T12:00:00Z")),
+            utc("2026-01-16T08:00:00Z")
+        );
+    }
+
+    #[test]
+    fn test_next_after_handles_daylight_saving_changes() {
+        // 02:30 does not exist on 2026-03-08 in Toronto; run at 03:30 EDT instead
+        let gap = RefreshSchedule::parse("02:30", "America/Toronto").unwrap();
+        assert_eq!(
+            gap.next_after(utc("2026-03-08T05:00:00Z")),
+            utc("2026-03-08T07:30:00Z")
+        );
+
+        // 01:30 happens twice on 2026-11-01; only the first one counts
+        let overlap = RefreshSchedule::parse("01:30", "America/Toronto").unwrap();
+        assert_eq!(
+            overlap.next_after(utc("2026-11-01T04:00:00Z")),
+            utc("2026-11-01T05:30:00Z")
+        );
+        assert_eq!(
+            overlap.next_after(utc("2026-11-01T05:30:00Z")),
+            utc("2026-11-02T06:30:00Z")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_runs_never_overlap() {
+        let db = Database::new_in_memory().unwrap();
+        db.initialize_schema().unwrap();
+        let refresher = Refresher::new(Arc::new(db), AggregatorOptions::default(), false);
+
+        let _guard = refresher.running.try_lock().unwrap();
+        assert!(refresher.is_running());
+        assert!(refresher.try_run().await.is_none());
+    }
+}
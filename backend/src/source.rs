@@ -0,0 +1,121 @@
+use crate::aggregator::StationFilter;
+use crate::db::{DailyObservation, StationMetadata};
+use chrono::NaiveDate;
+use futures::stream::BoxStream;
+
+/// Weather data providers the aggregator can be pointed at by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SourceKind {
+    /// Environment and Climate Change Canada: the OGC API station catalogue, and
+    /// bulk CSV or `climate-daily` observations depending on `DailySource`
+    #[default]
+    Eccc,
+}
+
+impl std::str::FromStr for SourceKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "eccc" => Ok(SourceKind::Eccc),
+            other => Err(format!("unknown weather source '{}' (expected 'eccc')", other)),
+        }
+    }
+}
+
+impl SourceKind {
+    /// Name accepted by `from_str`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SourceKind::Eccc => "eccc",
+        }
+    }
+}
+
+/// A station as listed by a source, before the aggregator applies its filter and
+/// eligibility rules
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceStation {
+    pub id: i64,
+    pub name: String,
+    pub lon_x: f64,
+    pub lat_y: f64,
+    /// First date of the daily record ("YYYY-MM-DD ..."), if the source knows it
+    pub dly_first_date: Option<String>,
+    /// Last date of the daily record, if the source knows it
+    pub dly_last_date: Option<String>,
+    pub metadata: StationMetadata,
+}
+
+/// One page (or file) of stations listed by a source
+#[derive(Debug, Clone, Default)]
+pub struct StationPage {
+    pub stations: Vec<SourceStation>,
+    /// Number of stations the source reported as matching, if it reports one
+    pub number_matched: Option<u64>,
+    /// Entries left out for missing an ID, name or coordinates
+    pub skipped: usize,
+}
+
+/// Daily observations of one calendar month, or the error the month failed with
+#[derive(Debug, Clone)]
+pub struct MonthRecords {
+    pub year: i32,
+    pub month: u32,
+    pub records: Result<Vec<DailyObservation>, String>,
+}
+
+/// A provider of weather stations and their daily observations
+///
+/// The aggregator decides which stations and months to fetch, and owns the
+/// storage, incremental bookkeeping and switch-date detection; a source only
+/// lists stations and delivers observations. Implementations are used behind
+/// an `Arc`, from several stations concurrently.
+pub trait WeatherSource: Send + Sync {
+    /// Name of the source, e.g. "eccc"
+    fn name(&self) -> &'static str;
+
+    /// Stream the stations of the source one page at a time
+    ///
+    /// # Arguments
+    /// * `filter` - Stations the caller is interested in; a source may use it to
+    ///   narrow its query, but every station is checked against it again
+    ///
+    /// # Returns
+    /// * `BoxStream<Result<StationPage, String>>` - Pages in order; a page that could
+    ///   not be read yields its error and ends the stream
+    fn list_stations<'a>(
+        &'a self,
+        filter: &'a StationFilter,
+    ) -> BoxStream<'a, Result<StationPage, String>>;
+
+    /// Stream the daily observations of a station between two dates (inclusive)
+    ///
+    /// # Arguments
+    /// * `station_id` - The station ID, as listed by `list_stations`
+    /// * `start` - First day of the first month
+    /// * `end` - Last day wanted; the month it falls in is the last one delivered
+    ///
+    /// # Returns
+    /// * `BoxStream<MonthRecords>` - One item per calendar month from `start` to `end`,
+    ///   in any order; a month that could not be fetched carries its error
+    fn daily_records<'a>(
+        &'a self,
+        station_id: i64,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> BoxStream<'a, MonthRecords>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_source_kind_from_str() {
+        assert_eq!("eccc".parse::<SourceKind>(), Ok(SourceKind::Eccc));
+        assert_eq!("ECCC".parse::<SourceKind>(), Ok(SourceKind::Eccc));
+        assert_eq!(SourceKind::Eccc.as_str(), "eccc");
+        assert!("noaa".parse::<SourceKind>().is_err());
+    }
+}
@@ -0,0 +1,1792 @@
+use chrono::{Datelike, Duration, Months, NaiveDate, Utc};
+use futures::stream::{self, BoxStream, StreamExt};
+use httpmock::MockServer;
+
+use axum::{body::Body, http::Request};
+use serde_json::Value;
+use std::sync::Arc;
+use tower::util::ServiceExt;
+
+use backend::aggregator::{
+    csv_station_id, Aggregator, AggregatorOptions, DailySource, RetryPolicy, RunTrigger,
+    StationFilter, DEFAULT_USER_AGENT,
+};
+use backend::analyzer::{Analyzer, AnalyzerOptions, TransitionCriterion};
+use backend::api::{create_router, AppState};
+use backend::cache::CacheMode;
+use backend::config::IngestConfig;
+use backend::db::{DailyObservation, Database, SeasonCompleteness};
+use backend::limiter::RequestLimits;
+use backend::scheduler::Refresher;
+use backend::source::{MonthRecords, SourceStation, StationPage, WeatherSource};
+
+const CSV_HEADER: &str = "\"Longitude (x)\",\"Latitude (y)\",\"Station Name\",\"Climate ID\",\"Date/Time\",\"Year\",\"Month\",\"Day\",\"Data Quality\",\"Max Temp (°C)\",\"Max Temp Flag\",\"Min Temp (°C)\",\"Min Temp Flag\",\"Mean Temp (°C)\",\"Mean Temp Flag\",\"Heat Deg Days (°C)\",\"Heat Deg Days Flag\",\"Cool Deg Days (°C)\",\"Cool Deg Days Flag\",\"Total Rain (mm)\",\"Total Rain Flag\",\"Total Snow (cm)\",\"Total Snow Flag\",\"Total Precip (mm)\",\"Total Precip Flag\",\"Snow on Grnd (cm)\",\"Snow on Grnd Flag\",\"Dir of Max Gust (10s deg)\",\"Dir of Max Gust Flag\",\"Spd of Max Gust (km/h)\",\"Spd of Max Gust Flag\"";
+
+fn build_db() -> Database {
+    let db = Database::new_in_memory().expect("db init");
+    db.initialize_schema().expect("schema init");
+    db
+}
+
+fn options(server: &MockServer) -> AggregatorOptions {
+    AggregatorOptions {
+        stations_base_url: server.url(""),
+        climate_base_url: server.url(""),
+        retry: RetryPolicy {
+            max_attempts: 3,
+            base_delay: std::time::Duration::from_millis(1),
+            max_delay: std::time::Duration::from_millis(5),
+        },
+        limits: RequestLimits {
+            max_concurrent: 8,
+            requests_per_second: None,
+        },
+        ..AggregatorOptions::default()
+    }
+}
+
+/// Build a daily bulk CSV body with one row per (date, mean temperature)
+fn daily_csv(days: &[(NaiveDate, f64)]) -> String {
+    let mut body = String::from(CSV_HEADER);
+    for (date, mean) in days {
+        body.push_str(&format!(
+            "\n\"-79.40\",\"43.67\",\"TORONTO CITY\",\"6158355\",\"{}\",\"{}\",\"{:02}\",\"{:02}\",\"\",\"{:.1}\",\"\",\"{:.1}\",\"\",\"{:.1}\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"\",\"0.0\",\"\",\"\",\"\",\"\",\"\",\"\",\"\"",
+            date.format("%Y-%m-%d"),
+            date.year(),
+            date.month(),
+            date.day(),
+            mean + 4.0,
+            mean - 4.0,
+            mean
+        ));
+    }
+    body
+}
+
+/// Daily means for a whole month: `before` up to and including `switch_day`, `after` from then on
+fn month_days(
+    year: i32,
+    month: u32,
+    switch_day: u32,
+    before: f64,
+    after: f64,
+) -> Vec<(NaiveDate, f64)> {
+    let mut days = Vec::new();
+    let mut date = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+    while date.month() == month {
+        let mean = if date.day() <= switch_day { before } else { after };
+        days.push((date, mean));
+        date += Duration::days(1);
+    }
+    days
+}
+
+/// Build `climate-daily` features with one feature per (date, mean temperature)
+fn climate_daily_features(days: &[(NaiveDate, f64)]) -> String {
+    days.iter()
+        .map(|(date, mean)| {
+            format!(
+                r#"{{
+                    "type": "Feature",
+                    "properties": {{
+                        "STN_ID": 4607,
+                        "LOCAL_DATE": "{} 00:00:00",
+                        "MEAN_TEMPERATURE": {:.1},
+                        "MEAN_TEMPERATURE_FLAG": null,
+                        "MIN_TEMPERATURE": {:.1},
+                        "MAX_TEMPERATURE": {:.1},
+                        "TOTAL_PRECIPITATION": 0.0
+                    }}
+                }}"#,
+                date.format("%Y-%m-%d"),
+                mean,
+                mean - 4.0,
+                mean + 4.0
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn app_state(db: &Arc<Database>, admin_token: Option<&str>) -> AppState {
+    AppState {
+        db: Arc::clone(db),
+        geocode_client: reqwest::Client::new(),
+        geocode_cache: Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
+        geocode_base_url: "http://127.0.0.1:9".to_string(),
+        admin_token: admin_token.map(str::to_string),
+        dem: None,
+        analyzer_options: AnalyzerOptions::default(),
+    }
+}
+
+/// GET `uri` from the router, optionally with a bearer token, and parse the JSON body
+async fn get_json(app: &axum::Router, uri: &str, token: Option<&str>) -> (u16, Value) {
+    let mut request = Request::builder().uri(uri);
+    if let Some(token) = token {
+        request = request.header("authorization", format!("Bearer {}", token));
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&body).unwrap())
+}
+
+fn station_feature(id: i64, name: &str, last_date: &str) -> String {
+    format!(
+        r#"{{
+            "type": "Feature",
+            "properties": {{
+                "STN_ID": {},
+                "STATION_NAME": "{}",
+                "LONGITUDE": -794000000,
+                "LATITUDE": 436700000,
+                "DLY_FIRST_DATE": "2000-01-01 00:00:00",
+                "DLY_LAST_DATE": "{}",
+                "PROV_STATE_TERR_CODE": "ON",
+                "CLIMATE_IDENTIFIER": "6158355",
+                "WMO_IDENTIFIER": 71508,
+                "TC_IDENTIFIER": "XTO",
+                "ELEVATION": "112.5",
+                "TIMEZONE": "EST"
+            }}
+        }}"#,
+        id, name, last_date
+    )
+}
+
+#[tokio::test]
+async fn fetch_and_store_stations_from_mock_catalogue() {
+    let server = MockServer::start();
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    let catalogue = format!(
+        r#"{{"type": "FeatureCollection", "features": [{}, {}]}}"#,
+        station_feature(4607, "TORONTO CITY", &today),
+        station_feature(5051, "CLOSED STATION", "2001-06-30 00:00:00"),
+    );
+    let stations_mock = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items");
+        then.status(200)
+            .header("content-type", "application/geo+json")
+            .body(catalogue);
+    });
+
+    let db = build_db();
+    let aggregator = Aggregator::with_options(&db, options(&server));
+    let report = aggregator.fetch_and_store_stations().await.unwrap();
+
+    stations_mock.assert();
+    assert_eq!(report.inserted, 1);
+    assert_eq!(report.inactive, 1);
+    assert_eq!(report.pages, 1);
+    let stations = db.get_all_stations().unwrap();
+    assert_eq!(stations.len(), 1);
+    assert_eq!(stations[0].id, 4607);
+    assert!((stations[0].lat_y - 43.67).abs() < 1e-9);
+    let metadata = &stations[0].metadata;
+    assert_eq!(metadata.province.as_deref(), Some("ON"));
+    assert_eq!(metadata.climate_id.as_deref(), Some("6158355"));
+    assert_eq!(metadata.wmo_id, Some(71508));
+    assert_eq!(metadata.tc_id.as_deref(), Some("XTO"));
+    assert_eq!(metadata.elevation_m, Some(112.5));
+    assert_eq!(metadata.timezone.as_deref(), Some("EST"));
+}
+
+#[tokio::test]
+async fn fetch_and_store_stations_follows_next_links() {
+    let server = MockServer::start();
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    let first_page = format!(
+        r#"{{
+            "type": "FeatureCollection",
+            "numberMatched": 4,
+            "numberReturned": 2,
+            "features": [{}, {}],
+            "links": [
+                {{"rel": "self", "href": "{}"}},
+                {{"rel": "next", "href": "{}"}}
+            ]
+        }}"#,
+        station_feature(1, "FIRST", &today),
+        station_feature(2, "SECOND", &today),
+        server.url("/collections/climate-stations/items?limit=2&offset=0"),
+        server.url("/collections/climate-stations/items?limit=2&offset=2"),
+    );
+    let second_page = format!(
+        r#"{{
+            "type": "FeatureCollection",
+            "numberMatched": 4,
+            "numberReturned": 2,
+            "features": [{}, {{"type": "Feature", "properties": {{"STATION_NAME": "NO ID"}}}}],
+            "links": [{{"rel": "prev", "href": "{}"}}]
+        }}"#,
+        station_feature(3, "THIRD", &today),
+        server.url("/collections/climate-stations/items?limit=2&offset=0"),
+    );
+    let first = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items")
+            .query_param("offset", "0");
+        then.status(200).body(first_page);
+    });
+    let second = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items")
+            .query_param("offset", "2");
+        then.status(200).body(second_page);
+    });
+
+    let db = build_db();
+    let aggregator = Aggregator::with_options(
+        &db,
+        AggregatorOptions {
+            stations_page_size: 2,
+            ..options(&server)
+        },
+    );
+    let report = aggregator.fetch_and_store_stations().await.unwrap();
+
+    first.assert();
+    second.assert();
+    assert_eq!(report.pages, 2);
+    assert_eq!(report.number_matched, Some(4));
+    // The feature without an ID counts as received, so the totals agree
+    assert_eq!(report.skipped, 1);
+    assert_eq!(report.received(), 4);
+    assert_eq!(report.total, 3);
+    assert_eq!(report.inserted, 3);
+    assert_eq!(db.get_all_stations().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn failed_catalogue_page_keeps_the_stations_already_stored() {
+    let server = MockServer::start();
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    let first_page = format!(
+        r#"{{
+            "type": "FeatureCollection",
+            "numberMatched": 4,
+            "features": [{}, {}],
+            "links": [{{"rel": "next", "href": "{}"}}]
+        }}"#,
+        station_feature(1, "FIRST", &today),
+        station_feature(2, "SECOND", &today),
+        server.url("/collections/climate-stations/items?limit=2&offset=2"),
+    );
+    server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items")
+            .query_param("offset", "0");
+        then.status(200).body(first_page);
+    });
+    let second = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items")
+            .query_param("offset", "2");
+        then.status(503);
+    });
+
+    let db = build_db();
+    let aggregator = Aggregator::with_options(
+        &db,
+        AggregatorOptions {
+            stations_page_size: 2,
+            ..options(&server)
+        },
+    );
+    let error = aggregator.fetch_and_store_stations().await.unwrap_err();
+
+    second.assert_hits(3);
+    assert!(error.to_string().contains("after storing 2 stations from 1 pages"));
+    // The first page was stored as it arrived, not discarded with the failed one
+    assert_eq!(db.get_all_stations().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn fetch_and_store_stations_falls_back_to_offset_paging() {
+    let server = MockServer::start();
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    let full_page = format!(
+        r#"{{"type": "FeatureCollection", "features": [{}, {}]}}"#,
+        station_feature(1, "FIRST", &today),
+        station_feature(2, "SECOND", &today),
+    );
+    let first = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items")
+            .query_param("limit", "2")
+            .query_param("offset", "0");
+        then.status(200).body(full_page);
+    });
+    let last = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items")
+            .query_param("limit", "2")
+            .query_param("offset", "2");
+        then.status(200)
+            .body(r#"{"type": "FeatureCollection", "features": []}"#);
+    });
+
+    let db = build_db();
+    let aggregator = Aggregator::with_options(
+        &db,
+        AggregatorOptions {
+            stations_page_size: 2,
+            ..options(&server)
+        },
+    );
+    let report = aggregator.fetch_and_store_stations().await.unwrap();
+
+    first.assert();
+    last.assert();
+    assert_eq!(report.pages, 2);
+    assert_eq!(report.number_matched, None);
+    assert_eq!(report.inserted, 2);
+}
+
+#[tokio::test]
+async fn fetch_and_store_climate_data_from_mock_bulk_csv() {
+    let server = MockServer::start();
+    let year = Utc::now().year() - 1;
+
+    let spring = server.mock(|when, then| {
+        when.method("GET")
+            .path("/climate_data/bulk_data_e.html")
+            .query_param("stationID", "4607")
+            .query_param("Year", year.to_string())
+            .query_param("Month", "3")
+            .query_param("timeframe", "2");
+        then.status(200)
+            .body(daily_csv(&month_days(year, 3, 20, 2.0, 10.0)));
+    });
+    let fall = server.mock(|when, then| {
+        when.method("GET")
+            .path("/climate_data/bulk_data_e.html")
+            .query_param("stationID", "4607")
+            .query_param("Year", year.to_string())
+            .query_param("Month", "10")
+            .query_param("timeframe", "2");
+        then.status(200)
+            .body(daily_csv(&month_days(year, 10, 14, 12.0, 3.0)));
+    });
+    let other_months = server.mock(|when, then| {
+        when.method("GET").path("/climate_data/bulk_data_e.html");
+        then.status(200).body(CSV_HEADER);
+    });
+
+    let db = build_db();
+    db.insert_station(4607, &"TORONTO CITY".to_string(), -79.4, 43.67, None, None)
+        .unwrap();
+    let aggregator = Aggregator::with_options(&db, options(&server));
+    let report = aggregator
+        .fetch_and_store_climate_data(4607, "TORONTO CITY")
+        .await
+        .unwrap();
+    assert!(report.complete);
+    assert!(report.failed_months.is_empty());
+    assert_eq!(report.months_fetched, report.months_requested);
+
+    spring.assert();
+    fall.assert();
+    assert!(other_months.hits() > 0);
+
+    let march = db
+        .get_daily_observations(4607, &format!("{}-03-01", year), &format!("{}-03-31", year))
+        .unwrap();
+    assert_eq!(march.len(), 31);
+    assert_eq!(march[0].mean_temp, Some(2.0));
+    assert_eq!(march[0].min_temp, Some(-2.0));
+
+    let data = db.get_data_by_station(4607).unwrap();
+    assert_eq!(data.len(), 1);
+    assert!(data[0].switch_to_summer.as_deref().unwrap().contains("-03-2"));
+    assert!(data[0].switch_to_winter.as_deref().unwrap().contains("-10-1"));
+    // Only March and October reported, so both season windows are mostly empty
+    let spring_completeness = data[0].spring_completeness.unwrap();
+    assert!((spring_completeness.valid_pct - 31.0 * 100.0 / 180.0).abs() < 1e-9);
+    assert!(data[0].fall_completeness.unwrap().valid_pct < 20.0);
+
+    // Closed months are recorded, so a second run only fetches still-open months
+    let complete = db.get_complete_months(4607).unwrap();
+    assert!(complete.contains(&(year, 3)));
+    assert!(complete.contains(&(year, 12)));
+
+    let hits_before = other_months.hits();
+    aggregator
+        .fetch_and_store_climate_data(4607, "TORONTO CITY")
+        .await
+        .unwrap();
+    spring.assert_hits(1);
+    fall.assert_hits(1);
+    assert!(other_months.hits() - hits_before <= 2);
+}
+
+#[tokio::test]
+async fn refresh_results_are_served_without_restart() {
+    let server = MockServer::start();
+    let year = Utc::now().year() - 1;
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    let catalogue = format!(
+        r#"{{"type": "FeatureCollection", "features": [{}]}}"#,
+        station_feature(4607, "TORONTO CITY", &today),
+    );
+    server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items");
+        then.status(200).body(catalogue);
+    });
+    // A complete year, so both seasons pass the default completeness threshold
+    for month in 1..=12u32 {
+        let days = match month {
+            1..=3 | 11..=12 => month_days(year, month, 31, 0.0, 0.0),
+            4 => month_days(year, 4, 10, 2.0, 10.0),
+            10 => month_days(year, 10, 14, 12.0, 3.0),
+            _ => month_days(year, month, 31, 15.0, 15.0),
+        };
+        server.mock(|when, then| {
+            when.method("GET")
+                .path("/climate_data/bulk_data_e.html")
+                .query_param("Year", year.to_string())
+                .query_param("Month", month.to_string());
+            then.status(200).body(daily_csv(&days));
+        });
+    }
+    server.mock(|when, then| {
+        when.method("GET").path("/climate_data/bulk_data_e.html");
+        then.status(200).body(CSV_HEADER);
+    });
+
+    let db = Arc::new(build_db());
+    let app = create_router(app_state(&db, None));
+    let uri = "/api/optimal-dates?latitude=43.67&longitude=-79.4&num_stations=1";
+
+    let (status, before) = get_json(&app, uri, None).await;
+    assert_eq!(status, 200);
+    assert_eq!(before["stations_analyzed"], 0);
+    assert!(before["switch_to_summer"].is_null());
+
+    let refresher = Refresher::new(Arc::clone(&db), options(&server), false);
+    let report = refresher.try_run().await.unwrap();
+    assert_eq!(report.stations.as_ref().unwrap().inserted, 1);
+    assert_eq!(report.stations_updated, 1);
+    assert!(!refresher.is_running());
+
+    let (status, after) = get_json(&app, uri, None).await;
+    assert_eq!(status, 200);
+    assert_eq!(after["stations_analyzed"], 1);
+    assert_eq!(after["switch_to_summer"], "April 11");
+    assert_eq!(after["switch_to_winter"], "October 14");
+    let station = &after["stations"]["list"][0];
+    assert_eq!(station["province"], "ON");
+    assert_eq!(station["tc_id"], "XTO");
+    assert_eq!(station["elevation_m"], 112.5);
+
+    // At the station's own elevation the dates are unchanged
+    let (status, level) = get_json(&app, &format!("{}&elevation=112.5", uri), None).await;
+    assert_eq!(status, 200);
+    assert_eq!(level["elevation_m"], 112.5);
+    assert_eq!(level["switch_to_summer"], "April 11");
+    let (status, _) = get_json(&app, &format!("{}&elevation=9000", uri), None).await;
+    assert_eq!(status, 400);
+}
+
+#[tokio::test]
+async fn api_uses_the_analyzer_settings_the_server_was_started_with() {
+    let db = Arc::new(build_db());
+    db.insert_station(4607, &"TORONTO CITY".to_string(), -79.4, 43.67, None, None)
+        .unwrap();
+    db.insert_data(4607, 2024, Some("2024-04-20"), Some("2024-10-10"))
+        .unwrap();
+    let patchy = SeasonCompleteness {
+        valid_pct: 60.0,
+        flagged_days: 0,
+    };
+    db.set_season_completeness(4607, 2024, &patchy, &patchy)
+        .unwrap();
+    let uri = "/api/optimal-dates?latitude=43.67&longitude=-79.4&num_stations=1";
+
+    // The default 80% threshold leaves the patchy year out
+    let app = create_router(app_state(&db, None));
+    let (status, strict) = get_json(&app, uri, None).await;
+    assert_eq!(status, 200);
+    assert!(strict["switch_to_summer"].is_null());
+
+    // As with `--min-valid-pct 50` on the command line
+    let app = create_router(AppState {
+        analyzer_options: AnalyzerOptions {
+            min_valid_pct: 50.0,
+            ..AnalyzerOptions::default()
+        },
+        ..app_state(&db, None)
+    });
+    let (status, lenient) = get_json(&app, uri, None).await;
+    assert_eq!(status, 200);
+    assert_eq!(lenient["switch_to_summer"], "April 20");
+    assert_eq!(lenient["switch_to_winter"], "October 10");
+}
+
+#[tokio::test]
+async fn ingest_runs_are_recorded_and_listed_for_admins() {
+    let server = MockServer::start();
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    let catalogue = format!(
+        r#"{{"type": "FeatureCollection", "features": [{}, {}]}}"#,
+        station_feature(4607, "TORONTO CITY", &today),
+        station_feature(5051, "CLOSED STATION", "2001-06-30 00:00:00"),
+    );
+    server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items");
+        then.status(200).body(catalogue);
+    });
+    server.mock(|when, then| {
+        when.method("GET").path("/climate_data/bulk_data_e.html");
+        then.status(503);
+    });
+
+    let db = Arc::new(build_db());
+    let aggregator = Aggregator::with_options(&db, options(&server));
+    let report = aggregator.update_all(false, RunTrigger::Cli, false).await;
+    assert_eq!(report.status(), "partial");
+    assert_eq!(report.incomplete_stations, 1);
+    assert!(report.months_failed > 0);
+    assert_eq!(report.error_count, report.months_failed);
+
+    let uri = "/api/admin/ingest-runs?limit=5";
+    // Without a configured token the endpoint is disabled, not open
+    let unconfigured = create_router(app_state(&db, None));
+    let (status, json) = get_json(&unconfigured, uri, None).await;
+    assert_eq!(status, 403);
+    assert_eq!(json["error"]["code"], "ADMIN_DISABLED");
+    assert_eq!(get_json(&unconfigured, uri, Some("anything")).await.0, 403);
+
+    let app = create_router(app_state(&db, Some("s3cret")));
+    assert_eq!(get_json(&app, uri, None).await.0, 401);
+    assert_eq!(get_json(&app, uri, Some("wrong")).await.0, 401);
+    assert_eq!(
+        get_json(&app, "/api/admin/ingest-runs?limit=0", Some("s3cret")).await.0,
+        400
+    );
+
+    let (status, json) = get_json(&app, uri, Some("s3cret")).await;
+    assert_eq!(status, 200);
+    let runs = json["runs"].as_array().unwrap();
+    assert_eq!(runs.len(), 1);
+    let run = &runs[0];
+    assert_eq!(run["id"], report.run_id.unwrap());
+    assert_eq!(run["trigger"], "cli");
+    assert_eq!(run["status"], "partial");
+    assert!(run["finished_at"].is_string());
+    assert_eq!(run["sources"]["daily_source"], "csv");
+    assert_eq!(run["stations"]["seen"], 2);
+    assert_eq!(run["stations"]["inserted"], 1);
+    assert_eq!(run["stations"]["inactive"], 1);
+    assert_eq!(run["stations"]["incomplete"], 1);
+    assert_eq!(run["months"]["fetched"], 0);
+    assert_eq!(run["months"]["failed"], report.months_failed as u64);
+    assert!(run["errors"][0].as_str().unwrap().starts_with("TORONTO CITY "));
+}
+
+#[tokio::test]
+async fn dry_run_reports_stations_dropped_from_the_catalogue() {
+    let server = MockServer::start();
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    let catalogue = |features: &[String]| {
+        format!(
+            r#"{{"type": "FeatureCollection", "features": [{}]}}"#,
+            features.join(",")
+        )
+    };
+    let mut full_catalogue = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items");
+        then.status(200).body(catalogue(&[
+            station_feature(4607, "TORONTO CITY", &today),
+            station_feature(5051, "TORONTO ISLAND", &today),
+        ]));
+    });
+    server.mock(|when, then| {
+        when.method("GET").path("/climate_data/bulk_data_e.html");
+        then.status(200).body(CSV_HEADER);
+    });
+
+    let path =
+        std::env::temp_dir().join(format!("tireswap-dry-run-live-{}.db", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    {
+        let db = Database::new(&path).unwrap();
+        db.initialize_schema().unwrap();
+        let options = AggregatorOptions {
+            ingest: IngestConfig {
+                history_years: 1,
+                ..IngestConfig::default()
+            },
+            ..options(&server)
+        };
+        let report = Aggregator::with_options(&db, options)
+            .update_all(false, RunTrigger::Cli, false)
+            .await;
+        assert_eq!(report.status(), "ok");
+        assert_eq!(db.get_all_stations().unwrap().len(), 2);
+    }
+
+    // The next catalogue no longer lists the island station
+    full_catalogue.delete();
+    server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items");
+        then.status(200)
+            .body(catalogue(&[station_feature(4607, "TORONTO CITY", &today)]));
+    });
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_backend"))
+        .args(["--update-db", "--dry-run", "--history-years", "1"])
+        .args(["--requests-per-second", "0"])
+        .args(["--db-path", path.to_str().unwrap()])
+        .args(["--stations-base-url", &server.url("")])
+        .args(["--climate-base-url", &server.url("")])
+        .output()
+        .unwrap();
+    let stations_after = Database::new(&path).unwrap().get_all_stations().unwrap().len();
+    let _ = std::fs::remove_file(&path);
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert_eq!(output.status.code(), Some(3), "{}\n{}", stdout, stderr);
+    assert!(stdout.contains("Stations removed: 1"), "{}", stdout);
+    assert!(stdout.contains("  - TORONTO ISLAND (5051)"), "{}", stdout);
+    assert!(stderr.contains("1 stations removed (allowed: 0)"), "{}", stderr);
+    // The live database is left as it was
+    assert_eq!(stations_after, 2);
+}
+
+#[tokio::test]
+async fn fetch_and_store_climate_data_from_mock_climate_daily() {
+    let server = MockServer::start();
+    let year = Utc::now().year() - 1;
+    let start_year = Utc::now().year() - 5;
+    let today = Utc::now().format("%Y-%m-%d").to_string();
+
+    let first_page = format!(
+        r#"{{
+            "type": "FeatureCollection",
+            "numberMatched": 62,
+            "features": [{}],
+            "links": [{{"rel": "next", "href": "{}"}}]
+        }}"#,
+        climate_daily_features(&month_days(year, 3, 20, 2.0, 10.0)),
+        server.url("/collections/climate-daily/items?STN_ID=4607&page=2"),
+    );
+    let second_page = format!(
+        r#"{{"type": "FeatureCollection", "numberMatched": 62, "features": [{}], "links": []}}"#,
+        climate_daily_features(&month_days(year, 10, 14, 12.0, 3.0)),
+    );
+    // The whole missing window is requested as one date range
+    let first = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-daily/items")
+            .query_param("STN_ID", "4607")
+            .query_param("offset", "0")
+            .query_param("datetime", format!("{}-01-01/{}", start_year, today));
+        then.status(200).body(first_page);
+    });
+    let second = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-daily/items")
+            .query_param("page", "2");
+        then.status(200).body(second_page);
+    });
+    let bulk_csv = server.mock(|when, then| {
+        when.method("GET").path("/climate_data/bulk_data_e.html");
+        then.status(500);
+    });
+
+    let db = build_db();
+    db.insert_station(4607, &"TORONTO CITY".to_string(), -79.4, 43.67, None, None)
+        .unwrap();
+    let aggregator = Aggregator::with_options(
+        &db,
+        AggregatorOptions {
+            daily_source: DailySource::ClimateDaily,
+            ..options(&server)
+        },
+    );
+    let report = aggregator
+        .fetch_and_store_climate_data(4607, "TORONTO CITY")
+        .await
+        .unwrap();
+
+    first.assert();
+    second.assert();
+    bulk_csv.assert_hits(0);
+    assert!(report.complete);
+    assert!(report.failed_months.is_empty());
+    assert_eq!(report.months_fetched, report.months_requested);
+
+    let october = db
+        .get_daily_observations(4607, &format!("{}-10-01", year), &format!("{}-10-31", year))
+        .unwrap();
+    assert_eq!(october.len(), 31);
+    assert_eq!(october[0].max_temp, Some(16.0));
+
+    // Same switch-date logic as the bulk CSV path
+    let data = db.get_data_by_station(4607).unwrap();
+    assert_eq!(data.len(), 1);
+    assert!(data[0].switch_to_summer.as_deref().unwrap().contains("-03-2"));
+    assert!(data[0].switch_to_winter.as_deref().unwrap().contains("-10-1"));
+
+    // Months without any features are still recorded once closed
+    let complete = db.get_complete_months(4607).unwrap();
+    assert!(complete.contains(&(year, 3)));
+    assert!(complete.contains(&(year, 7)));
+}
+
+#[tokio::test]
+async fn climate_daily_pages_by_offset_and_stops_at_a_repeated_next_link() {
+    let server = MockServer::start();
+    let year = Utc::now().year() - 1;
+    let march = month_days(year, 3, 20, 2.0, 10.0);
+    let october = month_days(year, 10, 14, 12.0, 3.0);
+
+    // Full pages without `next` links are followed by offset
+    let page = |days: &[(NaiveDate, f64)], links: &str| {
+        format!(
+            r#"{{"type": "FeatureCollection", "features": [{}], "links": [{}]}}"#,
+            climate_daily_features(days),
+            links
+        )
+    };
+    let loop_link = format!(
+        r#"{{"rel": "next", "href": "{}"}}"#,
+        server.url("/collections/climate-daily/items?STN_ID=4607&page=3")
+    );
+    let first = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-daily/items")
+            .query_param("offset", "0")
+            .query_param("limit", "31");
+        then.status(200).body(page(&march, ""));
+    });
+    let second = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-daily/items")
+            .query_param("offset", "31");
+        then.status(200).body(page(&october, &loop_link));
+    });
+    // The last page links back to itself; it is requested only once
+    let third = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-daily/items")
+            .query_param("page", "3");
+        then.status(200).body(page(&[], &loop_link));
+    });
+
+    let db = build_db();
+    db.insert_station(4607, &"TORONTO CITY".to_string(), -79.4, 43.67, None, None)
+        .unwrap();
+    let aggregator = Aggregator::with_options(
+        &db,
+        AggregatorOptions {
+            daily_source: DailySource::ClimateDaily,
+            daily_page_size: 31,
+            ..options(&server)
+        },
+    );
+    let report = aggregator
+        .fetch_and_store_climate_data(4607, "TORONTO CITY")
+        .await
+        .unwrap();
+
+    first.assert();
+    second.assert();
+    third.assert();
+    assert!(report.failed_months.is_empty());
+    let observations = db
+        .get_daily_observations(4607, &format!("{}-01-01", year), &format!("{}-12-31", year))
+        .unwrap();
+    assert_eq!(observations.len(), 62);
+}
+
+#[tokio::test]
+async fn station_filter_limits_catalogue_and_climate_fetch() {
+    let server = MockServer::start();
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    let catalogue = format!(
+        r#"{{"type": "FeatureCollection", "features": [{}, {}]}}"#,
+        station_feature(4607, "TORONTO CITY", &today),
+        // A server ignoring the filter still only gets the Ontario station stored
+        station_feature(5051, "MONTREAL", &today).replace("\"ON\"", "\"QC\""),
+    );
+    let stations_mock = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items")
+            .query_param("PROV_STATE_TERR_CODE", "ON");
+        then.status(200).body(catalogue);
+    });
+    let toronto = server.mock(|when, then| {
+        when.method("GET")
+            .path("/climate_data/bulk_data_e.html")
+            .query_param("stationID", "4607");
+        then.status(200).body(CSV_HEADER);
+    });
+    let others = server.mock(|when, then| {
+        when.method("GET").path("/climate_data/bulk_data_e.html");
+        then.status(200).body(CSV_HEADER);
+    });
+
+    let db = build_db();
+    // Already stored from an earlier run, but outside the filter
+    db.insert_station(6158, &"OTTAWA".to_string(), -75.7, 45.4, None, None)
+        .unwrap();
+    let aggregator = Aggregator::with_options(
+        &db,
+        AggregatorOptions {
+            filter: StationFilter {
+                provinces: vec!["ON".to_string()],
+                bbox: Some("-80,43,-79,44".parse().unwrap()),
+                ..StationFilter::default()
+            },
+            ..options(&server)
+        },
+    );
+    let report = aggregator.update_all(false, RunTrigger::Cli, false).await;
+
+    stations_mock.assert();
+    let stations = report.stations.unwrap();
+    assert_eq!((stations.inserted, stations.excluded), (1, 1));
+    assert_eq!(report.stations_updated, 1);
+    assert!(toronto.hits() > 0);
+    assert_eq!(others.hits(), 0);
+    let mut ids: Vec<i64> = db.get_all_stations().unwrap().iter().map(|s| s.id).collect();
+    ids.sort();
+    assert_eq!(ids, vec![4607, 6158]);
+}
+
+#[tokio::test]
+async fn resume_skips_stations_finished_before_the_interruption() {
+    let server = MockServer::start();
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    let catalogue = format!(
+        r#"{{"type": "FeatureCollection", "features": [{}, {}]}}"#,
+        station_feature(4607, "TORONTO CITY", &today),
+        station_feature(5051, "TORONTO ISLAND", &today),
+    );
+    server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items");
+        then.status(200).body(catalogue);
+    });
+    let finished = server.mock(|when, then| {
+        when.method("GET")
+            .path("/climate_data/bulk_data_e.html")
+            .query_param("stationID", "4607");
+        then.status(200).body(CSV_HEADER);
+    });
+    let pending = server.mock(|when, then| {
+        when.method("GET")
+            .path("/climate_data/bulk_data_e.html")
+            .query_param("stationID", "5051");
+        then.status(200).body(CSV_HEADER);
+    });
+
+    // A run that was killed after finishing the first station
+    let db = build_db();
+    let now = Utc::now().naive_utc();
+    let interrupted = db
+        .start_ingest_run("cli", &server.url(""), &server.url(""), "csv", now)
+        .unwrap();
+    db.mark_station_checkpoint(interrupted, 4607, "daily", now).unwrap();
+
+    let aggregator = Aggregator::with_options(&db, options(&server));
+    let report = aggregator.update_all(false, RunTrigger::Cli, true).await;
+
+    assert_eq!(report.run_id, Some(interrupted));
+    assert!(report.resumed);
+    assert_eq!(report.stations_resumed, 1);
+    assert_eq!(report.stations_updated, 1);
+    assert_eq!(finished.hits(), 0);
+    assert!(pending.hits() > 0);
+
+    // The resumed run is now finished, and the next --resume starts afresh
+    let runs = db.get_recent_ingest_runs(10).unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].status, "ok");
+    assert_eq!(runs[0].stations_resumed, 1);
+    assert_eq!(db.get_interrupted_ingest_run().unwrap(), None);
+    let report = aggregator.update_all(false, RunTrigger::Cli, true).await;
+    assert!(!report.resumed);
+    assert_eq!(report.stations_resumed, 0);
+    assert!(finished.hits() > 0);
+}
+
+/// Every row of every table in a database file, sorted, keyed by table name
+fn table_contents(path: &std::path::Path) -> Vec<(String, Vec<String>)> {
+    let conn = rusqlite::Connection::open(path).unwrap();
+    let tables: Vec<String> = conn
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    tables
+        .into_iter()
+        .map(|table| {
+            let mut stmt = conn.prepare(&format!("SELECT * FROM {}", table)).unwrap();
+            let columns = stmt.column_count();
+            let mut rows: Vec<String> = stmt
+                .query_map([], |row| {
+                    (0..columns)
+                        .map(|i| Ok(format!("{:?}", row.get::<_, rusqlite::types::Value>(i)?)))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map(|values| values.join("|"))
+                })
+                .unwrap()
+                .collect::<Result<_, _>>()
+                .unwrap();
+            rows.sort();
+            (table, rows)
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn replayed_ingest_rebuilds_the_recorded_database_without_network() {
+    let server = MockServer::start();
+    let year = Utc::now().year() - 1;
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    let catalogue = format!(
+        r#"{{"type": "FeatureCollection", "features": [{}]}}"#,
+        station_feature(4607, "TORONTO CITY", &today),
+    );
+    let mocks = [
+        server.mock(|when, then| {
+            when.method("GET")
+                .path("/collections/climate-stations/items");
+            then.status(200).body(catalogue);
+        }),
+        server.mock(|when, then| {
+            when.method("GET")
+                .path("/climate_data/bulk_data_e.html")
+                .query_param("Year", year.to_string())
+                .query_param("Month", "3");
+            then.status(200)
+                .body(daily_csv(&month_days(year, 3, 20, 2.0, 10.0)));
+        }),
+        server.mock(|when, then| {
+            when.method("GET").path("/climate_data/bulk_data_e.html");
+            then.status(200).body(CSV_HEADER);
+        }),
+    ];
+
+    let cache_dir = std::env::temp_dir().join(format!("tireswap-replay-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&cache_dir);
+    std::fs::create_dir_all(&cache_dir).unwrap();
+    let file_db = |name: &str| {
+        let db = Database::new(cache_dir.join(name)).unwrap();
+        db.initialize_schema().unwrap();
+        db
+    };
+    let recorded = file_db("recorded.db");
+    let report = Aggregator::with_options(
+        &recorded,
+        AggregatorOptions {
+            http_cache: Some(CacheMode::Record(cache_dir.clone())),
+            ..options(&server)
+        },
+    )
+    .update_all(false, RunTrigger::Cli, false)
+    .await;
+    assert_eq!(report.status(), "ok");
+
+    // The server no longer answers anything; every response comes from the cache
+    for mut mock in mocks {
+        mock.delete();
+    }
+    let as_of = backend::cache::ResponseCache::new(&cache_dir)
+        .read_manifest()
+        .unwrap();
+    let replayed = file_db("replayed.db");
+    let report = Aggregator::with_options(
+        &replayed,
+        AggregatorOptions {
+            http_cache: Some(CacheMode::Replay(cache_dir.clone())),
+            as_of: Some(as_of),
+            ..options(&server)
+        },
+    )
+    .update_all(false, RunTrigger::Cli, false)
+    .await;
+    assert_eq!(report.status(), "ok");
+    assert_eq!(report.stations_updated, 1);
+
+    let observations = |db: &Database| {
+        db.get_daily_observations(4607, "1900-01-01", "2999-12-31")
+            .unwrap()
+    };
+    assert_eq!(observations(&replayed).len(), 31);
+    assert_eq!(observations(&replayed), observations(&recorded));
+    let switch_dates = |db: &Database| {
+        db.get_data_by_station(4607)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.year, d.switch_to_summer, d.switch_to_winter))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(switch_dates(&replayed), switch_dates(&recorded));
+    // Down to the run history and every fetched-at timestamp
+    assert_eq!(
+        table_contents(&cache_dir.join("replayed.db")),
+        table_contents(&cache_dir.join("recorded.db"))
+    );
+
+    // A request the cache never saw fails instead of reaching the network
+    let missing = build_db();
+    missing
+        .insert_station(5051, &"TORONTO ISLAND".to_string(), -79.4, 43.6, None, None)
+        .unwrap();
+    let result = Aggregator::with_options(
+        &missing,
+        AggregatorOptions {
+            http_cache: Some(CacheMode::Replay(cache_dir.clone())),
+            as_of: Some(as_of),
+            ..options(&server)
+        },
+    )
+    .fetch_and_store_climate_data(5051, "TORONTO ISLAND")
+    .await
+    .unwrap();
+    assert!(!result.complete);
+    assert!(result.failed_months[0].error.contains("not in the replay cache"));
+
+    std::fs::remove_dir_all(&cache_dir).unwrap();
+}
+
+/// `climate-normals` features of the mean temperature element, one per (month, value)
+/// In-memory weather source with one station whose seasons switch on fixed days
+struct FakeSource {
+    year: i32,
+    /// (start, end) of every `daily_records` call
+    requests: std::sync::Mutex<Vec<(NaiveDate, NaiveDate)>>,
+}
+
+impl WeatherSource for FakeSource {
+    fn name(&self) -> &'static str {
+        "fake"
+    }
+
+    fn list_stations<'a>(
+        &'a self,
+        _filter: &'a StationFilter,
+    ) -> BoxStream<'a, Result<StationPage, String>> {
+        let station = SourceStation {
+            id: 9001,
+            name: "FAKE STATION".to_string(),
+            lon_x: -79.4,
+            lat_y: 43.67,
+            dly_first_date: Some("2000-01-01 00:00:00".to_string()),
+            dly_last_date: Some(Utc::now().format("%Y-%m-%d 00:00:00").to_string()),
+            ..SourceStation::default()
+        };
+        stream::iter([Ok(StationPage {
+            stations: vec![station],
+            number_matched: Some(1),
+            skipped: 0,
+        })])
+        .boxed()
+    }
+
+    fn daily_records<'a>(
+        &'a self,
+        station_id: i64,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> BoxStream<'a, MonthRecords> {
+        self.requests.lock().unwrap().push((start, end));
+        let mut months = Vec::new();
+        let mut first = start;
+        while first <= end {
+            let days = match (first.year(), first.month()) {
+                (year, 3) if year == self.year => month_days(year, 3, 20, 2.0, 10.0),
+                (year, 10) if year == self.year => month_days(year, 10, 14, 12.0, 3.0),
+                (year, month) => {
+                    let mean = if (4..=9).contains(&month) { 15.0 } else { 0.0 };
+                    month_days(year, month, 31, mean, mean)
+                }
+            };
+            let records = days
+                .into_iter()
+                .filter(|(date, _)| *date <= end)
+                .map(|(date, mean)| DailyObservation {
+                    station_id,
+                    date: date.format("%Y-%m-%d").to_string(),
+                    mean_temp: Some(mean),
+                    ..DailyObservation::default()
+                })
+                .collect();
+            months.push(MonthRecords {
+                year: first.year(),
+                month: first.month(),
+                records: Ok(records),
+            });
+            first = first.checked_add_months(Months::new(1)).unwrap();
+        }
+        stream::iter(months).boxed()
+    }
+}
+
+#[tokio::test]
+async fn custom_weather_source_drives_the_ingest_without_network() {
+    let year = Utc::now().year() - 1;
+    let source = Arc::new(FakeSource {
+        year,
+        requests: std::sync::Mutex::new(Vec::new()),
+    });
+
+    let db = build_db();
+    let aggregator = Aggregator::with_source(
+        &db,
+        AggregatorOptions {
+            // Nothing listens here; every request would fail
+            stations_base_url: "http://127.0.0.1:9".to_string(),
+            climate_base_url: "http://127.0.0.1:9".to_string(),
+            ..AggregatorOptions::default()
+        },
+        source.clone(),
+    );
+    assert_eq!(aggregator.source.name(), "fake");
+    let report = aggregator.update_all(false, RunTrigger::Cli, false).await;
+
+    assert_eq!(report.stations.unwrap().inserted, 1);
+    assert_eq!((report.stations_updated, report.months_failed), (1, 0));
+    assert!(report.errors.is_empty(), "{:?}", report.errors);
+    // Consecutive months are asked for as one range
+    assert_eq!(source.requests.lock().unwrap().len(), 1);
+
+    let stations = db.get_all_stations().unwrap();
+    assert_eq!(stations[0].name, "FAKE STATION");
+    let data = db.get_data_by_station(9001).unwrap();
+    let last_year = data.iter().find(|d| d.year == year as i64).unwrap();
+    assert_eq!(
+        last_year.switch_to_summer.as_deref(),
+        Some(format!("{}-03-21", year).as_str())
+    );
+    assert_eq!(
+        last_year.switch_to_winter.as_deref(),
+        Some(format!("{}-10-14", year).as_str())
+    );
+}
+
+fn climate_normal_features(values: &[(u32, f64)], last_year: i32) -> String {
+    values
+        .iter()
+        .map(|(month, value)| {
+            format!(
+                r#"{{
+                    "type": "Feature",
+                    "properties": {{
+                        "STN_ID": 4607,
+                        "NORMAL_ID": 1,
+                        "E_NORMAL_ELEMENT_NAME": "Mean daily temperature deg C",
+                        "MONTH": {},
+                        "VALUE": {:.1},
+                        "NORMAL_CODE": "A",
+                        "FIRST_YEAR": {},
+                        "LAST_YEAR": {}
+                    }}
+                }}"#,
+                month,
+                value,
+                last_year - 29,
+                last_year
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+#[tokio::test]
+async fn climate_normals_are_ingested_and_drive_the_normals_criterion() {
+    let server = MockServer::start();
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    let catalogue = format!(
+        r#"{{"type": "FeatureCollection", "features": [{}]}}"#,
+        station_feature(4607, "TORONTO CITY", &today),
+    );
+    server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items");
+        then.status(200).body(catalogue);
+    });
+    server.mock(|when, then| {
+        when.method("GET").path("/climate_data/bulk_data_e.html");
+        then.status(200).body(CSV_HEADER);
+    });
+
+    let monthly: Vec<(u32, f64)> = [
+        -5.5, -4.5, 0.1, 6.7, 12.9, 18.4, 21.5, 20.6, 16.4, 9.9, 4.2, -1.7,
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, value)| (i as u32 + 1, value))
+    .collect();
+    // The first page holds an older period and the annual value; the newer period wins
+    let first_page = format!(
+        r#"{{"type": "FeatureCollection", "features": [{}, {}],
+            "links": [{{"rel": "next", "href": "{}"}}]}}"#,
+        climate_normal_features(&[(4, 3.0), (13, 7.9)], 2010),
+        climate_normal_features(&monthly[..6], 2020),
+        server.url("/collections/climate-normals/items?page=2"),
+    );
+    let second_page = format!(
+        r#"{{"type": "FeatureCollection", "features": [{}]}}"#,
+        climate_normal_features(&monthly[6..], 2020),
+    );
+    let first = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-normals/items")
+            .query_param("STN_ID", "4607")
+            .query_param("NORMAL_ID", "1");
+        then.status(200).body(first_page);
+    });
+    let second = server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-normals/items")
+            .query_param("page", "2");
+        then.status(200).body(second_page);
+    });
+
+    let db = build_db();
+    let aggregator = Aggregator::with_options(
+        &db,
+        AggregatorOptions {
+            normals: true,
+            ..options(&server)
+        },
+    );
+    let report = aggregator.update_all(false, RunTrigger::Cli, false).await;
+
+    assert_eq!(report.status(), "ok");
+    assert_eq!(report.normals_stored, 1);
+    first.assert();
+    second.assert();
+    let normals = db.get_climate_normals(4607).unwrap();
+    assert_eq!(normals.len(), 12);
+    assert_eq!(normals[3].mean_temp, 6.7);
+    assert_eq!((normals[3].first_year, normals[3].last_year), (Some(1991), Some(2020)));
+
+    let options = AnalyzerOptions {
+        criterion: TransitionCriterion::Normals,
+        ..AnalyzerOptions::default()
+    };
+    let rec = Analyzer::with_options(&db, options)
+        .unwrap()
+        .analyze(43.67, -79.4, 1)
+        .unwrap();
+    assert_eq!(rec.switch_to_summer.as_deref(), Some("April 17"));
+    assert_eq!(rec.switch_to_winter.as_deref(), Some("October 31"));
+}
+
+#[tokio::test]
+async fn ingest_requests_identify_the_client_and_respect_the_rate_limit() {
+    let server = MockServer::start();
+    let bulk = server.mock(|when, then| {
+        when.method("GET")
+            .path("/climate_data/bulk_data_e.html")
+            .header("user-agent", DEFAULT_USER_AGENT);
+        then.status(200).body(CSV_HEADER);
+    });
+
+    let db = build_db();
+    db.insert_station(4607, &"TORONTO CITY".to_string(), -79.4, 43.67, None, None)
+        .unwrap();
+    let aggregator = Aggregator::with_options(
+        &db,
+        AggregatorOptions {
+            limits: RequestLimits {
+                max_concurrent: 2,
+                requests_per_second: Some(40.0),
+            },
+            ingest: IngestConfig {
+                history_years: 1,
+                ..IngestConfig::default()
+            },
+            ..options(&server)
+        },
+    );
+    let started = std::time::Instant::now();
+    let report = aggregator
+        .fetch_and_store_climate_data(4607, "TORONTO CITY")
+        .await
+        .unwrap();
+
+    // Every month matched the User-Agent; none were rejected by the mock
+    assert!(report.failed_months.is_empty());
+    bulk.assert_hits(report.months_requested);
+    // All months are requested at once, but starts are spaced 25 ms apart
+    let spacing = std::time::Duration::from_millis(25);
+    assert!(started.elapsed() >= spacing * (report.months_requested as u32 - 1));
+}
+
+#[tokio::test]
+async fn failed_months_are_retried_reported_and_mark_station_incomplete() {
+    let server = MockServer::start();
+    let year = Utc::now().year() - 1;
+
+    let unavailable = server.mock(|when, then| {
+        when.method("GET")
+            .path("/climate_data/bulk_data_e.html")
+            .query_param("Year", year.to_string())
+            .query_param("Month", "3");
+        then.status(503);
+    });
+    let not_found = server.mock(|when, then| {
+        when.method("GET")
+            .path("/climate_data/bulk_data_e.html")
+            .query_param("Year", year.to_string())
+            .query_param("Month", "4");
+        then.status(404);
+    });
+    let other_months = server.mock(|when, then| {
+        when.method("GET").path("/climate_data/bulk_data_e.html");
+        then.status(200)
+            .body(daily_csv(&month_days(year, 10, 14, 12.0, 3.0)));
+    });
+
+    let db = build_db();
+    db.insert_station(4607, &"TORONTO CITY".to_string(), -79.4, 43.67, None, None)
+        .unwrap();
+    let aggregator = Aggregator::with_options(
+        &db,
+        AggregatorOptions {
+            max_failed_months: 1,
+            ..options(&server)
+        },
+    );
+    let report = aggregator
+        .fetch_and_store_climate_data(4607, "TORONTO CITY")
+        .await
+        .unwrap();
+
+    // 5xx is retried up to the attempt limit, other 4xx fail immediately
+    unavailable.assert_hits(3);
+    not_found.assert_hits(1);
+    assert!(other_months.hits() > 0);
+
+    let mut failed: Vec<(i32, u32)> = report
+        .failed_months
+        .iter()
+        .map(|failed| (failed.year, failed.month))
+        .collect();
+    failed.sort();
+    assert_eq!(failed, vec![(year, 3), (year, 4)]);
+    assert!(report.failed_months[0].error.contains("status"));
+    assert_eq!(report.months_fetched, report.months_requested - 2);
+
+    // Too many gaps: no switch dates are stored and the station is flagged
+    assert!(!report.complete);
+    assert!(db.get_data_by_station(4607).unwrap().is_empty());
+    assert_eq!(db.get_incomplete_stations().unwrap(), vec![4607]);
+    assert!(!db.get_complete_months(4607).unwrap().contains(&(year, 3)));
+}
+
+#[tokio::test]
+async fn error_pages_served_with_status_200_fail_the_month() {
+    let server = MockServer::start();
+    let year = Utc::now().year() - 1;
+
+    let error_page = server.mock(|when, then| {
+        when.method("GET")
+            .path("/climate_data/bulk_data_e.html")
+            .query_param("Year", year.to_string())
+            .query_param("Month", "3");
+        then.status(200)
+            .body("<html><body>Service temporarily unavailable</body></html>");
+    });
+    let other_months = server.mock(|when, then| {
+        when.method("GET").path("/climate_data/bulk_data_e.html");
+        then.status(200).body(CSV_HEADER);
+    });
+
+    let db = build_db();
+    db.insert_station(4607, &"TORONTO CITY".to_string(), -79.4, 43.67, None, None)
+        .unwrap();
+    let aggregator = Aggregator::with_options(&db, options(&server));
+    let report = aggregator
+        .fetch_and_store_climate_data(4607, "TORONTO CITY")
+        .await
+        .unwrap();
+
+    // Retried like a 5xx response, then reported instead of stored as an empty month
+    error_page.assert_hits(3);
+    assert!(other_months.hits() > 0);
+    assert_eq!(report.failed_months.len(), 1);
+    assert_eq!(
+        (report.failed_months[0].year, report.failed_months[0].month),
+        (year, 3)
+    );
+    assert!(report.failed_months[0].error.contains("not a bulk CSV"));
+    let complete = db.get_complete_months(4607).unwrap();
+    assert!(!complete.contains(&(year, 3)));
+    assert!(complete.contains(&(year, 4)));
+}
+
+#[tokio::test]
+async fn fetch_and_store_hourly_data_from_mock_bulk_csv() {
+    let server = MockServer::start();
+    let year = Utc::now().year() - 1;
+    let hourly_csv = format!(
+        "\"Longitude (x)\",\"Latitude (y)\",\"Station Name\",\"Climate ID\",\"Date/Time (LST)\",\"Year\",\"Month\",\"Day\",\"Time (LST)\",\"Temp (°C)\",\"Temp Flag\",\"Dew Point Temp (°C)\",\"Dew Point Temp Flag\"\n\
+         \"-79.40\",\"43.67\",\"TORONTO CITY\",\"6158355\",\"{y}-01-15 06:00\",\"{y}\",\"01\",\"15\",\"06:00\",\"-7.5\",\"\",\"-9.0\",\"\"\n\
+         \"-79.40\",\"43.67\",\"TORONTO CITY\",\"6158355\",\"{y}-01-15 07:00\",\"{y}\",\"01\",\"15\",\"07:00\",\"\",\"M\",\"\",\"M\"\n",
+        y = year
+    );
+
+    let january = server.mock(|when, then| {
+        when.method("GET")
+            .path("/climate_data/bulk_data_e.html")
+            .query_param("Year", year.to_string())
+            .query_param("Month", "1")
+            .query_param("timeframe", "1");
+        then.status(200).body(hourly_csv);
+    });
+    let other_months = server.mock(|when, then| {
+        when.method("GET")
+            .path("/climate_data/bulk_data_e.html")
+            .query_param("timeframe", "1");
+        then.status(200).body("\"Date/Time (LST)\",\"Temp (°C)\"");
+    });
+
+    let db = build_db();
+    db.insert_station(4607, &"TORONTO CITY".to_string(), -79.4, 43.67, None, None)
+        .unwrap();
+    let aggregator = Aggregator::with_options(&db, options(&server));
+    let report = aggregator.fetch_and_store_hourly_data(4607).await.unwrap();
+
+    january.assert();
+    assert!(other_months.hits() > 0);
+    assert!(report.complete);
+    assert_eq!(report.months_fetched, report.months_requested);
+
+    let hours = db
+        .get_hourly_observations(4607, &format!("{}-01-15", year), &format!("{}-01-15", year))
+        .unwrap();
+    assert_eq!(hours.len(), 2);
+    assert_eq!(hours[0].temp, Some(-7.5));
+    assert_eq!(hours[0].dew_point, Some(-9.0));
+    assert_eq!(hours[1].temp_flag.as_deref(), Some("M"));
+
+    // Hourly months are tracked apart from the daily ones
+    assert!(db.get_complete_hourly_months(4607).unwrap().contains(&(year, 1)));
+    assert!(db.get_complete_months(4607).unwrap().is_empty());
+    assert!(db.get_data_by_station(4607).unwrap().is_empty());
+}
+
+#[test]
+fn import_csv_directory_without_network() {
+    let year = Utc::now().year() - 1;
+    let dir = std::env::temp_dir().join(format!("tireswap-import-{}", std::process::id()));
+    let nested = dir.join("2024-archive");
+    std::fs::create_dir_all(&nested).unwrap();
+    std::fs::write(
+        dir.join("en_climate_daily_ON_6158355_03_P1D.csv"),
+        daily_csv(&month_days(year, 3, 20, 2.0, 10.0)),
+    )
+    .unwrap();
+    std::fs::write(
+        nested.join("en_climate_daily_ON_6158355_10_P1D.CSV"),
+        daily_csv(&month_days(year, 10, 14, 12.0, 3.0)),
+    )
+    .unwrap();
+    std::fs::write(dir.join("README.txt"), "not a csv").unwrap();
+    // Not text; sorted between the two good files, which are both still imported
+    std::fs::write(dir.join("corrupt.csv"), [0xff, 0xfe, 0x00, 0x80]).unwrap();
+    std::fs::write(
+        dir.join("unknown_station.csv"),
+        daily_csv(&month_days(year, 5, 1, 10.0, 10.0))
+            .replace("TORONTO CITY", "ELSEWHERE")
+            .replace("\"-79.40\",\"43.67\"", "\"-123.10\",\"49.20\"")
+            .replace("\"6158355\"", "\"\""),
+    )
+    .unwrap();
+
+    let db = build_db();
+    db.insert_station(4607, &"Toronto City".to_string(), -79.4, 43.67, None, None)
+        .unwrap();
+    db.insert_station(5051, &"TORONTO ISLAND".to_string(), -79.39, 43.63, None, None)
+        .unwrap();
+
+    let aggregator = Aggregator::new(&db);
+    let report = aggregator.import_csv_dir(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report.files_read, 3);
+    assert_eq!(report.failed_files.len(), 1);
+    assert!(report.failed_files[0].0.ends_with("corrupt.csv"));
+    assert_eq!(report.unmatched_files.len(), 1);
+    assert!(report.unmatched_files[0].ends_with("unknown_station.csv"));
+    assert_eq!(report.stations_updated, 1);
+    assert_eq!(report.observations_stored, 31 + 31);
+
+    let data = db.get_data_by_station(4607).unwrap();
+    assert_eq!(data.len(), 1);
+    assert!(data[0].switch_to_summer.as_deref().unwrap().contains("-03-2"));
+    assert!(data[0].switch_to_winter.as_deref().unwrap().contains("-10-1"));
+    assert!(db.get_complete_months(4607).unwrap().contains(&(year, 10)));
+    assert!(db.get_data_by_station(5051).unwrap().is_empty());
+    assert_eq!(report.stations_created, 0);
+    assert_eq!(db.get_all_stations().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn import_csv_directory_into_an_empty_database() {
+    let year = Utc::now().year() - 1;
+    let dir = std::env::temp_dir().join(format!("tireswap-import-empty-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(
+        dir.join("en_climate_daily_ON_6158355_03_P1D.csv"),
+        daily_csv(&month_days(year, 3, 20, 2.0, 10.0)),
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("en_climate_daily_ON_6158355_10_P1D.csv"),
+        daily_csv(&month_days(year, 10, 14, 12.0, 3.0)),
+    )
+    .unwrap();
+
+    let db = build_db();
+    let report = Aggregator::new(&db).import_csv_dir(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report.files_read, 2);
+    assert!(report.unmatched_files.is_empty());
+    assert_eq!(report.stations_created, 1);
+    assert_eq!(report.stations_updated, 1);
+    assert_eq!(report.observations_stored, 31 + 31);
+
+    // Both files land on one station built from the CSV columns
+    let stations = db.get_all_stations().unwrap();
+    assert_eq!(stations.len(), 1);
+    let station = &stations[0];
+    assert_eq!(station.id, csv_station_id("6158355"));
+    assert_eq!(station.name, "TORONTO CITY");
+    assert!((station.lon_x + 79.40).abs() < 1e-9);
+    assert!((station.lat_y - 43.67).abs() < 1e-9);
+    assert_eq!(station.metadata.climate_id.as_deref(), Some("6158355"));
+    assert_eq!(
+        station.dly_first_date.as_deref(),
+        Some(format!("{}-03-01 00:00:00", year).as_str())
+    );
+    assert_eq!(
+        station.dly_last_date.as_deref(),
+        Some(format!("{}-10-31 00:00:00", year).as_str())
+    );
+    let data = db.get_data_by_station(station.id).unwrap();
+    assert_eq!(data.len(), 1);
+    assert!(data[0].switch_to_summer.as_deref().unwrap().contains("-03-2"));
+    assert!(data[0].switch_to_winter.as_deref().unwrap().contains("-10-1"));
+
+    // Once the catalogue lists the Climate ID, its station takes over the created one
+    // together with the imported data, without downloading it again
+    let server = MockServer::start();
+    let today = Utc::now().format("%Y-%m-%d 00:00:00").to_string();
+    server.mock(|when, then| {
+        when.method("GET")
+            .path("/collections/climate-stations/items");
+        then.status(200)
+            .header("content-type", "application/geo+json")
+            .body(format!(
+                r#"{{"type": "FeatureCollection", "features": [{}]}}"#,
+                station_feature(4607, "TORONTO CITY", &today)
+            ));
+    });
+    let aggregator = Aggregator::with_options(&db, options(&server));
+    aggregator.fetch_and_store_stations().await.unwrap();
+
+    let stations = db.get_all_stations().unwrap();
+    assert_eq!(stations.len(), 1);
+    assert_eq!(stations[0].id, 4607);
+    assert!(db.get_data_by_station(csv_station_id("6158355")).unwrap().is_empty());
+    assert!(db.get_complete_months(csv_station_id("6158355")).unwrap().is_empty());
+    let data = db.get_data_by_station(4607).unwrap();
+    assert_eq!(data.len(), 1);
+    assert!(data[0].switch_to_summer.as_deref().unwrap().contains("-03-2"));
+    let complete = db.get_complete_months(4607).unwrap();
+    assert!(complete.contains(&(year, 3)) && complete.contains(&(year, 10)));
+    let october = db
+        .get_daily_observations(4607, &format!("{}-10-01", year), &format!("{}-10-31", year))
+        .unwrap();
+    assert_eq!(october.len(), 31);
+}
+
+#[test]
+fn shrinking_the_history_window_drops_older_switch_dates() {
+    let this_year = Utc::now().year();
+    let dir = std::env::temp_dir().join(format!("tireswap-window-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    for year in [this_year - 10, this_year - 1] {
+        for (month, before, after) in [(3, 2.0, 10.0), (10, 12.0, 3.0)] {
+            std::fs::write(
+                dir.join(format!("{}_{:02}.csv", year, month)),
+                daily_csv(&month_days(year, month, 15, before, after)),
+            )
+            .unwrap();
+        }
+    }
+
+    let db = build_db();
+    db.insert_station(4607, &"Toronto City".to_string(), -79.4, 43.67, None, None)
+        .unwrap();
+    let with_history = |history_years| AggregatorOptions {
+        ingest: IngestConfig {
+            history_years,
+            ..IngestConfig::default()
+        },
+        ..AggregatorOptions::default()
+    };
+    Aggregator::with_options(&db, with_history(30))
+        .import_csv_dir(&dir)
+        .unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+    let years: Vec<i64> = db.get_data_by_station(4607).unwrap().iter().map(|d| d.year).collect();
+    assert_eq!(years, vec![(this_year - 1) as i64, (this_year - 10) as i64]);
+
+    // The year before the new window is no longer averaged into recommendations
+    Aggregator::with_options(&db, with_history(5))
+        .refresh_switch_dates(4607)
+        .unwrap();
+    let years: Vec<i64> = db.get_data_by_station(4607).unwrap().iter().map(|d| d.year).collect();
+    assert_eq!(years, vec![(this_year - 1) as i64]);
+}
+
+/// Build `.dly` TMAX and TMIN lines with one value per (date, mean temperature) in a month
+fn dly_month(ghcn_id: &str, days: &[(NaiveDate, f64)]) -> String {
+    let (year, month) = (days[0].0.year(), days[0].0.month());
+    ["TMAX", "TMIN"]
+        .iter()
+        .map(|element| {
+            let mut line = format!("{:<11}{}{:02}{}", ghcn_id, year, month, element);
+            for day in 1..=31 {
+                let value = match days.iter().find(|(date, _)| date.day() == day) {
+                    Some((_, mean)) if *element == "TMAX" => ((mean + 4.0) * 10.0) as i32,
+                    Some((_, mean)) => ((mean - 4.0) * 10.0) as i32,
+                    None => -9999,
+                };
+                line.push_str(&format!("{:>5}   ", value));
+            }
+            line
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[tokio::test]
+async fn import_ghcn_directory_registers_cross_border_stations() {
+    let year = Utc::now().year() - 1;
+    let dir = std::env::temp_dir().join(format!("tireswap-ghcn-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let all = dir.join("ghcnd_all");
+    std::fs::create_dir_all(&all).unwrap();
+    std::fs::write(
+        dir.join("ghcnd-stations.txt"),
+        "\
+USW00014822  42.2314  -83.3308  192.3 MI DETROIT METRO AP               GSN     72537
+USC00201234  42.0000  -83.5000  200.0 MI CLOSED LONG AGO
+",
+    )
+    .unwrap();
+    let detroit = [
+        dly_month("USW00014822", &month_days(year, 3, 20, 2.0, 10.0)),
+        dly_month("USW00014822", &month_days(year, 10, 14, 12.0, 3.0)),
+        // Outside the history window
+        dly_month("USW00014822", &month_days(1990, 3, 10, 2.0, 10.0)),
+    ]
+    .join("\n");
+    std::fs::write(all.join("USW00014822.dly"), detroit).unwrap();
+    std::fs::write(
+        all.join("USC00201234.dly"),
+        dly_month("USC00201234", &month_days(1950, 5, 1, 10.0, 10.0)),
+    )
+    .unwrap();
+    std::fs::write(
+        all.join("USW00099999.dly"),
+        dly_month("USW00099999", &month_days(year, 5, 1, 10.0, 10.0)),
+    )
+    .unwrap();
+    // A file mixing two stations and one that is not text are skipped, not fatal
+    std::fs::write(
+        all.join("USC00000001.dly"),
+        [
+            dly_month("USC00000001", &month_days(year, 5, 1, 10.0, 10.0)),
+            dly_month("USW00014822", &month_days(year, 5, 1, 10.0, 10.0)),
+        ]
+        .join("\n"),
+    )
+    .unwrap();
+    std::fs::write(all.join("USC00000002.dly"), [0xff, 0xfe, 0x00, 0x80]).unwrap();
+
+    let db = build_db();
+    db.insert_station(4716, &"WINDSOR A".to_string(), -82.96, 42.28, None, None)
+        .unwrap();
+    let aggregator = Aggregator::new(&db);
+    let report = aggregator.import_ghcn_dir(&dir).unwrap();
+    // Importing again replaces the station and its months in place
+    aggregator.import_ghcn_dir(&dir).unwrap();
+    std::fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(report.files_read, 4);
+    assert_eq!(report.failed_files.len(), 2);
+    assert!(report.failed_files[0].0.ends_with("USC00000001.dly"));
+    assert!(report.failed_files[0].1.contains("station USW00014822 in a file of USC00000001"));
+    assert!(report.failed_files[1].0.ends_with("USC00000002.dly"));
+    assert_eq!(report.unmatched_files.len(), 1);
+    assert!(report.unmatched_files[0].ends_with("USW00099999.dly"));
+    assert_eq!(report.files_without_recent_data, 1);
+    assert_eq!(report.stations_updated, 1);
+    assert_eq!(report.observations_stored, 31 + 31);
+
+    let id = backend::ghcn::station_id("USW00014822");
+    assert!(id < 0);
+    let stations = db.get_all_stations().unwrap();
+    assert_eq!(stations.len(), 2);
+    let station = stations.iter().find(|s| s.id == id).unwrap();
+    assert_eq!(station.name, "DETROIT METRO AP");
+    assert_eq!(station.dly_first_date.as_deref(), Some("1990-03-01 00:00:00"));
+    assert_eq!(station.metadata.source_name(), "ghcn");
+    assert_eq!(station.metadata.source_id.as_deref(), Some("USW00014822"));
+    assert_eq!(station.metadata.wmo_id, Some(72537));
+
+    let march = db
+        .get_daily_observations(id, &format!("{}-03-01", year), &format!("{}-03-31", year))
+        .unwrap();
+    assert_eq!(march.len(), 31);
+    assert_eq!(march[0].mean_temp, Some(2.0));
+    assert_eq!(march[0].max_temp, Some(6.0));
+    let data = db.get_data_by_station(id).unwrap();
+    assert_eq!(data.len(), 1);
+    assert!(data[0].switch_to_summer.as_deref().unwrap().contains("-03-2"));
+    assert!(data[0].switch_to_winter.as_deref().unwrap().contains("-10-1"));
+
+    // The imported station is found like an ECCC one
+    let finder = backend::nearest::NearestStationFinder::new(&db).unwrap();
+    let nearest = finder.find_k_nearest(42.25, -83.25, 1);
+    assert_eq!(nearest[0].id, id);
+
+    // An update from another source neither lists nor fetches it
+    let source = Arc::new(FakeSource {
+        year,
+        requests: std::sync::Mutex::new(Vec::new()),
+    });
+    let aggregator = Aggregator::with_source(&db, AggregatorOptions::default(), source.clone());
+    aggregator.update_all(false, RunTrigger::Cli, false).await;
+    assert_eq!(source.requests.lock().unwrap().len(), 1);
+    let after = db.get_data_by_station(id).unwrap();
+    assert_eq!(after.len(), 1);
+    assert_eq!(after[0].switch_to_summer, data[0].switch_to_summer);
+}
@@ -3,6 +3,7 @@ use httpmock::MockServer;
 use serde_json::Value;
 use tower::util::ServiceExt;
 
+use backend::analyzer::AnalyzerOptions;
 use backend::api::{create_router, AppState};
 use backend::db::Database;
 
@@ -15,6 +16,9 @@ fn build_state(base_url: String) -> AppState {
         geocode_client: reqwest::Client::new(),
         geocode_cache: std::sync::Arc::new(std::sync::Mutex::new(std::collections::HashMap::new())),
         geocode_base_url: base_url,
+        admin_token: None,
+        dem: None,
+        analyzer_options: AnalyzerOptions::default(),
     }
 }
 